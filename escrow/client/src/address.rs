@@ -0,0 +1,46 @@
+//! Addresses of the escrow program's accounts.  These mirror the `seeds` of
+//! the program's account constraints.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &escrow::ID).0
+}
+
+pub fn maker_profile_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"maker_profile", maker.as_ref()], &escrow::ID).0
+}
+
+pub fn mint_allowance_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint_allowance", mint.as_ref()], &escrow::ID).0
+}
+
+/// Address of the offer `maker` makes with `id`, which is the maker profile's
+/// `next_offer_id` at the time.
+pub fn offer_address(maker: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"offer", maker.as_ref(), id.to_le_bytes().as_ref()],
+        &escrow::ID,
+    )
+    .0
+}
+
+pub fn bundle_offer_address(maker: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"bundle_offer", maker.as_ref(), id.to_le_bytes().as_ref()],
+        &escrow::ID,
+    )
+    .0
+}
+
+/// Associated token account of `owner` for `mint`, which belongs to
+/// `token_program`.
+pub fn token_account_address(mint: &Pubkey, owner: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+/// The vault holding the token A of `offer`.
+pub fn vault_address(offer: &Pubkey, token_mint_a: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    token_account_address(token_mint_a, offer, token_program)
+}
@@ -0,0 +1,166 @@
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::Instruction};
+use escrow::{Config, MakerProfile, MintAllowance, Offer};
+
+use crate::{
+    config_address, decode_offer, fetch_account, fetch_account_with, instruction,
+    maker_profile_address, mint_allowance_address, AccountSource, Error,
+};
+
+/// Builds escrow instructions, fetching the accounts they depend on from an
+/// `AccountSource`.
+pub struct EscrowClient<S> {
+    source: S,
+}
+
+impl<S> EscrowClient<S> {
+    pub fn new(source: S) -> Self {
+        Self { source }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+}
+
+impl<S: AccountSource> EscrowClient<S> {
+    pub fn config(&self) -> Result<Config, Error<S::Error>> {
+        let address = config_address();
+        fetch_account(&self.source, &address, &escrow::ID)?.ok_or(Error::AccountNotFound(address))
+    }
+
+    /// The offer at `address`, see `decode_offer`.
+    pub fn offer(&self, address: &Pubkey) -> Result<Option<Offer>, Error<S::Error>> {
+        fetch_account_with(&self.source, address, &escrow::ID, decode_offer)
+    }
+
+    pub fn maker_profile(&self, maker: &Pubkey) -> Result<Option<MakerProfile>, Error<S::Error>> {
+        fetch_account(&self.source, &maker_profile_address(maker), &escrow::ID)
+    }
+
+    /// Id of the next offer `maker` makes, `0` before their first one.
+    pub fn next_offer_id(&self, maker: &Pubkey) -> Result<u64, Error<S::Error>> {
+        Ok(self
+            .maker_profile(maker)?
+            .map_or(0, |profile| profile.next_offer_id))
+    }
+
+    /// The token program `mint` belongs to, SPL Token or Token-2022.
+    pub fn token_program_of(&self, mint: &Pubkey) -> Result<Pubkey, Error<S::Error>> {
+        self.source
+            .get_account(mint)
+            .map_err(Error::Source)?
+            .map(|account| account.owner)
+            .ok_or(Error::AccountNotFound(*mint))
+    }
+
+    /// Address of the allowance for `mint`, if the admin has allowed it.
+    pub fn mint_allowance(&self, mint: &Pubkey) -> Result<Option<Pubkey>, Error<S::Error>> {
+        let address = mint_allowance_address(mint);
+        let allowance: Option<MintAllowance> = fetch_account(&self.source, &address, &escrow::ID)?;
+        Ok(allowance.map(|_| address))
+    }
+
+    /// A `make_offer` instruction for the next offer of `maker`, along with
+    /// the address of that offer.
+    pub fn make_offer(
+        &self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+        args: escrow::instruction::MakeOffer,
+    ) -> Result<(Pubkey, Instruction), Error<S::Error>> {
+        let mut accounts = instruction::make_offer_accounts(
+            maker,
+            self.next_offer_id(maker)?,
+            token_mint_a,
+            token_mint_b,
+            &self.token_program_of(token_mint_a)?,
+        );
+        accounts.token_mint_a_allowance = self.mint_allowance(token_mint_a)?;
+        accounts.token_mint_b_allowance = self.mint_allowance(token_mint_b)?;
+        let offer = accounts.offer;
+        Ok((offer, instruction::make_offer(accounts, args)))
+    }
+
+    pub fn take_offer_accounts(
+        &self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> Result<escrow::accounts::TakeOffer, Error<S::Error>> {
+        let state = self.offer(offer)?.ok_or(Error::AccountNotFound(*offer))?;
+        let mut accounts = instruction::take_offer_accounts(
+            taker,
+            offer,
+            &state,
+            &self.config()?.treasury,
+            &self.token_program_of(&state.token_mint_a)?,
+        );
+        accounts.token_mint_a_allowance = self.mint_allowance(&state.token_mint_a)?;
+        accounts.token_mint_b_allowance = self.mint_allowance(&state.token_mint_b)?;
+        Ok(accounts)
+    }
+
+    pub fn take_offer(
+        &self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+        max_token_b_amount: u64,
+    ) -> Result<Instruction, Error<S::Error>> {
+        Ok(instruction::take_offer(
+            self.take_offer_accounts(taker, offer)?,
+            max_token_b_amount,
+        ))
+    }
+
+    pub fn take_offer_partial(
+        &self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+        token_b_amount: u64,
+        min_token_a_amount: u64,
+    ) -> Result<Instruction, Error<S::Error>> {
+        Ok(instruction::take_offer_partial(
+            self.take_offer_accounts(taker, offer)?,
+            token_b_amount,
+            min_token_a_amount,
+        ))
+    }
+
+    /// A `take_offers` instruction sweeping `offers` of the pair, as listed
+    /// by `EscrowClient::offers`.
+    pub fn take_offers(
+        &self,
+        taker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+        offers: &[(Pubkey, Offer)],
+        max_token_b_amount: u64,
+        min_token_a_amount: u64,
+    ) -> Result<Instruction, Error<S::Error>> {
+        let mut accounts = instruction::take_offers_accounts(
+            taker,
+            token_mint_a,
+            token_mint_b,
+            &self.config()?.treasury,
+            &self.token_program_of(token_mint_a)?,
+        );
+        accounts.token_mint_a_allowance = self.mint_allowance(token_mint_a)?;
+        accounts.token_mint_b_allowance = self.mint_allowance(token_mint_b)?;
+        Ok(instruction::take_offers(
+            accounts,
+            offers,
+            max_token_b_amount,
+            min_token_a_amount,
+        ))
+    }
+
+    pub fn refund_offer(&self, offer: &Pubkey) -> Result<Instruction, Error<S::Error>> {
+        let state = self.offer(offer)?.ok_or(Error::AccountNotFound(*offer))?;
+        let accounts = instruction::refund_offer_accounts(
+            offer,
+            &state,
+            &self.token_program_of(&state.token_mint_a)?,
+        );
+        Ok(instruction::refund_offer(accounts))
+    }
+}
@@ -0,0 +1,138 @@
+//! Reading and decoding escrow accounts.
+
+use std::{collections::HashMap, convert::Infallible, fmt};
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode, prelude::Pubkey, AccountDeserialize, AnchorDeserialize,
+    Discriminator,
+};
+use escrow::{LegacyOffer, Offer, ANCHOR_DISCRIMINATOR};
+
+/// The parts of an on-chain account the client looks at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FetchedAccount {
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+/// Where the client reads accounts from.  `Ok(None)` means the account does
+/// not exist.
+pub trait AccountSource {
+    type Error;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<FetchedAccount>, Self::Error>;
+}
+
+impl<F, E> AccountSource for F
+where
+    F: Fn(&Pubkey) -> Result<Option<FetchedAccount>, E>,
+{
+    type Error = E;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<FetchedAccount>, E> {
+        self(address)
+    }
+}
+
+impl AccountSource for HashMap<Pubkey, FetchedAccount> {
+    type Error = Infallible;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<FetchedAccount>, Infallible> {
+        Ok(self.get(address).cloned())
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl AccountSource for solana_client::rpc_client::RpcClient {
+    type Error = solana_client::client_error::ClientError;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<FetchedAccount>, Self::Error> {
+        let response = self.get_account_with_commitment(address, self.commitment())?;
+        Ok(response.value.map(|account| FetchedAccount {
+            owner: account.owner,
+            data: account.data,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Source(E),
+    AccountNotFound(Pubkey),
+    // The account is not owned by the expected program, or its data does not
+    // decode as the expected type.
+    InvalidAccount(Pubkey, anchor_lang::error::Error),
+}
+
+impl<E: fmt::Display> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Source(error) => write!(f, "failed to fetch account: {error}"),
+            Error::AccountNotFound(address) => write!(f, "account {address} not found"),
+            Error::InvalidAccount(address, error) => {
+                write!(f, "account {address} is invalid: {error}")
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for Error<E> {}
+
+/// Decodes the data of an escrow account, checking its discriminator.
+pub fn decode_account<T: AccountDeserialize>(data: &[u8]) -> anchor_lang::Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Decodes an offer in the current layout.  Offers still in the
+/// `LegacyOffer` layout fail to decode until `migrate_offer` moves them; read
+/// those with `decode_legacy_offer`.
+pub fn decode_offer(data: &[u8]) -> anchor_lang::Result<Offer> {
+    decode_account(data)
+}
+
+/// Decodes an offer still in the `LegacyOffer` layout.  It does not hold how
+/// much token A is left, which is the balance of its vault.
+pub fn decode_legacy_offer(data: &[u8]) -> anchor_lang::Result<LegacyOffer> {
+    if !data.starts_with(&Offer::DISCRIMINATOR) {
+        return Err(AnchorErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    let mut data = &data[ANCHOR_DISCRIMINATOR..];
+    let legacy = LegacyOffer::deserialize(&mut data)
+        .map_err(|_| AnchorErrorCode::AccountDidNotDeserialize)?;
+    // Anything left over means the account is in some other layout.
+    if !data.is_empty() {
+        return Err(AnchorErrorCode::AccountDidNotDeserialize.into());
+    }
+    Ok(legacy)
+}
+
+/// Fetches the account at `address` and decodes it as a `T` owned by
+/// `owner`, or returns `None` if there is no account there.
+pub fn fetch_account<T: AccountDeserialize, S: AccountSource + ?Sized>(
+    source: &S,
+    address: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Option<T>, Error<S::Error>> {
+    fetch_account_with(source, address, owner, decode_account)
+}
+
+/// `fetch_account` with `decode` in place of `decode_account`.
+pub fn fetch_account_with<T, S: AccountSource + ?Sized>(
+    source: &S,
+    address: &Pubkey,
+    owner: &Pubkey,
+    decode: impl FnOnce(&[u8]) -> anchor_lang::Result<T>,
+) -> Result<Option<T>, Error<S::Error>> {
+    let Some(account) = source.get_account(address).map_err(Error::Source)? else {
+        return Ok(None);
+    };
+    if account.owner != *owner {
+        return Err(Error::InvalidAccount(
+            *address,
+            AnchorErrorCode::AccountOwnedByWrongProgram.into(),
+        ));
+    }
+    decode(&account.data)
+        .map(Some)
+        .map_err(|error| Error::InvalidAccount(*address, error))
+}
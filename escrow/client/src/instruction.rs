@@ -0,0 +1,239 @@
+//! Escrow instructions built from accounts the caller already knows about.
+//! `EscrowClient` fetches what these need instead.
+//!
+//! The `*_accounts` helpers leave out the mint allowances, which only mints
+//! allowed by the admin need.  Instructions moving a mint with a transfer hook
+//! also need the hook's accounts appended to `Instruction::accounts`.
+
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::{instruction::Instruction, system_program},
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::spl_associated_token_account;
+use escrow::{LegacyOffer, Offer, Pricing};
+
+use crate::{config_address, maker_profile_address, offer_address, token_account_address};
+
+pub fn escrow_instruction(
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Accounts for `make_offer`, `make_oracle_offer`, `make_dutch_offer` and
+/// `make_vesting_offer`, for the offer `maker` makes with `id`.  Oracle offers
+/// also need `price_feed` set.
+pub fn make_offer_accounts(
+    maker: &Pubkey,
+    id: u64,
+    token_mint_a: &Pubkey,
+    token_mint_b: &Pubkey,
+    token_program: &Pubkey,
+) -> escrow::accounts::MakeOffer {
+    let offer = offer_address(maker, id);
+    escrow::accounts::MakeOffer {
+        maker: *maker,
+        maker_profile: maker_profile_address(maker),
+        token_mint_a: *token_mint_a,
+        token_mint_b: *token_mint_b,
+        maker_token_account_a: token_account_address(token_mint_a, maker, token_program),
+        offer,
+        vault: token_account_address(token_mint_a, &offer, token_program),
+        price_feed: None,
+        token_mint_a_allowance: None,
+        token_mint_b_allowance: None,
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: *token_program,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn make_offer(
+    accounts: escrow::accounts::MakeOffer,
+    args: escrow::instruction::MakeOffer,
+) -> Instruction {
+    escrow_instruction(accounts, args)
+}
+
+/// Accounts for `take_offer` and `take_offer_partial`, derived from `state`,
+/// the data stored in `offer`.
+pub fn take_offer_accounts(
+    taker: &Pubkey,
+    offer: &Pubkey,
+    state: &Offer,
+    treasury: &Pubkey,
+    token_program: &Pubkey,
+) -> escrow::accounts::TakeOffer {
+    escrow::accounts::TakeOffer {
+        taker: *taker,
+        maker: state.maker,
+        maker_profile: maker_profile_address(&state.maker),
+        token_mint_a: state.token_mint_a,
+        token_mint_b: state.token_mint_b,
+        taker_token_account_a: token_account_address(&state.token_mint_a, taker, token_program),
+        taker_token_account_b: token_account_address(&state.token_mint_b, taker, token_program),
+        maker_token_account_b: token_account_address(
+            &state.token_mint_b,
+            &state.maker,
+            token_program,
+        ),
+        config: config_address(),
+        treasury: *treasury,
+        treasury_token_account_b: token_account_address(
+            &state.token_mint_b,
+            treasury,
+            token_program,
+        ),
+        offer: *offer,
+        vault: token_account_address(&state.token_mint_a, offer, token_program),
+        price_feed: match state.pricing {
+            Pricing::Fixed | Pricing::Dutch(_) => None,
+            Pricing::Oracle { price_feed, .. } => Some(price_feed),
+        },
+        token_mint_a_allowance: None,
+        token_mint_b_allowance: None,
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: *token_program,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn take_offer(accounts: escrow::accounts::TakeOffer, max_token_b_amount: u64) -> Instruction {
+    escrow_instruction(
+        accounts,
+        escrow::instruction::TakeOffer { max_token_b_amount },
+    )
+}
+
+pub fn take_offer_partial(
+    accounts: escrow::accounts::TakeOffer,
+    token_b_amount: u64,
+    min_token_a_amount: u64,
+) -> Instruction {
+    escrow_instruction(
+        accounts,
+        escrow::instruction::TakeOfferPartial {
+            token_b_amount,
+            min_token_a_amount,
+        },
+    )
+}
+
+/// Accounts for `take_offers` shared by every offer of the pair.
+pub fn take_offers_accounts(
+    taker: &Pubkey,
+    token_mint_a: &Pubkey,
+    token_mint_b: &Pubkey,
+    treasury: &Pubkey,
+    token_program: &Pubkey,
+) -> escrow::accounts::TakeOffers {
+    escrow::accounts::TakeOffers {
+        taker: *taker,
+        token_mint_a: *token_mint_a,
+        token_mint_b: *token_mint_b,
+        taker_token_account_a: token_account_address(token_mint_a, taker, token_program),
+        taker_token_account_b: token_account_address(token_mint_b, taker, token_program),
+        config: config_address(),
+        treasury: *treasury,
+        treasury_token_account_b: token_account_address(token_mint_b, treasury, token_program),
+        token_mint_a_allowance: None,
+        token_mint_b_allowance: None,
+        associated_token_program: spl_associated_token_account::ID,
+        token_program: *token_program,
+        system_program: system_program::ID,
+    }
+}
+
+/// `take_offers` for `offers`, filled in the order given, with the accounts
+/// of each offer appended as the program expects them.
+pub fn take_offers(
+    accounts: escrow::accounts::TakeOffers,
+    offers: &[(Pubkey, Offer)],
+    max_token_b_amount: u64,
+    min_token_a_amount: u64,
+) -> Instruction {
+    let token_program = accounts.token_program;
+    let mut instruction = escrow_instruction(
+        accounts,
+        escrow::instruction::TakeOffers {
+            max_token_b_amount,
+            min_token_a_amount,
+        },
+    );
+    for (offer, state) in offers {
+        let price_feed = match state.pricing {
+            Pricing::Fixed | Pricing::Dutch(_) => escrow::ID,
+            Pricing::Oracle { price_feed, .. } => price_feed,
+        };
+        instruction.accounts.extend([
+            AccountMeta::new(*offer, false),
+            AccountMeta::new(
+                token_account_address(&state.token_mint_a, offer, &token_program),
+                false,
+            ),
+            AccountMeta::new(state.maker, false),
+            AccountMeta::new(maker_profile_address(&state.maker), false),
+            AccountMeta::new(
+                token_account_address(&state.token_mint_b, &state.maker, &token_program),
+                false,
+            ),
+            AccountMeta::new_readonly(price_feed, false),
+        ]);
+    }
+    instruction
+}
+
+/// `migrate_offer` for `offer`, paid for by `payer`.  `legacy` is the offer
+/// as read with `decode_legacy_offer`.
+pub fn migrate_offer(
+    payer: &Pubkey,
+    offer: &Pubkey,
+    legacy: &LegacyOffer,
+    token_program: &Pubkey,
+) -> Instruction {
+    escrow_instruction(
+        escrow::accounts::MigrateOffer {
+            payer: *payer,
+            maker: legacy.maker,
+            maker_profile: maker_profile_address(&legacy.maker),
+            offer: *offer,
+            token_mint_a: legacy.token_mint_a,
+            vault: token_account_address(&legacy.token_mint_a, offer, token_program),
+            token_program: *token_program,
+            system_program: system_program::ID,
+        },
+        escrow::instruction::MigrateOffer {},
+    )
+}
+
+pub fn refund_offer_accounts(
+    offer: &Pubkey,
+    state: &Offer,
+    token_program: &Pubkey,
+) -> escrow::accounts::RefundOffer {
+    escrow::accounts::RefundOffer {
+        maker: state.maker,
+        maker_profile: maker_profile_address(&state.maker),
+        token_mint_a: state.token_mint_a,
+        maker_token_account_a: token_account_address(
+            &state.token_mint_a,
+            &state.maker,
+            token_program,
+        ),
+        offer: *offer,
+        vault: token_account_address(&state.token_mint_a, offer, token_program),
+        token_program: *token_program,
+        associated_token_program: spl_associated_token_account::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn refund_offer(accounts: escrow::accounts::RefundOffer) -> Instruction {
+    escrow_instruction(accounts, escrow::instruction::RefundOffer {})
+}
@@ -0,0 +1,21 @@
+//! Builds escrow instructions and reads escrow accounts from off-chain Rust
+//! code.
+//!
+//! `EscrowClient` works against any `AccountSource`.  With the `rpc` feature,
+//! `solana_client::rpc_client::RpcClient` is one; otherwise accounts can come
+//! from a closure or a `HashMap`.  The lower-level helpers in `address` and
+//! `instruction` need no accounts at all, and `query` lists open offers by
+//! maker or mint pair.
+
+pub mod address;
+pub mod client;
+pub mod fetch;
+pub mod instruction;
+pub mod query;
+
+pub use address::*;
+pub use client::*;
+pub use fetch::*;
+pub use query::*;
+
+pub use escrow::{self, AmountBasis, Config, MakerProfile, MintAllowance, Offer, Pricing};
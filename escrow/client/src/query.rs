@@ -0,0 +1,193 @@
+//! Listing open offers with `getProgramAccounts`, filtered on the fixed part
+//! of the `Offer` layout.  See `Offer::MAKER_OFFSET` and the offsets next to
+//! it.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use anchor_lang::{prelude::Pubkey, Discriminator};
+use escrow::Offer;
+
+use crate::{decode_offer, EscrowClient, FetchedAccount};
+
+/// Matches accounts whose data holds `bytes` at `offset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memcmp {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl Memcmp {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.get(self.offset..)
+            .is_some_and(|data| data.starts_with(&self.bytes))
+    }
+}
+
+/// Where the client lists program accounts from.
+pub trait ProgramAccountSource {
+    type Error;
+
+    /// The accounts owned by `program_id` matching every filter.
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[Memcmp],
+    ) -> Result<Vec<(Pubkey, FetchedAccount)>, Self::Error>;
+}
+
+impl ProgramAccountSource for HashMap<Pubkey, FetchedAccount> {
+    type Error = std::convert::Infallible;
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[Memcmp],
+    ) -> Result<Vec<(Pubkey, FetchedAccount)>, Self::Error> {
+        Ok(self
+            .iter()
+            .filter(|(_, account)| account.owner == *program_id)
+            .filter(|(_, account)| filters.iter().all(|filter| filter.matches(&account.data)))
+            .map(|(address, account)| (*address, account.clone()))
+            .collect())
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl ProgramAccountSource for solana_client::rpc_client::RpcClient {
+    type Error = solana_client::client_error::ClientError;
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[Memcmp],
+    ) -> Result<Vec<(Pubkey, FetchedAccount)>, Self::Error> {
+        use solana_account_decoder::UiAccountEncoding;
+        use solana_client::{
+            rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+            rpc_filter::{self, RpcFilterType},
+        };
+
+        let filters = filters
+            .iter()
+            .map(|filter| {
+                RpcFilterType::Memcmp(rpc_filter::Memcmp::new_base58_encoded(
+                    filter.offset,
+                    &filter.bytes,
+                ))
+            })
+            .collect();
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                // Offers are too large for the default base58 encoding.
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self.get_program_accounts_with_config(program_id, config)?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, account)| {
+                let account = FetchedAccount {
+                    owner: account.owner,
+                    data: account.data,
+                };
+                (address, account)
+            })
+            .collect())
+    }
+}
+
+/// Which offers to list.  Fields left as `None` match any offer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OfferQuery {
+    pub maker: Option<Pubkey>,
+    pub token_mint_a: Option<Pubkey>,
+    pub token_mint_b: Option<Pubkey>,
+}
+
+impl OfferQuery {
+    pub fn by_maker(maker: Pubkey) -> Self {
+        Self {
+            maker: Some(maker),
+            ..Self::default()
+        }
+    }
+
+    /// Offers of `token_mint_a` for `token_mint_b`.
+    pub fn by_pair(token_mint_a: Pubkey, token_mint_b: Pubkey) -> Self {
+        Self {
+            token_mint_a: Some(token_mint_a),
+            token_mint_b: Some(token_mint_b),
+            ..Self::default()
+        }
+    }
+
+    /// Filters for `getProgramAccounts`.  The first one matches the `Offer`
+    /// discriminator, so other escrow accounts are never returned.
+    pub fn filters(&self) -> Vec<Memcmp> {
+        let mut filters = vec![Memcmp {
+            offset: 0,
+            bytes: Offer::DISCRIMINATOR.to_vec(),
+        }];
+        let fields = [
+            (Offer::MAKER_OFFSET, self.maker),
+            (Offer::TOKEN_MINT_A_OFFSET, self.token_mint_a),
+            (Offer::TOKEN_MINT_B_OFFSET, self.token_mint_b),
+        ];
+        for (offset, value) in fields {
+            if let Some(value) = value {
+                filters.push(Memcmp {
+                    offset,
+                    bytes: value.to_bytes().to_vec(),
+                });
+            }
+        }
+        filters
+    }
+}
+
+/// Orders offers by the price of what is left of them, token B asked per
+/// token A in base units, cheapest first.  Oracle-priced and Dutch auction
+/// offers are compared at the price they were last quoted at.
+pub fn cmp_implied_price(a: &Offer, b: &Offer) -> Ordering {
+    let a_price = u128::from(a.token_b_remaining_amount) * u128::from(b.token_a_remaining_amount);
+    let b_price = u128::from(b.token_b_remaining_amount) * u128::from(a.token_a_remaining_amount);
+    a_price.cmp(&b_price)
+}
+
+/// The offers matching `query`, cheapest first.  Offers still in the
+/// `LegacyOffer` layout are left out until they are migrated.
+pub fn fetch_offers<S: ProgramAccountSource + ?Sized>(
+    source: &S,
+    query: &OfferQuery,
+) -> Result<Vec<(Pubkey, Offer)>, crate::Error<S::Error>> {
+    let accounts = source
+        .get_program_accounts(&escrow::ID, &query.filters())
+        .map_err(crate::Error::Source)?;
+    let mut offers = accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            decode_offer(&account.data)
+                .ok()
+                .map(|offer| (address, offer))
+        })
+        .collect::<Vec<_>>();
+    // Ties are broken by address so the order does not depend on the RPC.
+    offers.sort_by(|(a_address, a), (b_address, b)| {
+        cmp_implied_price(a, b).then_with(|| a_address.cmp(b_address))
+    });
+    Ok(offers)
+}
+
+impl<S: ProgramAccountSource> EscrowClient<S> {
+    /// The offers matching `query`, cheapest first.
+    pub fn offers(
+        &self,
+        query: &OfferQuery,
+    ) -> Result<Vec<(Pubkey, Offer)>, crate::Error<S::Error>> {
+        fetch_offers(self.source(), query)
+    }
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use std::collections::HashMap;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode, prelude::Pubkey, solana_program::system_program,
+    Discriminator, InstructionData,
+};
+use anchor_spl::{token::spl_token, token_2022::spl_token_2022};
+use escrow_client::{
+    config_address, decode_legacy_offer, decode_offer, maker_profile_address,
+    mint_allowance_address, offer_address, token_account_address, vault_address, AmountBasis,
+    Config, Error, EscrowClient, FetchedAccount, MakerProfile, MintAllowance, Offer, Pricing,
+};
+
+use common::*;
+
+fn make_offer_args() -> escrow::instruction::MakeOffer {
+    escrow::instruction::MakeOffer {
+        token_a_offered_amount: 10_000_000,
+        token_b_wanted_amount: 50_000_000,
+        token_b_amount_basis: AmountBasis::Gross,
+        expires_at: None,
+        allowed_taker: None,
+    }
+}
+
+#[test]
+fn make_offer_uses_the_next_id_of_the_maker_profile() {
+    let maker = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let mut accounts = HashMap::from([
+        (usdc, mint(&spl_token_2022::ID)),
+        (wif, mint(&spl_token_2022::ID)),
+    ]);
+
+    let client = EscrowClient::new(accounts.clone());
+    let (first, instruction) = client
+        .make_offer(&maker, &usdc, &wif, make_offer_args())
+        .unwrap();
+    assert_eq!(first, offer_address(&maker, 0));
+    assert_eq!(instruction.program_id, escrow::ID);
+    assert_eq!(instruction.data, make_offer_args().data());
+
+    let profile = MakerProfile {
+        maker,
+        next_offer_id: 7,
+        open_offers: 2,
+        filled_offers: 5,
+        volume: 0,
+        bump: 255,
+    };
+    accounts.insert(maker_profile_address(&maker), escrow_account(&profile));
+    accounts.insert(
+        mint_allowance_address(&wif),
+        escrow_account(&MintAllowance {
+            mint: wif,
+            bump: 255,
+        }),
+    );
+
+    let client = EscrowClient::new(accounts);
+    let (offer, instruction) = client
+        .make_offer(&maker, &usdc, &wif, make_offer_args())
+        .unwrap();
+    assert_eq!(offer, offer_address(&maker, 7));
+
+    let keys: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            maker,
+            usdc,
+            wif,
+            token_account_address(&usdc, &maker, &spl_token_2022::ID),
+            maker_profile_address(&maker),
+            offer,
+            vault_address(&offer, &usdc, &spl_token_2022::ID),
+            // Anchor marks a missing optional account with the program id.
+            escrow::ID,
+            escrow::ID,
+            mint_allowance_address(&wif),
+            anchor_spl::associated_token::ID,
+            spl_token_2022::ID,
+            system_program::ID,
+        ]
+    );
+}
+
+#[test]
+fn take_offer_derives_its_accounts_from_the_offer() {
+    let maker = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let price_feed = Pubkey::new_unique();
+    let address = offer_address(&maker, 4);
+    let state = offer(
+        maker,
+        usdc,
+        wif,
+        Pricing::Oracle {
+            price_feed,
+            feed_id: [7; 32],
+            spread_basis_points: 0,
+        },
+    );
+    let config = Config {
+        admin: Pubkey::new_unique(),
+        fee_basis_points: 100,
+        treasury,
+        bump: 255,
+    };
+    let client = EscrowClient::new(HashMap::from([
+        (usdc, mint(&spl_token::ID)),
+        (wif, mint(&spl_token::ID)),
+        (address, escrow_account(&state)),
+        (config_address(), escrow_account(&config)),
+    ]));
+
+    let accounts = client.take_offer_accounts(&taker, &address).unwrap();
+    assert_eq!(accounts.maker, maker);
+    assert_eq!(accounts.maker_profile, maker_profile_address(&maker));
+    assert_eq!(
+        accounts.taker_token_account_a,
+        token_account_address(&usdc, &taker, &spl_token::ID)
+    );
+    assert_eq!(
+        accounts.maker_token_account_b,
+        token_account_address(&wif, &maker, &spl_token::ID)
+    );
+    assert_eq!(accounts.treasury, treasury);
+    assert_eq!(
+        accounts.treasury_token_account_b,
+        token_account_address(&wif, &treasury, &spl_token::ID)
+    );
+    assert_eq!(
+        accounts.vault,
+        vault_address(&address, &usdc, &spl_token::ID)
+    );
+    assert_eq!(accounts.price_feed, Some(price_feed));
+    assert_eq!(accounts.token_mint_a_allowance, None);
+    assert_eq!(accounts.token_program, spl_token::ID);
+
+    let instruction = client
+        .take_offer_partial(&taker, &address, 20_000_000, 4_000_000)
+        .unwrap();
+    assert_eq!(
+        instruction.data,
+        escrow::instruction::TakeOfferPartial {
+            token_b_amount: 20_000_000,
+            min_token_a_amount: 4_000_000,
+        }
+        .data()
+    );
+
+    let instruction = client.refund_offer(&address).unwrap();
+    assert_eq!(instruction.accounts[0].pubkey, maker);
+    assert!(instruction.accounts[0].is_signer);
+}
+
+#[test]
+fn offers_decode_from_account_data() {
+    let state = offer(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pricing::Fixed,
+    );
+    let account = escrow_account(&state);
+    assert_eq!(&account.data[..8], &Offer::DISCRIMINATOR);
+
+    let decoded = decode_offer(&account.data).unwrap();
+    assert_eq!(decoded.id, state.id);
+    assert_eq!(decoded.maker, state.maker);
+    assert_eq!(decoded.token_b_remaining_amount, 50_000_000);
+    assert!(decode_offer(&account.data[..40]).is_err());
+}
+
+#[test]
+fn legacy_offers_decode_only_as_legacy_offers() {
+    let maker = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let account = legacy_offer_account(4, maker, usdc, wif);
+
+    assert!(decode_offer(&account.data).is_err());
+    let legacy = decode_legacy_offer(&account.data).unwrap();
+    assert_eq!(legacy.id, 4);
+    assert_eq!(legacy.maker, maker);
+    assert_eq!(legacy.token_mint_a, usdc);
+    assert_eq!(legacy.token_b_wanted_amount, 50_000_000);
+
+    let current = escrow_account(&offer(maker, usdc, wif, Pricing::Fixed));
+    assert!(decode_legacy_offer(&current.data).is_err());
+
+    let offer = offer_address(&maker, 4);
+    let payer = Pubkey::new_unique();
+    let instruction =
+        escrow_client::instruction::migrate_offer(&payer, &offer, &legacy, &spl_token::ID);
+    assert_eq!(
+        instruction.data,
+        escrow::instruction::MigrateOffer {}.data()
+    );
+    assert!(instruction.accounts[0].is_signer);
+    assert_eq!(instruction.accounts[1].pubkey, maker);
+    assert_eq!(
+        instruction.accounts[5].pubkey,
+        token_account_address(&usdc, &offer, &spl_token::ID)
+    );
+}
+
+#[test]
+fn fetching_checks_the_owner_and_the_account_type() {
+    let maker = Pubkey::new_unique();
+    let address = offer_address(&maker, 0);
+    let profile = MakerProfile {
+        maker,
+        next_offer_id: 1,
+        open_offers: 1,
+        filled_offers: 0,
+        volume: 0,
+        bump: 255,
+    };
+    let mut not_an_offer = escrow_account(&profile);
+
+    let client = EscrowClient::new(HashMap::from([(address, not_an_offer.clone())]));
+    assert!(matches!(
+        client.offer(&address),
+        Err(Error::InvalidAccount(invalid, error))
+            if invalid == address && error == AnchorErrorCode::AccountDiscriminatorMismatch.into()
+    ));
+
+    not_an_offer.owner = system_program::ID;
+    let client = EscrowClient::new(HashMap::from([(address, not_an_offer)]));
+    assert!(matches!(
+        client.offer(&address),
+        Err(Error::InvalidAccount(_, error))
+            if error == AnchorErrorCode::AccountOwnedByWrongProgram.into()
+    ));
+
+    // Any closure can serve as the source.
+    let client = EscrowClient::new(|_: &Pubkey| Ok::<_, String>(None));
+    assert!(matches!(client.offer(&address), Ok(None)));
+    assert!(matches!(
+        client.refund_offer(&address),
+        Err(Error::AccountNotFound(missing)) if missing == address
+    ));
+    let client = EscrowClient::new(|_: &Pubkey| Err::<Option<FetchedAccount>, _>("offline"));
+    assert!(matches!(client.config(), Err(Error::Source("offline"))));
+}
@@ -0,0 +1,62 @@
+// Each test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
+use anchor_lang::{prelude::Pubkey, AccountSerialize, Discriminator};
+use escrow_client::{AmountBasis, FetchedAccount, Offer, Pricing};
+
+pub fn escrow_account(account: &impl AccountSerialize) -> FetchedAccount {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    FetchedAccount {
+        owner: escrow::ID,
+        data,
+    }
+}
+
+pub fn mint(token_program: &Pubkey) -> FetchedAccount {
+    FetchedAccount {
+        owner: *token_program,
+        data: vec![0; 82],
+    }
+}
+
+pub fn offer(maker: Pubkey, token_mint_a: Pubkey, token_mint_b: Pubkey, pricing: Pricing) -> Offer {
+    Offer {
+        id: 4,
+        maker,
+        token_mint_a,
+        token_mint_b,
+        token_b_wanted_amount: 50_000_000,
+        token_a_remaining_amount: 10_000_000,
+        token_b_remaining_amount: 50_000_000,
+        expires_at: 0,
+        version: Offer::VERSION,
+        allowed_taker: None,
+        pricing,
+        token_b_amount_basis: AmountBasis::Gross,
+        bump: 255,
+        vesting: None,
+        nft: None,
+        reserved: [0; escrow::OFFER_RESERVED_SPACE],
+    }
+}
+
+/// An offer as the first deployment of the program stored it.
+pub fn legacy_offer_account(
+    id: u64,
+    maker: Pubkey,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+) -> FetchedAccount {
+    let mut data = Offer::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&id.to_le_bytes());
+    data.extend_from_slice(maker.as_ref());
+    data.extend_from_slice(token_mint_a.as_ref());
+    data.extend_from_slice(token_mint_b.as_ref());
+    data.extend_from_slice(&50_000_000u64.to_le_bytes());
+    data.push(255);
+    FetchedAccount {
+        owner: escrow::ID,
+        data,
+    }
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use std::{cell::RefCell, collections::HashMap, convert::Infallible};
+
+use anchor_lang::{prelude::Pubkey, Discriminator};
+use anchor_spl::token::spl_token;
+use escrow_client::{
+    config_address, maker_profile_address, offer_address, token_account_address, AccountSource,
+    Config, EscrowClient, FetchedAccount, MakerProfile, Memcmp, Offer, OfferQuery, Pricing,
+    ProgramAccountSource,
+};
+
+use common::*;
+
+/// Answers `getProgramAccounts` from a fixed set of accounts, keeping the
+/// filters of every request.
+#[derive(Default)]
+struct MockRpc {
+    accounts: HashMap<Pubkey, FetchedAccount>,
+    requests: RefCell<Vec<Vec<Memcmp>>>,
+}
+
+impl MockRpc {
+    fn add_offer(&mut self, offer: &Offer) -> Pubkey {
+        let address = offer_address(&offer.maker, offer.id);
+        self.accounts.insert(address, escrow_account(offer));
+        address
+    }
+}
+
+impl AccountSource for MockRpc {
+    type Error = Infallible;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<FetchedAccount>, Infallible> {
+        self.accounts.get_account(address)
+    }
+}
+
+impl ProgramAccountSource for MockRpc {
+    type Error = Infallible;
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[Memcmp],
+    ) -> Result<Vec<(Pubkey, FetchedAccount)>, Infallible> {
+        self.requests.borrow_mut().push(filters.to_vec());
+        self.accounts.get_program_accounts(program_id, filters)
+    }
+}
+
+fn priced_offer(
+    maker: Pubkey,
+    id: u64,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+    token_a_remaining_amount: u64,
+    token_b_remaining_amount: u64,
+) -> Offer {
+    Offer {
+        id,
+        token_a_remaining_amount,
+        token_b_remaining_amount,
+        ..offer(maker, token_mint_a, token_mint_b, Pricing::Fixed)
+    }
+}
+
+#[test]
+fn filtered_fields_sit_at_the_documented_offsets() {
+    let maker = Pubkey::new_unique();
+    let token_mint_a = Pubkey::new_unique();
+    let token_mint_b = Pubkey::new_unique();
+    let mut state = offer(
+        maker,
+        token_mint_a,
+        token_mint_b,
+        Pricing::Oracle {
+            price_feed: Pubkey::new_unique(),
+            feed_id: [7; 32],
+            spread_basis_points: -50,
+        },
+    );
+    state.allowed_taker = Some(Pubkey::new_unique());
+    let data = escrow_account(&state).data;
+
+    assert_eq!(&data[..8], &Offer::DISCRIMINATOR);
+    assert_eq!(
+        &data[Offer::MAKER_OFFSET..Offer::MAKER_OFFSET + 32],
+        maker.as_ref()
+    );
+    assert_eq!(
+        &data[Offer::TOKEN_MINT_A_OFFSET..Offer::TOKEN_MINT_A_OFFSET + 32],
+        token_mint_a.as_ref()
+    );
+    assert_eq!(
+        &data[Offer::TOKEN_MINT_B_OFFSET..Offer::TOKEN_MINT_B_OFFSET + 32],
+        token_mint_b.as_ref()
+    );
+}
+
+#[test]
+fn offers_for_a_pair_are_listed_cheapest_first() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let mut rpc = MockRpc::default();
+
+    // 5, 4 and 6 WIF per USDC, partly filled or not.
+    let five = rpc.add_offer(&priced_offer(alice, 0, usdc, wif, 10_000_000, 50_000_000));
+    let four = rpc.add_offer(&priced_offer(bob, 0, usdc, wif, 3_000_000, 12_000_000));
+    let six = rpc.add_offer(&priced_offer(alice, 1, usdc, wif, 1, 6));
+    // The other way round, and another account type of the escrow program.
+    rpc.add_offer(&priced_offer(bob, 1, wif, usdc, 50_000_000, 10_000_000));
+    let profile = MakerProfile {
+        maker: alice,
+        next_offer_id: 2,
+        open_offers: 2,
+        filled_offers: 0,
+        volume: 0,
+        bump: 255,
+    };
+    rpc.accounts
+        .insert(maker_profile_address(&alice), escrow_account(&profile));
+
+    let client = EscrowClient::new(rpc);
+    let offers = client.offers(&OfferQuery::by_pair(usdc, wif)).unwrap();
+    let addresses: Vec<Pubkey> = offers.iter().map(|(address, _)| *address).collect();
+    assert_eq!(addresses, [four, five, six]);
+    assert_eq!(offers[0].1.maker, bob);
+
+    assert_eq!(
+        client.source().requests.borrow()[0],
+        [
+            Memcmp {
+                offset: 0,
+                bytes: Offer::DISCRIMINATOR.to_vec(),
+            },
+            Memcmp {
+                offset: Offer::TOKEN_MINT_A_OFFSET,
+                bytes: usdc.to_bytes().to_vec(),
+            },
+            Memcmp {
+                offset: Offer::TOKEN_MINT_B_OFFSET,
+                bytes: wif.to_bytes().to_vec(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn offers_of_a_maker_span_every_pair() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let bonk = Pubkey::new_unique();
+    let mut rpc = MockRpc::default();
+
+    let wif_for_usdc = rpc.add_offer(&priced_offer(alice, 0, wif, usdc, 10, 20));
+    let usdc_for_bonk = rpc.add_offer(&priced_offer(alice, 1, usdc, bonk, 10, 10));
+    rpc.add_offer(&priced_offer(bob, 0, usdc, wif, 10, 1));
+    // Not listed until it is migrated.
+    rpc.accounts.insert(
+        offer_address(&alice, 9),
+        legacy_offer_account(9, alice, usdc, wif),
+    );
+
+    let client = EscrowClient::new(rpc);
+    let offers = client.offers(&OfferQuery::by_maker(alice)).unwrap();
+    let addresses: Vec<Pubkey> = offers.iter().map(|(address, _)| *address).collect();
+    assert_eq!(addresses, [usdc_for_bonk, wif_for_usdc]);
+
+    let filters = client.source().requests.borrow()[0].clone();
+    assert_eq!(filters.len(), 2);
+    assert_eq!(filters[1].offset, Offer::MAKER_OFFSET);
+
+    // Everything, when nothing is filtered on.
+    let offers = client.offers(&OfferQuery::default()).unwrap();
+    assert_eq!(offers.len(), 3);
+}
+
+#[test]
+fn listed_offers_can_be_swept_in_one_instruction() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    let wif = Pubkey::new_unique();
+    let price_feed = Pubkey::new_unique();
+    let mut rpc = MockRpc::default();
+
+    let fixed = rpc.add_offer(&priced_offer(alice, 0, usdc, wif, 10, 60));
+    let oracle = rpc.add_offer(&Offer {
+        pricing: Pricing::Oracle {
+            price_feed,
+            feed_id: [7; 32],
+            spread_basis_points: 0,
+        },
+        ..priced_offer(bob, 0, usdc, wif, 10, 50)
+    });
+    let config = Config {
+        admin: Pubkey::new_unique(),
+        fee_basis_points: 0,
+        treasury: Pubkey::new_unique(),
+        bump: 255,
+    };
+    rpc.accounts
+        .insert(config_address(), escrow_account(&config));
+    rpc.accounts.insert(usdc, mint(&spl_token::ID));
+    rpc.accounts.insert(wif, mint(&spl_token::ID));
+
+    let client = EscrowClient::new(rpc);
+    let offers = client.offers(&OfferQuery::by_pair(usdc, wif)).unwrap();
+    let instruction = client
+        .take_offers(&taker, &usdc, &wif, &offers, 100, 0)
+        .unwrap();
+
+    let swept: Vec<Pubkey> = instruction.accounts[13..]
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        swept,
+        [
+            oracle,
+            token_account_address(&usdc, &oracle, &spl_token::ID),
+            bob,
+            maker_profile_address(&bob),
+            token_account_address(&wif, &bob, &spl_token::ID),
+            price_feed,
+            fixed,
+            token_account_address(&usdc, &fixed, &spl_token::ID),
+            alice,
+            maker_profile_address(&alice),
+            token_account_address(&wif, &alice, &spl_token::ID),
+            escrow::ID,
+        ]
+    );
+    assert!(!instruction.accounts[18].is_writable);
+}
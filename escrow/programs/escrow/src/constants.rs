@@ -3,3 +3,32 @@ use anchor_lang::prelude::*;
 #[constant]
 pub const SEED: &str = "anchor";
 pub const ANCHOR_DISCRIMINATOR: usize = 8;
+
+pub const BASIS_POINTS_DENOMINATOR: u64 = 10_000;
+// Upper bound for `Config::fee_basis_points`, 10%.
+#[constant]
+pub const MAX_FEE_BASIS_POINTS: u16 = 1_000;
+
+// Stored as `token_mint_a` or `token_mint_b` of an offer whose leg is native
+// SOL.  No token mint can live at the System program's address.
+#[constant]
+pub const NATIVE_SOL_MINT: Pubkey = pubkey!("11111111111111111111111111111111");
+
+// Owner of the `PriceUpdateV2` accounts Pyth prices are posted to.
+#[constant]
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+// Oracle-priced offers cannot be taken against a price older than this.
+#[constant]
+pub const MAX_PRICE_AGE_SECONDS: i64 = 60;
+// Bound on `Pricing::Oracle::spread_basis_points`, either way.
+#[constant]
+pub const MAX_SPREAD_BASIS_POINTS: i16 = 5_000;
+
+// Most mints a bundle offer can hold, or ask for, on each side.
+#[constant]
+pub const MAX_BUNDLE_ASSETS: usize = 4;
+
+// Zeroed bytes at the end of `Offer` for fields added later, so adding one
+// does not change the size of the account.
+pub const OFFER_RESERVED_SPACE: usize = 64;
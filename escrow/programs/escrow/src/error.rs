@@ -2,6 +2,106 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Custom error message")]
-    CustomError,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Offer has not expired yet")]
+    OfferNotExpired,
+    #[msg("Offer expiry must be in the future")]
+    ExpiryInPast,
+    #[msg("Offer is reserved for a different taker")]
+    TakerNotAllowed,
+    #[msg("Offered and wanted amounts must be greater than zero")]
+    InvalidAmount,
+    #[msg("Fill amount exceeds what is left on the offer")]
+    FillExceedsRemaining,
+    #[msg("Fill is too small to release any of the offered tokens")]
+    FillTooSmall,
+    #[msg("Fee exceeds the maximum allowed")]
+    FeeTooHigh,
+    #[msg("Treasury has to be a system account holding at least the rent-exempt minimum")]
+    InvalidTreasury,
+    #[msg("Only the program upgrade authority can initialize the config")]
+    NotUpgradeAuthority,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Vault still holds tokens after withdrawal")]
+    VaultNotEmpty,
+    #[msg("Offer does not trade native SOL on this side")]
+    NotNativeSol,
+    #[msg("Bundles need between one and MAX_BUNDLE_ASSETS assets on each side")]
+    InvalidBundleSize,
+    #[msg("A mint is listed more than once on the same side of a bundle")]
+    DuplicateBundleMint,
+    #[msg("Remaining accounts do not match the bundle offer")]
+    BundleAccountMismatch,
+    #[msg("Price feed has not been updated recently enough")]
+    StalePrice,
+    #[msg("Price feed does not give a usable price")]
+    InvalidPrice,
+    #[msg("Spread exceeds the maximum allowed")]
+    SpreadTooLarge,
+    #[msg("Price feed account is missing or is not the one the offer uses")]
+    PriceFeedMismatch,
+    #[msg("Mint has a permanent delegate that could move tokens out of the vault")]
+    PermanentDelegateNotAllowed,
+    #[msg("Mint has a transfer hook")]
+    TransferHookNotAllowed,
+    #[msg("Mint is non-transferable")]
+    NonTransferableMint,
+    #[msg("Mint supports confidential transfers")]
+    ConfidentialTransfersNotAllowed,
+    #[msg("Mint allowance is for a different mint")]
+    MintAllowanceMismatch,
+    #[msg("Remaining accounts do not match the offers being taken")]
+    SweepAccountMismatch,
+    #[msg("Maximum spend is too small to fill any of the offers")]
+    SweepFilledNothing,
+    #[msg("Vesting needs a positive duration and a cliff within it")]
+    InvalidVestingSchedule,
+    #[msg("Offer vests and can only be taken with take_vesting_offer")]
+    OfferVests,
+    #[msg("Offer does not vest")]
+    OfferDoesNotVest,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Auction has to fall to a lower, non-zero price over a window that has not ended")]
+    InvalidAuction,
+    #[msg("Buyer, seller and arbiter have to be three different wallets")]
+    InvalidEscrowParties,
+    #[msg("Arbitration period has to be greater than zero")]
+    InvalidArbitrationPeriod,
+    #[msg("Escrow has timed out and can only be refunded")]
+    EscrowTimedOut,
+    #[msg("Signer is neither the buyer nor the seller")]
+    NotAnEscrowParty,
+    #[msg("Seller has not confirmed delivery")]
+    DeliveryNotConfirmed,
+    #[msg("Seller has already confirmed delivery")]
+    DeliveryAlreadyConfirmed,
+    #[msg("Escrow is already disputed")]
+    EscrowAlreadyDisputed,
+    #[msg("Only the buyer, or the arbiter of a dispute over a confirmed delivery, can release the escrow")]
+    ReleaseNotAllowed,
+    #[msg(
+        "Only the seller, or the arbiter of a dispute, can refund the escrow before it times out"
+    )]
+    RefundNotAllowed,
+    #[msg("Token A has to have no decimals and a supply of one")]
+    NotAnNft,
+    #[msg("Metadata account is not the Metaplex metadata of token A")]
+    NftMetadataMismatch,
+    #[msg("NFT is not a verified member of the required collection")]
+    NftNotInCollection,
+    #[msg("Offer sells an NFT and can only be taken with take_nft_offer")]
+    OfferIsNft,
+    #[msg("Offer does not sell an NFT")]
+    OfferIsNotNft,
+    #[msg("Remaining accounts do not match the creators of the NFT")]
+    RoyaltyAccountMismatch,
+    #[msg("Offer is already in the current layout")]
+    OfferAlreadyMigrated,
+    #[msg("Offer is priced beyond the limit set by the taker")]
+    TakerLimitExceeded,
+    #[msg("Oracle-priced and Dutch auction offers are quoted on every take, so their token B amount cannot be set")]
+    OfferIsQuoted,
 }
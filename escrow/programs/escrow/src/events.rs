@@ -0,0 +1,168 @@
+use anchor_lang::prelude::*;
+
+use crate::{BundleAsset, Pricing};
+
+#[event]
+pub struct OfferMade {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub expires_at: i64,
+    pub allowed_taker: Option<Pubkey>,
+    pub pricing: Pricing,
+    pub slot: u64,
+}
+
+// Emitted for every fill, full or partial.  The offer is closed once both
+// remaining amounts reach zero.
+#[event]
+pub struct OfferTaken {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    // Paid by the taker, `fee` included.
+    pub token_b_amount: u64,
+    pub fee: u64,
+    pub token_a_remaining_amount: u64,
+    pub token_b_remaining_amount: u64,
+    pub slot: u64,
+}
+
+// Amounts are what was left on the offer before and after the update.
+#[event]
+pub struct OfferUpdated {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub old_token_a_amount: u64,
+    pub new_token_a_amount: u64,
+    pub old_token_b_amount: u64,
+    pub new_token_b_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OfferCancelled {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_refunded_amount: u64,
+    // Closed by `close_expired_offer` rather than refunded by the maker.
+    pub expired: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BundleOfferMade {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BundleAsset>,
+    pub wanted: Vec<BundleAsset>,
+    pub expires_at: i64,
+    pub allowed_taker: Option<Pubkey>,
+    pub slot: u64,
+}
+
+// `wanted` amounts are paid by the taker, `fees` (one per wanted asset)
+// included.
+#[event]
+pub struct BundleOfferTaken {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub offered: Vec<BundleAsset>,
+    pub wanted: Vec<BundleAsset>,
+    pub fees: Vec<u64>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BundleOfferCancelled {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BundleAsset>,
+    pub slot: u64,
+}
+
+// `token_a_amount` left the vesting account, before any transfer fee.
+#[event]
+pub struct VestedTokensClaimed {
+    pub vesting: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub token_a_amount: u64,
+    pub claimed_amount: u64,
+    pub total_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ArbitratedEscrowOpened {
+    pub id: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_b_amount: u64,
+    pub refund_after: i64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct DeliveryConfirmed {
+    pub id: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ArbitratedEscrowDisputed {
+    pub id: u64,
+    pub buyer: Pubkey,
+    // The buyer or the seller.
+    pub disputed_by: Pubkey,
+    pub refund_after: i64,
+    pub slot: u64,
+}
+
+// `released` if the seller was paid, otherwise the buyer was refunded.
+#[event]
+pub struct ArbitratedEscrowSettled {
+    pub id: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub settled_by: Pubkey,
+    pub released: bool,
+    pub token_b_amount: u64,
+    pub slot: u64,
+}
+
+// One per creator paid on the take of an NFT offer, out of the token B the
+// maker would otherwise have received.
+#[event]
+pub struct RoyaltyPaid {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub nft_mint: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OfferMigrated {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub version: u8,
+    pub slot: u64,
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::Mint;
+
+use crate::{Config, MintAllowance, ANCHOR_DISCRIMINATOR};
+
+#[derive(Accounts)]
+pub struct AllowMint<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + MintAllowance::INIT_SPACE,
+        seeds = [b"mint_allowance", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_allowance: Account<'info, MintAllowance>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisallowMint<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"mint_allowance", mint_allowance.mint.as_ref()],
+        bump = mint_allowance.bump
+    )]
+    pub mint_allowance: Account<'info, MintAllowance>,
+}
+
+pub fn save_mint_allowance(context: Context<AllowMint>) -> Result<()> {
+    context.accounts.mint_allowance.set_inner(MintAllowance {
+        mint: context.accounts.mint.key(),
+        bump: context.bumps.mint_allowance,
+    });
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::spl_token_2022::onchain::invoke_transfer_checked,
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{error::ErrorCode, harvest_withheld_fees, VestedTokensClaimed, Vesting};
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    // Writable so transfer fees withheld in the vesting vault can be
+    // harvested to it before the vault is closed.
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        associated_token::mint = mint,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program,
+    )]
+    pub beneficiary_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Not `close = beneficiary`: it stays open until the last claim.
+    #[account(
+        mut,
+        has_one = beneficiary,
+        has_one = mint,
+        seeds = [b"vesting", vesting.offer.as_ref()],
+        bump = vesting.bump
+    )]
+    pub vesting: Account<'info, Vesting>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vesting,
+        associated_token::token_program = token_program,
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Sends the beneficiary what has vested since the last claim.  The last claim
+/// sends whatever is in the vesting vault, tokens anyone sent to it included,
+/// and closes it and the vesting account, with their rent going to the
+/// beneficiary.
+pub fn withdraw_vested_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClaimVested<'info>>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let vested_amount = ctx
+        .accounts
+        .vesting
+        .claimable_amount(clock.unix_timestamp)?;
+    require!(vested_amount > 0, ErrorCode::NothingToClaim);
+
+    let vesting = &ctx.accounts.vesting;
+    let last_claim = vesting.claimed_amount + vested_amount == vesting.total_amount;
+    // The vault is a public associated token account, so it may hold more
+    // than what is left to vest, and it has to be empty to be closed.
+    let amount = if last_claim {
+        ctx.accounts.vesting_vault.amount
+    } else {
+        vested_amount
+    };
+    let signer_seeds: [&[&[u8]]; 1] = [&[b"vesting", vesting.offer.as_ref(), &[vesting.bump]]];
+
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.vesting_vault.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        ctx.accounts.beneficiary_token_account.to_account_info(),
+        vesting.to_account_info(),
+        ctx.remaining_accounts,
+        amount,
+        ctx.accounts.mint.decimals,
+        &signer_seeds,
+    )?;
+
+    let vesting = &mut ctx.accounts.vesting;
+    vesting.claimed_amount += vested_amount;
+
+    emit!(VestedTokensClaimed {
+        vesting: vesting.key(),
+        beneficiary: vesting.beneficiary,
+        mint: vesting.mint,
+        token_a_amount: amount,
+        claimed_amount: vesting.claimed_amount,
+        total_amount: vesting.total_amount,
+        slot: clock.slot,
+    });
+
+    if !last_claim {
+        return Ok(());
+    }
+
+    ctx.accounts.vesting_vault.reload()?;
+    require_eq!(
+        ctx.accounts.vesting_vault.amount,
+        0,
+        ErrorCode::VaultNotEmpty
+    );
+
+    let vesting = &ctx.accounts.vesting;
+    let signer_seeds: [&[&[u8]]; 1] = [&[b"vesting", vesting.offer.as_ref(), &[vesting.bump]]];
+
+    harvest_withheld_fees(
+        ctx.accounts.vesting_vault.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    let accounts = CloseAccount {
+        account: ctx.accounts.vesting_vault.to_account_info(),
+        destination: ctx.accounts.beneficiary.to_account_info(),
+        authority: vesting.to_account_info(),
+    };
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    ))?;
+
+    ctx.accounts
+        .vesting
+        .close(ctx.accounts.beneficiary.to_account_info())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, MakerProfile, Offer, OfferCancelled, NATIVE_SOL_MINT};
+
+// As with `close_expired_offer`, anyone may close an expired offer since the
+// lamports can only go back to the maker.
+#[derive(Accounts)]
+pub struct CloseExpiredSolOffer<'info> {
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        constraint = offer.token_mint_a == NATIVE_SOL_MINT @ ErrorCode::NotNativeSol,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump,
+        constraint = offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferNotExpired
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+pub fn return_expired_lamports(context: Context<CloseExpiredSolOffer>) -> Result<()> {
+    let offer = &context.accounts.offer;
+    emit!(OfferCancelled {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_refunded_amount: offer.token_a_remaining_amount,
+        expired: true,
+        slot: Clock::get()?.slot,
+    });
+
+    context.accounts.maker_profile.close_offer(false);
+    Ok(())
+}
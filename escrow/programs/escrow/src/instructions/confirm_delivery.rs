@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, ArbitratedEscrow, DeliveryConfirmed};
+
+#[derive(Accounts)]
+pub struct ConfirmDelivery<'info> {
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = seller,
+        seeds = [
+            b"arbitrated_escrow",
+            escrow.buyer.as_ref(),
+            escrow.id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, ArbitratedEscrow>,
+}
+
+pub fn record_delivery(context: Context<ConfirmDelivery>) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow = &mut context.accounts.escrow;
+    require!(
+        !escrow.is_timed_out(clock.unix_timestamp),
+        ErrorCode::EscrowTimedOut
+    );
+    require!(
+        !escrow.delivery_confirmed,
+        ErrorCode::DeliveryAlreadyConfirmed
+    );
+
+    escrow.delivery_confirmed = true;
+
+    emit!(DeliveryConfirmed {
+        id: escrow.id,
+        buyer: escrow.buyer,
+        seller: escrow.seller,
+        slot: clock.slot,
+    });
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, ArbitratedEscrow, ArbitratedEscrowDisputed};
+
+// Signed by the buyer or the seller.
+#[derive(Accounts)]
+pub struct DisputeArbitratedEscrow<'info> {
+    pub party: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"arbitrated_escrow",
+            escrow.buyer.as_ref(),
+            escrow.id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, ArbitratedEscrow>,
+}
+
+/// Hands the escrow to the arbiter, who then has `arbitration_seconds` to
+/// release or refund it before it times out in favor of the buyer.  The
+/// seller can only dispute a delivery they have confirmed.
+pub fn raise_dispute(context: Context<DisputeArbitratedEscrow>) -> Result<()> {
+    let clock = Clock::get()?;
+    let party = context.accounts.party.key();
+    let escrow = &mut context.accounts.escrow;
+    require!(
+        party == escrow.buyer || party == escrow.seller,
+        ErrorCode::NotAnEscrowParty
+    );
+    require!(
+        !escrow.is_timed_out(clock.unix_timestamp),
+        ErrorCode::EscrowTimedOut
+    );
+    require!(!escrow.disputed, ErrorCode::EscrowAlreadyDisputed);
+    if party == escrow.seller {
+        require!(escrow.delivery_confirmed, ErrorCode::DeliveryNotConfirmed);
+    }
+
+    escrow.disputed = true;
+    escrow.refund_after = clock
+        .unix_timestamp
+        .checked_add(escrow.arbitration_seconds)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    emit!(ArbitratedEscrowDisputed {
+        id: escrow.id,
+        buyer: escrow.buyer,
+        disputed_by: party,
+        refund_after: escrow.refund_after,
+        slot: clock.slot,
+    });
+    Ok(())
+}
@@ -1,9 +1,60 @@
 use anchor_lang::prelude::*;
 
+use crate::{
+    error::ErrorCode, program::Escrow, Config, ANCHOR_DISCRIMINATOR, MAX_FEE_BASIS_POINTS,
+};
+
 #[derive(Accounts)]
-pub struct Initialize {}
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = treasury_is_rent_exempt(&treasury)? @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    // Only the upgrade authority may create the config, otherwise anyone could
+    // claim the admin role right after the program is deployed.
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Escrow>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Whether `treasury` can be paid fees in lamports. Transfers that would
+/// leave a system account below the rent-exempt minimum fail, so a fee
+/// smaller than that minimum could never reach an unfunded treasury.
+pub fn treasury_is_rent_exempt(treasury: &SystemAccount) -> Result<bool> {
+    Ok(treasury.lamports() >= Rent::get()?.minimum_balance(0))
+}
+
+pub fn save_config(context: Context<Initialize>, fee_basis_points: u16) -> Result<()> {
+    require!(
+        fee_basis_points <= MAX_FEE_BASIS_POINTS,
+        ErrorCode::FeeTooHigh
+    );
 
-pub fn handler(ctx: Context<Initialize>) -> Result<()> {
-    msg!("Greetings from: {:?}", ctx.program_id);
+    context.accounts.config.set_inner(Config {
+        admin: context.accounts.admin.key(),
+        fee_basis_points,
+        treasury: context.accounts.treasury.key(),
+        bump: context.bumps.config,
+    });
     Ok(())
 }
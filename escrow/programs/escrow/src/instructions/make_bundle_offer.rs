@@ -0,0 +1,152 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token_interface::{transfer_checked, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    bundle_mint, check_bundle_vault, check_mint_extensions, error::ErrorCode, offer_expiry,
+    validate_bundle_assets, BundleAsset, BundleOffer, BundleOfferMade, MakerProfile,
+    ANCHOR_DISCRIMINATOR,
+};
+
+// Every mint in the bundle must belong to `token_program`.
+#[derive(Accounts)]
+pub struct MakeBundleOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + BundleOffer::INIT_SPACE,
+        seeds = [
+            b"bundle_offer",
+            maker.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub bundle_offer: Account<'info, BundleOffer>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates a vault for every offered asset and fills it from the maker.
+///
+/// `remaining_accounts` holds, for each offered asset in order: its mint, the
+/// maker's token account and the vault, which is the associated token account
+/// of the bundle offer.
+pub fn deposit_bundle<'info>(
+    context: &Context<'_, '_, 'info, 'info, MakeBundleOffer<'info>>,
+    offered: &[BundleAsset],
+) -> Result<()> {
+    validate_bundle_assets(offered)?;
+    require_eq!(
+        context.remaining_accounts.len(),
+        3 * offered.len(),
+        ErrorCode::BundleAccountMismatch
+    );
+
+    let accounts = &context.accounts;
+
+    for (asset, remaining) in offered
+        .iter()
+        .zip(context.remaining_accounts.chunks_exact(3))
+    {
+        let mint = bundle_mint(&remaining[0], asset, &accounts.token_program)?;
+        // There is no room for mint allowances in a bundle, so only mints that
+        // pass the default policy can be bundled.
+        check_mint_extensions(&mint, None)?;
+        let maker_token_account = &remaining[1];
+        let vault = &remaining[2];
+
+        check_bundle_vault(
+            &accounts.bundle_offer.key(),
+            &asset.mint,
+            vault,
+            &accounts.token_program.key(),
+        )?;
+
+        let create_accounts = Create {
+            payer: accounts.maker.to_account_info(),
+            associated_token: vault.clone(),
+            authority: accounts.bundle_offer.to_account_info(),
+            mint: mint.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        };
+        // Idempotent for the reason `MakeOffer` creates its vault with
+        // `init_if_needed`.
+        create_idempotent(CpiContext::new(
+            accounts.associated_token_program.to_account_info(),
+            create_accounts,
+        ))?;
+
+        let transfer_accounts = TransferChecked {
+            from: maker_token_account.clone(),
+            mint: mint.to_account_info(),
+            to: vault.clone(),
+            authority: accounts.maker.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new(accounts.token_program.to_account_info(), transfer_accounts),
+            asset.amount,
+            mint.decimals,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn save_bundle_offer(
+    context: Context<MakeBundleOffer>,
+    offered: Vec<BundleAsset>,
+    wanted: Vec<BundleAsset>,
+    expires_at: Option<i64>,
+    allowed_taker: Option<Pubkey>,
+) -> Result<()> {
+    validate_bundle_assets(&wanted)?;
+
+    let id = context
+        .accounts
+        .maker_profile
+        .open_offer(context.accounts.maker.key(), context.bumps.maker_profile)?;
+
+    let clock = Clock::get()?;
+    let expires_at = offer_expiry(expires_at, clock.unix_timestamp)?;
+
+    context.accounts.bundle_offer.set_inner(BundleOffer {
+        id,
+        maker: context.accounts.maker.key(),
+        offered: offered.clone(),
+        wanted: wanted.clone(),
+        expires_at,
+        allowed_taker,
+        bump: context.bumps.bundle_offer,
+    });
+
+    emit!(BundleOfferMade {
+        id,
+        maker: context.accounts.maker.key(),
+        offered,
+        wanted,
+        expires_at,
+        allowed_taker,
+        slot: clock.slot,
+    });
+    Ok(())
+}
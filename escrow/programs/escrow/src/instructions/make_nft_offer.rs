@@ -0,0 +1,143 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    metadata::MetadataAccount,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    check_mint_extensions, deposited_amount, emit_offer_made, error::ErrorCode, offer_expiry,
+    transfer_tokens, AmountBasis, MakerProfile, MintAllowance, NftTerms, Offer, Pricing,
+    ANCHOR_DISCRIMINATOR, OFFER_RESERVED_SPACE,
+};
+
+// `MakeOffer` with the Metaplex metadata of token A, the NFT being sold.
+#[derive(Accounts)]
+pub struct MakeNftOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Checked against `token_mint_a` by `NftTerms::check`.
+    pub metadata: Account<'info, MetadataAccount>,
+
+    // Only needed for mints with extensions `check_mint_extensions` rejects.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves the NFT into the vault and records a fixed-price offer for it, in
+/// `collection` if one is given.
+pub fn list_nft<'info>(
+    context: Context<'_, '_, '_, 'info, MakeNftOffer<'info>>,
+    token_b_wanted_amount: u64,
+    token_b_amount_basis: AmountBasis,
+    expires_at: Option<i64>,
+    allowed_taker: Option<Pubkey>,
+    collection: Option<Pubkey>,
+) -> Result<()> {
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+
+    let accounts = context.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )?;
+    let nft = NftTerms { collection };
+    nft.check(&accounts.token_mint_a, &accounts.metadata)?;
+
+    transfer_tokens(
+        &accounts.maker,
+        &accounts.maker_token_account_a,
+        &accounts.token_mint_a,
+        &accounts.vault,
+        1,
+        &accounts.token_program,
+        context.remaining_accounts,
+    )?;
+    let token_a_offered_amount = deposited_amount(&mut accounts.vault)?;
+
+    let id = accounts
+        .maker_profile
+        .open_offer(accounts.maker.key(), context.bumps.maker_profile)?;
+
+    let clock = Clock::get()?;
+    let expires_at = offer_expiry(expires_at, clock.unix_timestamp)?;
+
+    accounts.offer.set_inner(Offer {
+        id,
+        maker: accounts.maker.key(),
+        token_mint_a: accounts.token_mint_a.key(),
+        token_mint_b: accounts.token_mint_b.key(),
+        token_b_wanted_amount,
+        token_a_remaining_amount: token_a_offered_amount,
+        token_b_remaining_amount: token_b_wanted_amount,
+        expires_at,
+        version: Offer::VERSION,
+        allowed_taker,
+        pricing: Pricing::Fixed,
+        token_b_amount_basis,
+        bump: context.bumps.offer,
+        vesting: None,
+        nft: Some(nft),
+        reserved: [0; OFFER_RESERVED_SPACE],
+    });
+
+    emit_offer_made(&accounts.offer, clock.slot);
+    Ok(())
+}
@@ -2,13 +2,17 @@ use anchor_lang::prelude::*;
 
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::{Offer, ANCHOR_DISCRIMINATOR};
+use crate::{
+    check_mint_extensions, deposited_amount, emit_offer_made, error::ErrorCode, load_oracle_price,
+    offer_expiry, quote_from_feed, transfer_tokens, AmountBasis, DutchAuction, MakerProfile,
+    MintAllowance, Offer, Pricing, VestingSchedule, ANCHOR_DISCRIMINATOR, MAX_SPREAD_BASIS_POINTS,
+    OFFER_RESERVED_SPACE,
+};
 
 #[derive(Accounts)]
-#[instruction(id: u64)]
 pub struct MakeOffer<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
@@ -27,17 +31,34 @@ pub struct MakeOffer<'info> {
     )]
     pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
 
+    // Created along with the maker's first offer.  The new offer takes its
+    // id, and so its address, from `next_offer_id`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
     #[account(
         init,
         payer = maker,
         space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
-        seeds = [b"offer", maker.key().as_ref(), id.to_le_bytes().as_ref()],
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
         bump
     )]
     pub offer: Account<'info, Offer>,
 
+    // `init_if_needed`: the vault's address is known before the offer is made,
+    // so anyone could create it first to block the offer.
     #[account(
-        init,
+        init_if_needed,
         payer = maker,
         associated_token::mint = token_mint_a,
         associated_token::authority = offer,
@@ -45,42 +66,132 @@ pub struct MakeOffer<'info> {
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: Only read by `make_oracle_offer`, which prices the offer from
+    /// it.  `load_oracle_price` checks it is an oracle account.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    // Only needed for mints with extensions `check_mint_extensions` rejects.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn send_offered_tokens_to_vault(
-    context: &Context<MakeOffer>,
+pub fn check_make_offer_mints(context: &Context<MakeOffer>) -> Result<()> {
+    let accounts = &context.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )
+}
+
+pub fn send_offered_tokens_to_vault<'info>(
+    context: &Context<'_, '_, '_, 'info, MakeOffer<'info>>,
     token_a_offered_amount: u64,
 ) -> Result<()> {
-    let transfer_accounts = TransferChecked {
-        from: context.accounts.maker_token_account_a.to_account_info(),
-        mint: context.accounts.token_mint_a.to_account_info(),
-        to: context.accounts.vault.to_account_info(),
-        authority: context.accounts.maker.to_account_info(),
-    };
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidAmount);
+
+    transfer_tokens(
+        &context.accounts.maker,
+        &context.accounts.maker_token_account_a,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault,
+        token_a_offered_amount,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )
+}
 
-    let cpi_context = CpiContext::new(
-        context.accounts.token_program.to_account_info(),
-        transfer_accounts,
+/// Pricing for an offer quoted from `price_feed`, along with the amount of
+/// token B the offered tokens are worth right now.
+pub fn oracle_pricing(
+    context: &Context<MakeOffer>,
+    token_a_offered_amount: u64,
+    spread_basis_points: i16,
+) -> Result<(Pricing, u64)> {
+    require!(
+        spread_basis_points.unsigned_abs() <= MAX_SPREAD_BASIS_POINTS.unsigned_abs(),
+        ErrorCode::SpreadTooLarge
     );
+    let price_feed = context
+        .accounts
+        .price_feed
+        .as_ref()
+        .ok_or(ErrorCode::PriceFeedMismatch)?;
 
-    transfer_checked(
-        cpi_context,
+    let price = load_oracle_price(price_feed)?;
+    let token_b_wanted_amount = quote_from_feed(
+        &price,
+        Clock::get()?.unix_timestamp,
         token_a_offered_amount,
         context.accounts.token_mint_a.decimals,
-    )
+        context.accounts.token_mint_b.decimals,
+        spread_basis_points,
+    )?;
+
+    let pricing = Pricing::Oracle {
+        price_feed: price_feed.key(),
+        feed_id: price.feed_id,
+        spread_basis_points,
+    };
+    Ok((pricing, token_b_wanted_amount))
 }
 
-pub fn save_offer(context: Context<MakeOffer>, id: u64, token_b_wanted_amount: u64) -> Result<()> {
+/// Checks `auction` and returns what the offered tokens are worth right now.
+pub fn dutch_auction_price(auction: &DutchAuction) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    auction.validate(now)?;
+    Ok(auction.token_b_amount_at(now))
+}
+
+pub fn save_offer(
+    context: Context<MakeOffer>,
+    token_b_wanted_amount: u64,
+    token_b_amount_basis: AmountBasis,
+    expires_at: Option<i64>,
+    allowed_taker: Option<Pubkey>,
+    pricing: Pricing,
+    vesting: Option<VestingSchedule>,
+) -> Result<()> {
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+    if let Some(vesting) = vesting {
+        vesting.validate()?;
+    }
+    let token_a_offered_amount = deposited_amount(&mut context.accounts.vault)?;
+
+    let id = context
+        .accounts
+        .maker_profile
+        .open_offer(context.accounts.maker.key(), context.bumps.maker_profile)?;
+
+    let clock = Clock::get()?;
+    let expires_at = offer_expiry(expires_at, clock.unix_timestamp)?;
+
     context.accounts.offer.set_inner(Offer {
         id,
         maker: context.accounts.maker.key(),
         token_mint_a: context.accounts.token_mint_a.key(),
         token_mint_b: context.accounts.token_mint_b.key(),
         token_b_wanted_amount,
+        token_a_remaining_amount: token_a_offered_amount,
+        token_b_remaining_amount: token_b_wanted_amount,
+        expires_at,
+        version: Offer::VERSION,
+        allowed_taker,
+        pricing,
+        token_b_amount_basis,
         bump: context.bumps.offer,
+        vesting,
+        nft: None,
+        reserved: [0; OFFER_RESERVED_SPACE],
     });
+
+    emit_offer_made(&context.accounts.offer, clock.slot);
     Ok(())
 }
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    check_mint_extensions, deposited_amount, emit_offer_made, error::ErrorCode, offer_expiry,
+    transfer_tokens, AmountBasis, MakerProfile, MintAllowance, Offer, Pricing,
+    ANCHOR_DISCRIMINATOR, NATIVE_SOL_MINT, OFFER_RESERVED_SPACE,
+};
+
+// Sells token A for native SOL.  Token A sits in a vault as usual, the taker
+// pays the maker in lamports.
+#[derive(Accounts)]
+pub struct MakeOfferForSol<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // See `MakeOffer`.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn deposit_offered_tokens<'info>(
+    context: &Context<'_, '_, '_, 'info, MakeOfferForSol<'info>>,
+    token_a_offered_amount: u64,
+) -> Result<()> {
+    require!(token_a_offered_amount > 0, ErrorCode::InvalidAmount);
+    check_mint_extensions(
+        &context.accounts.token_mint_a,
+        context.accounts.token_mint_a_allowance.as_ref(),
+    )?;
+
+    transfer_tokens(
+        &context.accounts.maker,
+        &context.accounts.maker_token_account_a,
+        &context.accounts.token_mint_a,
+        &context.accounts.vault,
+        token_a_offered_amount,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )
+}
+
+pub fn save_offer_for_sol(
+    context: Context<MakeOfferForSol>,
+    lamports_wanted: u64,
+    expires_at: Option<i64>,
+    allowed_taker: Option<Pubkey>,
+) -> Result<()> {
+    require!(lamports_wanted > 0, ErrorCode::InvalidAmount);
+    let token_a_offered_amount = deposited_amount(&mut context.accounts.vault)?;
+
+    let id = context
+        .accounts
+        .maker_profile
+        .open_offer(context.accounts.maker.key(), context.bumps.maker_profile)?;
+
+    let clock = Clock::get()?;
+    let expires_at = offer_expiry(expires_at, clock.unix_timestamp)?;
+
+    context.accounts.offer.set_inner(Offer {
+        id,
+        maker: context.accounts.maker.key(),
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: NATIVE_SOL_MINT,
+        token_b_wanted_amount: lamports_wanted,
+        token_a_remaining_amount: token_a_offered_amount,
+        token_b_remaining_amount: lamports_wanted,
+        expires_at,
+        version: Offer::VERSION,
+        allowed_taker,
+        pricing: Pricing::Fixed,
+        token_b_amount_basis: AmountBasis::Gross,
+        bump: context.bumps.offer,
+        vesting: None,
+        nft: None,
+        reserved: [0; OFFER_RESERVED_SPACE],
+    });
+
+    emit_offer_made(&context.accounts.offer, clock.slot);
+    Ok(())
+}
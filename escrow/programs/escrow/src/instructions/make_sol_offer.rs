@@ -0,0 +1,116 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use anchor_spl::token_interface::{Mint, TokenInterface};
+
+use crate::{
+    check_mint_extensions, emit_offer_made, error::ErrorCode, offer_expiry, AmountBasis,
+    MakerProfile, MintAllowance, Offer, Pricing, ANCHOR_DISCRIMINATOR, NATIVE_SOL_MINT,
+    OFFER_RESERVED_SPACE,
+};
+
+// Buys token B with native SOL.  The offered lamports are held by the offer
+// account itself, on top of its rent.
+#[derive(Accounts)]
+pub struct MakeSolOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
+        seeds = [
+            b"offer",
+            maker.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // See `MakeOffer`.
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn deposit_offered_lamports(
+    context: &Context<MakeSolOffer>,
+    lamports_offered: u64,
+) -> Result<()> {
+    require!(lamports_offered > 0, ErrorCode::InvalidAmount);
+
+    let transfer_accounts = Transfer {
+        from: context.accounts.maker.to_account_info(),
+        to: context.accounts.offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new(
+        context.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+
+    transfer(cpi_context, lamports_offered)
+}
+
+pub fn save_sol_offer(
+    context: Context<MakeSolOffer>,
+    lamports_offered: u64,
+    token_b_wanted_amount: u64,
+    token_b_amount_basis: AmountBasis,
+    expires_at: Option<i64>,
+    allowed_taker: Option<Pubkey>,
+) -> Result<()> {
+    require!(token_b_wanted_amount > 0, ErrorCode::InvalidAmount);
+    check_mint_extensions(
+        &context.accounts.token_mint_b,
+        context.accounts.token_mint_b_allowance.as_ref(),
+    )?;
+
+    let id = context
+        .accounts
+        .maker_profile
+        .open_offer(context.accounts.maker.key(), context.bumps.maker_profile)?;
+
+    let clock = Clock::get()?;
+    let expires_at = offer_expiry(expires_at, clock.unix_timestamp)?;
+
+    context.accounts.offer.set_inner(Offer {
+        id,
+        maker: context.accounts.maker.key(),
+        token_mint_a: NATIVE_SOL_MINT,
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_b_wanted_amount,
+        token_a_remaining_amount: lamports_offered,
+        token_b_remaining_amount: token_b_wanted_amount,
+        expires_at,
+        version: Offer::VERSION,
+        allowed_taker,
+        pricing: Pricing::Fixed,
+        token_b_amount_basis,
+        bump: context.bumps.offer,
+        vesting: None,
+        nft: None,
+        reserved: [0; OFFER_RESERVED_SPACE],
+    });
+
+    emit_offer_made(&context.accounts.offer, clock.slot);
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode,
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator,
+};
+
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    error::ErrorCode, LegacyOffer, MakerProfile, Offer, OfferMigrated, ANCHOR_DISCRIMINATOR,
+};
+
+#[derive(Accounts)]
+pub struct MigrateOffer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Checked against the maker of the offer by
+    /// `rewrite_legacy_offer`.
+    pub maker: UncheckedAccount<'info>,
+
+    // Legacy offers have ids their maker picked, so the maker's profile is
+    // created if needed and moved past the id, keeping new offers off it.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    /// CHECK: A legacy offer does not deserialize as `Offer`.  Only this
+    /// program can write accounts it owns, so the `Offer` discriminator,
+    /// checked by `rewrite_legacy_offer`, is enough to know it is an offer.
+    #[account(mut, owner = crate::ID)]
+    pub offer: UncheckedAccount<'info>,
+
+    // Checked against the offer by `rewrite_legacy_offer`.
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Legacy offers do not record what is left of them; the vault does.
+    #[account(
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Reads the offer as a `LegacyOffer`, grows the account to the size of an
+/// `Offer`, topping its rent up from the payer, and writes it back as one
+/// offering what is in its vault.
+pub fn rewrite_legacy_offer(context: Context<MigrateOffer>) -> Result<()> {
+    let info = context.accounts.offer.to_account_info();
+    let space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE;
+
+    let legacy = {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.starts_with(&Offer::DISCRIMINATOR),
+            AnchorErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(data.len() < space, ErrorCode::OfferAlreadyMigrated);
+        LegacyOffer::deserialize(&mut &data[ANCHOR_DISCRIMINATOR..])
+            .map_err(|_| AnchorErrorCode::AccountDidNotDeserialize)?
+    };
+    require_keys_eq!(
+        legacy.maker,
+        context.accounts.maker.key(),
+        AnchorErrorCode::ConstraintHasOne
+    );
+    require_keys_eq!(
+        legacy.token_mint_a,
+        context.accounts.token_mint_a.key(),
+        AnchorErrorCode::ConstraintHasOne
+    );
+
+    let maker_profile = &mut context.accounts.maker_profile;
+    maker_profile.adopt_offer(legacy.maker, context.bumps.maker_profile, legacy.id)?;
+    // Legacy offers were never counted as open.
+    maker_profile.open_offers += 1;
+
+    let rent = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(info.lamports());
+    if rent > 0 {
+        transfer(
+            CpiContext::new(
+                context.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: context.accounts.payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent,
+        )?;
+    }
+    info.realloc(space, true)?;
+
+    let offer = legacy.into_offer(context.accounts.vault.amount);
+    offer.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    emit!(OfferMigrated {
+        id: offer.id,
+        maker: offer.maker,
+        version: offer.version,
+        slot: Clock::get()?.slot,
+    });
+    Ok(())
+}
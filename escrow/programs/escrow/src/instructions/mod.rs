@@ -1,7 +1,83 @@
-// pub mod initialize;
-// pub use initialize::*;
+pub mod initialize;
+pub use initialize::*;
+
 pub mod make_offer;
 pub use make_offer::*;
 
 pub mod take_offer;
 pub use take_offer::*;
+
+pub mod take_offers;
+pub use take_offers::*;
+
+pub mod take_vesting_offer;
+pub use take_vesting_offer::*;
+
+pub mod claim_vested;
+pub use claim_vested::*;
+
+pub mod make_nft_offer;
+pub use make_nft_offer::*;
+
+pub mod take_nft_offer;
+pub use take_nft_offer::*;
+
+pub mod update_offer;
+pub use update_offer::*;
+
+pub mod refund_offer;
+pub use refund_offer::*;
+
+pub mod close_expired_offer;
+pub use close_expired_offer::*;
+
+pub mod migrate_offer;
+pub use migrate_offer::*;
+
+pub mod make_offer_for_sol;
+pub use make_offer_for_sol::*;
+
+pub mod make_sol_offer;
+pub use make_sol_offer::*;
+
+pub mod take_offer_for_sol;
+pub use take_offer_for_sol::*;
+
+pub mod take_sol_offer;
+pub use take_sol_offer::*;
+
+pub mod refund_sol_offer;
+pub use refund_sol_offer::*;
+
+pub mod close_expired_sol_offer;
+pub use close_expired_sol_offer::*;
+
+pub mod make_bundle_offer;
+pub use make_bundle_offer::*;
+
+pub mod take_bundle_offer;
+pub use take_bundle_offer::*;
+
+pub mod refund_bundle_offer;
+pub use refund_bundle_offer::*;
+
+pub mod open_arbitrated_escrow;
+pub use open_arbitrated_escrow::*;
+
+pub mod confirm_delivery;
+pub use confirm_delivery::*;
+
+pub mod dispute_arbitrated_escrow;
+pub use dispute_arbitrated_escrow::*;
+
+pub mod settle_arbitrated_escrow;
+pub use settle_arbitrated_escrow::*;
+
+pub mod update_config;
+pub use update_config::*;
+
+pub mod allow_mint;
+pub use allow_mint::*;
+
+pub mod shared;
+pub use shared::*;
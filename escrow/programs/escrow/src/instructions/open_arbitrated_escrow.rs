@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    check_mint_extensions, deposited_amount, error::ErrorCode, transfer_tokens, ArbitratedEscrow,
+    ArbitratedEscrowOpened, MakerProfile, MintAllowance, ANCHOR_DISCRIMINATOR,
+};
+
+#[derive(Accounts)]
+pub struct OpenArbitratedEscrow<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program
+    )]
+    pub buyer_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", buyer.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = ANCHOR_DISCRIMINATOR + ArbitratedEscrow::INIT_SPACE,
+        seeds = [
+            b"arbitrated_escrow",
+            buyer.key().as_ref(),
+            maker_profile.next_offer_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub escrow: Account<'info, ArbitratedEscrow>,
+
+    // See `MakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Only needed for mints with extensions `check_mint_extensions` rejects.
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `token_b_amount` from the buyer into the vault and records the
+/// escrow.  It can be released to `seller` until `expires_at`, after which
+/// it can only be refunded, unless a dispute has moved the deadline.
+pub fn deposit_and_open_escrow<'info>(
+    mut context: Context<'_, '_, '_, 'info, OpenArbitratedEscrow<'info>>,
+    seller: Pubkey,
+    arbiter: Pubkey,
+    token_b_amount: u64,
+    expires_at: i64,
+    arbitration_seconds: i64,
+) -> Result<()> {
+    let buyer = context.accounts.buyer.key();
+    require!(
+        seller != buyer && arbiter != buyer && arbiter != seller,
+        ErrorCode::InvalidEscrowParties
+    );
+    require!(token_b_amount > 0, ErrorCode::InvalidAmount);
+    require!(arbitration_seconds > 0, ErrorCode::InvalidArbitrationPeriod);
+    let clock = Clock::get()?;
+    require!(expires_at > clock.unix_timestamp, ErrorCode::ExpiryInPast);
+
+    let accounts = &mut context.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )?;
+
+    transfer_tokens(
+        &accounts.buyer,
+        &accounts.buyer_token_account_b,
+        &accounts.token_mint_b,
+        &accounts.vault,
+        token_b_amount,
+        &accounts.token_program,
+        context.remaining_accounts,
+    )?;
+    let token_b_amount = deposited_amount(&mut accounts.vault)?;
+
+    let id = accounts
+        .maker_profile
+        .open_offer(buyer, context.bumps.maker_profile)?;
+
+    accounts.escrow.set_inner(ArbitratedEscrow {
+        id,
+        buyer,
+        seller,
+        arbiter,
+        token_mint_b: accounts.token_mint_b.key(),
+        token_b_amount,
+        refund_after: expires_at,
+        arbitration_seconds,
+        delivery_confirmed: false,
+        disputed: false,
+        bump: context.bumps.escrow,
+    });
+
+    emit!(ArbitratedEscrowOpened {
+        id,
+        buyer,
+        seller,
+        arbiter,
+        token_mint_b: accounts.token_mint_b.key(),
+        token_b_amount,
+        refund_after: expires_at,
+        slot: clock.slot,
+    });
+    Ok(())
+}
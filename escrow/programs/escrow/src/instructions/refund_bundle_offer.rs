@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::TokenInterface;
+
+use crate::{
+    bundle_mint, empty_bundle_vault, error::ErrorCode, BundleOffer, BundleOfferCancelled,
+    MakerProfile,
+};
+
+#[derive(Accounts)]
+pub struct RefundBundleOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"bundle_offer", maker.key().as_ref(), bundle_offer.id.to_le_bytes().as_ref()],
+        bump = bundle_offer.bump
+    )]
+    pub bundle_offer: Account<'info, BundleOffer>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Returns every offered asset to the maker and closes the vaults.
+///
+/// `remaining_accounts` holds, for each offered asset in order: its mint, its
+/// vault and the maker's token account to refund it to.  As in
+/// `settle_bundle`, offered mints that charge a transfer fee have to be
+/// writable.
+pub fn return_bundle_and_close_vaults<'info>(
+    context: Context<'_, '_, 'info, 'info, RefundBundleOffer<'info>>,
+) -> Result<()> {
+    let accounts = &context.accounts;
+    let offered = accounts.bundle_offer.offered.clone();
+
+    require_eq!(
+        context.remaining_accounts.len(),
+        3 * offered.len(),
+        ErrorCode::BundleAccountMismatch
+    );
+
+    for (asset, remaining) in offered
+        .iter()
+        .zip(context.remaining_accounts.chunks_exact(3))
+    {
+        let mint = bundle_mint(&remaining[0], asset, &accounts.token_program)?;
+
+        empty_bundle_vault(
+            &accounts.bundle_offer,
+            &mint,
+            &remaining[1],
+            &remaining[2],
+            accounts.maker.to_account_info(),
+            &accounts.token_program,
+        )?;
+    }
+
+    emit!(BundleOfferCancelled {
+        id: accounts.bundle_offer.id,
+        maker: accounts.maker.key(),
+        offered,
+        slot: Clock::get()?.slot,
+    });
+
+    context.accounts.maker_profile.close_offer(false);
+    Ok(())
+}
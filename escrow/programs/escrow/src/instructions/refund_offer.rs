@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    close_vault, transfer_from_vault, MakerProfile, Offer, OfferCancelled, ANCHOR_DISCRIMINATOR,
+};
+
+#[derive(Accounts)]
+pub struct RefundOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mut, mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker may have closed their token A account since making the offer.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // See `TakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn return_offered_tokens_and_close_vault<'info>(
+    context: Context<'_, '_, '_, 'info, RefundOffer<'info>>,
+) -> Result<()> {
+    let token_a_refunded_amount = context.accounts.vault.amount;
+
+    transfer_from_vault(
+        &context.accounts.offer,
+        &context.accounts.vault,
+        &context.accounts.token_mint_a,
+        &context.accounts.maker_token_account_a,
+        token_a_refunded_amount,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    close_vault(
+        &context.accounts.offer,
+        &mut context.accounts.vault,
+        &context.accounts.token_mint_a,
+        context.accounts.maker.to_account_info(),
+        &context.accounts.token_program,
+    )?;
+
+    let offer = &context.accounts.offer;
+    emit!(OfferCancelled {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_refunded_amount,
+        expired: false,
+        slot: Clock::get()?.slot,
+    });
+
+    let maker = context.accounts.maker.key();
+    let offer_id = context.accounts.offer.id;
+    let maker_profile = &mut context.accounts.maker_profile;
+    maker_profile.adopt_offer(maker, context.bumps.maker_profile, offer_id)?;
+    maker_profile.close_offer(false);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, MakerProfile, Offer, OfferCancelled, NATIVE_SOL_MINT};
+
+// Closing the offer returns both its rent and the offered lamports.
+#[derive(Accounts)]
+pub struct RefundSolOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        constraint = offer.token_mint_a == NATIVE_SOL_MINT @ ErrorCode::NotNativeSol,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+pub fn return_offered_lamports(context: Context<RefundSolOffer>) -> Result<()> {
+    let offer = &context.accounts.offer;
+    emit!(OfferCancelled {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_refunded_amount: offer.token_a_remaining_amount,
+        expired: false,
+        slot: Clock::get()?.slot,
+    });
+
+    context.accounts.maker_profile.close_offer(false);
+    Ok(())
+}
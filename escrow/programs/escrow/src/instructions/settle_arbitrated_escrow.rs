@@ -0,0 +1,178 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::spl_token_2022::onchain::invoke_transfer_checked,
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    error::ErrorCode, harvest_withheld_fees, ArbitratedEscrow, ArbitratedEscrowSettled,
+    MakerProfile,
+};
+
+// Shared by `release_arbitrated_escrow` and `refund_arbitrated_escrow`.
+// Who may sign depends on the instruction and the state of the escrow, see
+// `ArbitratedEscrow`.  The escrow and vault rent always go back to the buyer.
+#[derive(Accounts)]
+pub struct SettleArbitratedEscrow<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub buyer: SystemAccount<'info>,
+
+    pub seller: SystemAccount<'info>,
+
+    // Writable so transfer fees withheld in the vault can be harvested to it
+    // before the vault is closed.
+    #[account(mut)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program
+    )]
+    pub buyer_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program
+    )]
+    pub seller_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", buyer.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = buyer,
+        has_one = buyer,
+        has_one = seller,
+        has_one = token_mint_b,
+        seeds = [
+            b"arbitrated_escrow",
+            buyer.key().as_ref(),
+            escrow.id.to_le_bytes().as_ref()
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Box<Account<'info, ArbitratedEscrow>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn release_to_seller<'info>(
+    context: Context<'_, '_, '_, 'info, SettleArbitratedEscrow<'info>>,
+) -> Result<()> {
+    let accounts = &context.accounts;
+    require!(
+        accounts
+            .escrow
+            .can_be_released_by(&accounts.authority.key(), Clock::get()?.unix_timestamp),
+        ErrorCode::ReleaseNotAllowed
+    );
+
+    settle(context, true)
+}
+
+pub fn refund_to_buyer<'info>(
+    context: Context<'_, '_, '_, 'info, SettleArbitratedEscrow<'info>>,
+) -> Result<()> {
+    let accounts = &context.accounts;
+    require!(
+        accounts
+            .escrow
+            .can_be_refunded_by(&accounts.authority.key(), Clock::get()?.unix_timestamp),
+        ErrorCode::RefundNotAllowed
+    );
+
+    settle(context, false)
+}
+
+/// Empties the vault to the seller if `released`, otherwise to the buyer,
+/// and closes it.
+fn settle<'info>(
+    mut context: Context<'_, '_, '_, 'info, SettleArbitratedEscrow<'info>>,
+    released: bool,
+) -> Result<()> {
+    let accounts = &mut context.accounts;
+    let escrow = &accounts.escrow;
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"arbitrated_escrow",
+        escrow.buyer.as_ref(),
+        &escrow.id.to_le_bytes()[..],
+        &[escrow.bump],
+    ]];
+
+    let to = if released {
+        &accounts.seller_token_account_b
+    } else {
+        &accounts.buyer_token_account_b
+    };
+    let token_b_amount = accounts.vault.amount;
+    invoke_transfer_checked(
+        &accounts.token_program.key(),
+        accounts.vault.to_account_info(),
+        accounts.token_mint_b.to_account_info(),
+        to.to_account_info(),
+        escrow.to_account_info(),
+        context.remaining_accounts,
+        token_b_amount,
+        accounts.token_mint_b.decimals,
+        &signer_seeds,
+    )?;
+
+    accounts.vault.reload()?;
+    require_eq!(accounts.vault.amount, 0, ErrorCode::VaultNotEmpty);
+    harvest_withheld_fees(
+        accounts.vault.to_account_info(),
+        accounts.token_mint_b.to_account_info(),
+        &accounts.token_program,
+    )?;
+    close_account(CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: accounts.vault.to_account_info(),
+            destination: accounts.buyer.to_account_info(),
+            authority: escrow.to_account_info(),
+        },
+        &signer_seeds,
+    ))?;
+
+    emit!(ArbitratedEscrowSettled {
+        id: escrow.id,
+        buyer: escrow.buyer,
+        seller: escrow.seller,
+        settled_by: accounts.authority.key(),
+        released,
+        token_b_amount,
+        slot: Clock::get()?.slot,
+    });
+
+    if released {
+        accounts.maker_profile.record_fill(token_b_amount);
+    }
+    accounts.maker_profile.close_offer(released);
+    Ok(())
+}
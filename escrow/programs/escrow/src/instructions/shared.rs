@@ -0,0 +1,349 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::{TransferFeeAmount, TransferFeeConfig},
+            BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+        onchain::invoke_transfer_checked,
+    },
+    token_2022_extensions::{harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint},
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    error::ErrorCode, AmountBasis, BundleAsset, BundleOffer, Config, MintAllowance, Offer,
+    OfferMade,
+};
+
+/// Expiry to store on a new offer: `0` if it never expires, otherwise a
+/// timestamp that has to be in the future.
+pub fn offer_expiry(expires_at: Option<i64>, now: i64) -> Result<i64> {
+    match expires_at {
+        Some(expires_at) => {
+            require!(expires_at > now, ErrorCode::ExpiryInPast);
+            Ok(expires_at)
+        }
+        None => Ok(0),
+    }
+}
+
+pub fn emit_offer_made(offer: &Offer, slot: u64) {
+    emit!(OfferMade {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount: offer.token_a_remaining_amount,
+        token_b_wanted_amount: offer.token_b_wanted_amount,
+        expires_at: offer.expires_at,
+        allowed_taker: offer.allowed_taker,
+        pricing: offer.pricing,
+        slot,
+    });
+}
+
+/// Rejects Token-2022 mints with extensions that let someone other than the
+/// escrow move, freeze or hide tokens in its vaults, unless the admin has
+/// allowed the mint with `allow_mint`.
+pub fn check_mint_extensions(
+    mint: &InterfaceAccount<Mint>,
+    allowance: Option<&Account<MintAllowance>>,
+) -> Result<()> {
+    if let Some(allowance) = allowance {
+        require_keys_eq!(allowance.mint, mint.key(), ErrorCode::MintAllowanceMismatch);
+        return Ok(());
+    }
+
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in mint_state.get_extension_types()? {
+        match extension {
+            ExtensionType::PermanentDelegate => {
+                return err!(ErrorCode::PermanentDelegateNotAllowed)
+            }
+            ExtensionType::TransferHook => return err!(ErrorCode::TransferHookNotAllowed),
+            ExtensionType::NonTransferable => return err!(ErrorCode::NonTransferableMint),
+            ExtensionType::ConfidentialTransferMint
+            | ExtensionType::ConfidentialTransferFeeConfig => {
+                return err!(ErrorCode::ConfidentialTransfersNotAllowed)
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Balance of a new vault after the maker's deposit.  Mints with a transfer
+/// fee credit the vault less than the maker sent, and offers are made for what
+/// actually landed.
+pub fn deposited_amount(vault: &mut InterfaceAccount<TokenAccount>) -> Result<u64> {
+    vault.reload()?;
+    require!(vault.amount > 0, ErrorCode::InvalidAmount);
+    Ok(vault.amount)
+}
+
+/// Amount of `mint` to send so that the recipient is credited `amount` under
+/// `basis`.  For `AmountBasis::Net` this adds the Token-2022 transfer fee the
+/// mint charges in the current epoch, if any.
+pub fn amount_to_send(
+    mint: &InterfaceAccount<Mint>,
+    amount: u64,
+    basis: AmountBasis,
+) -> Result<u64> {
+    if basis == AmountBasis::Gross || amount == 0 {
+        return Ok(amount);
+    }
+
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+
+    let fee = transfer_fee_config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    amount
+        .checked_add(fee)
+        .ok_or_else(|| ErrorCode::ArithmeticOverflow.into())
+}
+
+/// What the taker pays for `token_b_amount` of `offer`: the protocol fee plus
+/// the maker's share, grossed up by `amount_to_send`.
+pub fn token_b_spent(
+    config: &Config,
+    token_mint_b: &InterfaceAccount<Mint>,
+    offer: &Offer,
+    token_b_amount: u64,
+) -> Result<u64> {
+    let fee = config.fee_for(token_b_amount)?;
+    let sent = amount_to_send(
+        token_mint_b,
+        token_b_amount - fee,
+        offer.token_b_amount_basis,
+    )?;
+    sent.checked_add(fee)
+        .ok_or_else(|| ErrorCode::ArithmeticOverflow.into())
+}
+
+/// Moves `amount` of `mint` between two token accounts, signed by the wallet
+/// that owns `from`.
+///
+/// If `mint` has a transfer hook, the hook program, its extra account metas
+/// account and every account those list have to be in `extra_accounts`,
+/// usually the instruction's `remaining_accounts`.
+pub fn transfer_tokens<'info>(
+    authority: &Signer<'info>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    token_program: &Interface<'info, TokenInterface>,
+    extra_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    invoke_transfer_checked(
+        &token_program.key(),
+        from.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        authority.to_account_info(),
+        extra_accounts,
+        amount,
+        mint.decimals,
+        &[],
+    )
+    .map_err(Into::into)
+}
+
+/// Moves `amount` of the offered token out of the vault, signing as the `offer`
+/// PDA.  See `transfer_tokens` for `extra_accounts`.
+pub fn transfer_from_vault<'info>(
+    offer: &Account<'info, Offer>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_mint_a: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    token_program: &Interface<'info, TokenInterface>,
+    extra_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"offer",
+        offer.maker.as_ref(),
+        &offer.id.to_le_bytes()[..],
+        &[offer.bump],
+    ]];
+
+    invoke_transfer_checked(
+        &token_program.key(),
+        vault.to_account_info(),
+        token_mint_a.to_account_info(),
+        to.to_account_info(),
+        offer.to_account_info(),
+        extra_accounts,
+        amount,
+        token_mint_a.decimals,
+        &signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Closes the vault, sending its rent to `destination`.  Fails unless the
+/// vault has been emptied.  `token_mint_a` has to be writable, see
+/// `harvest_withheld_fees`.
+pub fn close_vault<'info>(
+    offer: &Account<'info, Offer>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    token_mint_a: &InterfaceAccount<'info, Mint>,
+    destination: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    // `vault.amount` was read before any transfer out of it in this
+    // instruction, so check the balance the token program left behind.
+    vault.reload()?;
+    require_eq!(vault.amount, 0, ErrorCode::VaultNotEmpty);
+
+    harvest_withheld_fees(
+        vault.to_account_info(),
+        token_mint_a.to_account_info(),
+        token_program,
+    )?;
+
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"offer",
+        offer.maker.as_ref(),
+        &offer.id.to_le_bytes()[..],
+        &[offer.bump],
+    ]];
+
+    let accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination,
+        authority: offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+
+    close_account(cpi_context)
+}
+
+/// Moves the Token-2022 transfer fees withheld in `vault` to its mint, which
+/// has to be writable.  The token program refuses to close an account that
+/// still holds withheld fees.  Does nothing for vaults without any.
+pub fn harvest_withheld_fees<'info>(
+    vault: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let withheld_amount = {
+        let data = vault.try_borrow_data()?;
+        let vault_state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+        vault_state
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |transfer_fee_amount| {
+                u64::from(transfer_fee_amount.withheld_amount)
+            })
+    };
+    if withheld_amount == 0 {
+        return Ok(());
+    }
+
+    let accounts = HarvestWithheldTokensToMint {
+        token_program_id: token_program.to_account_info(),
+        mint,
+    };
+    let cpi_context = CpiContext::new(token_program.to_account_info(), accounts);
+    harvest_withheld_tokens_to_mint(cpi_context, vec![vault])
+}
+
+/// Deserializes the mint of `asset` from a remaining account, checking it is
+/// the expected mint and belongs to `token_program`.
+pub fn bundle_mint<'info>(
+    info: &'info AccountInfo<'info>,
+    asset: &BundleAsset,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<InterfaceAccount<'info, Mint>> {
+    require_keys_eq!(info.key(), asset.mint, ErrorCode::BundleAccountMismatch);
+    require_keys_eq!(
+        *info.owner,
+        token_program.key(),
+        ErrorCode::BundleAccountMismatch
+    );
+    InterfaceAccount::try_from(info)
+}
+
+/// Checks that `vault` is the associated token account of `bundle_offer` for
+/// `mint`.
+pub fn check_bundle_vault(
+    bundle_offer: &Pubkey,
+    mint: &Pubkey,
+    vault: &AccountInfo,
+    token_program: &Pubkey,
+) -> Result<()> {
+    let expected = get_associated_token_address_with_program_id(bundle_offer, mint, token_program);
+    require_keys_eq!(vault.key(), expected, ErrorCode::BundleAccountMismatch);
+    Ok(())
+}
+
+/// Moves everything in a bundle vault to `to`, then closes the vault and
+/// sends its rent to `destination`.
+pub fn empty_bundle_vault<'info>(
+    bundle_offer: &Account<'info, BundleOffer>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &'info AccountInfo<'info>,
+    to: &'info AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    check_bundle_vault(
+        &bundle_offer.key(),
+        &mint.key(),
+        vault,
+        &token_program.key(),
+    )?;
+
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"bundle_offer",
+        bundle_offer.maker.as_ref(),
+        &bundle_offer.id.to_le_bytes()[..],
+        &[bundle_offer.bump],
+    ]];
+
+    let mut vault_account = InterfaceAccount::<TokenAccount>::try_from(vault)?;
+
+    let accounts = TransferChecked {
+        from: vault.clone(),
+        mint: mint.to_account_info(),
+        to: to.clone(),
+        authority: bundle_offer.to_account_info(),
+    };
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, &signer_seeds);
+    transfer_checked(cpi_context, vault_account.amount, mint.decimals)?;
+
+    vault_account.reload()?;
+    require_eq!(vault_account.amount, 0, ErrorCode::VaultNotEmpty);
+
+    harvest_withheld_fees(vault.clone(), mint.to_account_info(), token_program)?;
+
+    let accounts = CloseAccount {
+        account: vault.clone(),
+        destination,
+        authority: bundle_offer.to_account_info(),
+    };
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, &signer_seeds);
+    close_account(cpi_context)
+}
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::{
+    bundle_mint, check_mint_extensions, empty_bundle_vault, error::ErrorCode, transfer_tokens,
+    BundleOffer, BundleOfferTaken, Config, MakerProfile,
+};
+
+// Every mint in the bundle must belong to `token_program`.
+#[derive(Accounts)]
+pub struct TakeBundleOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        constraint = !bundle_offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = bundle_offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        seeds = [b"bundle_offer", maker.key().as_ref(), bundle_offer.id.to_le_bytes().as_ref()],
+        bump = bundle_offer.bump
+    )]
+    pub bundle_offer: Account<'info, BundleOffer>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Pays every wanted asset and releases every offered asset, or fails as a
+/// whole.
+///
+/// `remaining_accounts` holds, for each offered asset in order: its mint, its
+/// vault and the token account the taker receives it in.  Then, for each
+/// wanted asset in order: its mint, the taker's token account paying it, the
+/// maker's token account and the treasury's token account for the protocol
+/// fee.  Offered mints have to be writable if they charge a transfer fee.
+pub fn settle_bundle<'info>(
+    context: Context<'_, '_, 'info, 'info, TakeBundleOffer<'info>>,
+) -> Result<()> {
+    let accounts = &context.accounts;
+    let offered = accounts.bundle_offer.offered.clone();
+    let wanted = accounts.bundle_offer.wanted.clone();
+
+    require_eq!(
+        context.remaining_accounts.len(),
+        3 * offered.len() + 4 * wanted.len(),
+        ErrorCode::BundleAccountMismatch
+    );
+    let (offered_accounts, wanted_accounts) =
+        context.remaining_accounts.split_at(3 * offered.len());
+
+    let mut fees = Vec::with_capacity(wanted.len());
+    for (asset, remaining) in wanted.iter().zip(wanted_accounts.chunks_exact(4)) {
+        let mint = bundle_mint(&remaining[0], asset, &accounts.token_program)?;
+        check_mint_extensions(&mint, None)?;
+        let taker_token_account = InterfaceAccount::<TokenAccount>::try_from(&remaining[1])?;
+        let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(&remaining[2])?;
+        let treasury_token_account = InterfaceAccount::<TokenAccount>::try_from(&remaining[3])?;
+
+        require_keys_eq!(
+            maker_token_account.owner,
+            accounts.maker.key(),
+            ErrorCode::BundleAccountMismatch
+        );
+        require_keys_eq!(
+            treasury_token_account.owner,
+            accounts.config.treasury,
+            ErrorCode::BundleAccountMismatch
+        );
+
+        let fee = accounts.config.fee_for(asset.amount)?;
+
+        transfer_tokens(
+            &accounts.taker,
+            &taker_token_account,
+            &mint,
+            &maker_token_account,
+            asset.amount - fee,
+            &accounts.token_program,
+            &[],
+        )?;
+
+        if fee > 0 {
+            transfer_tokens(
+                &accounts.taker,
+                &taker_token_account,
+                &mint,
+                &treasury_token_account,
+                fee,
+                &accounts.token_program,
+                &[],
+            )?;
+        }
+
+        fees.push(fee);
+    }
+
+    for (asset, remaining) in offered.iter().zip(offered_accounts.chunks_exact(3)) {
+        let mint = bundle_mint(&remaining[0], asset, &accounts.token_program)?;
+
+        empty_bundle_vault(
+            &accounts.bundle_offer,
+            &mint,
+            &remaining[1],
+            &remaining[2],
+            accounts.taker.to_account_info(),
+            &accounts.token_program,
+        )?;
+    }
+
+    emit!(BundleOfferTaken {
+        id: accounts.bundle_offer.id,
+        maker: accounts.maker.key(),
+        taker: accounts.taker.key(),
+        offered,
+        wanted,
+        fees,
+        slot: Clock::get()?.slot,
+    });
+
+    let maker_profile = &mut context.accounts.maker_profile;
+    for asset in &context.accounts.bundle_offer.wanted {
+        maker_profile.record_fill(asset.amount);
+    }
+    maker_profile.close_offer(true);
+    Ok(())
+}
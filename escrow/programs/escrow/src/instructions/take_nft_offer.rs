@@ -0,0 +1,284 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    metadata::MetadataAccount,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    amount_to_send, check_mint_extensions, close_vault, creator_royalties, error::ErrorCode,
+    transfer_from_vault, transfer_tokens, Config, MakerProfile, MintAllowance, Offer, OfferTaken,
+    RoyaltyPaid, ANCHOR_DISCRIMINATOR,
+};
+
+// `TakeOffer` for NFT offers, with the NFT's metadata to read royalties from.
+// NFT offers are fixed-price, so there is no price feed.
+#[derive(Accounts)]
+pub struct TakeNftOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    // Writable so transfer fees withheld in the vault can be harvested to it
+    // before the vault is closed.
+    #[account(mut)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only used as the authority of `treasury_token_account_b`, and
+    /// checked against `config.treasury`.
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // See `TakeOffer`.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = token_mint_a,
+        has_one = token_mint_b,
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        constraint = offer.nft.is_some() @ ErrorCode::OfferIsNotNft,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Read again on the take, so royalties follow the metadata as it is now.
+    pub metadata: Box<Account<'info, MetadataAccount>>,
+
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Checks the mints as `take_offer` does, and that the NFT still meets the
+/// terms it was listed under.
+pub fn check_take_nft_offer(ctx: &Context<TakeNftOffer>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )?;
+
+    let nft = accounts.offer.nft.ok_or(ErrorCode::OfferIsNotNft)?;
+    nft.check(&accounts.token_mint_a, &accounts.metadata)
+}
+
+/// Pays what is left of the offer as `send_wanted_tokens_to_maker` does,
+/// except that the creators' royalties come out of the maker's share.
+///
+/// `remaining_accounts` holds two accounts for each creator with a share, in
+/// the order of the NFT's metadata: the creator and their associated token
+/// account for token B, created if needed.  Mints with a transfer hook cannot
+/// be traded this way.
+pub fn pay_maker_and_creators<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, TakeNftOffer<'info>>,
+) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let offer = &accounts.offer;
+    let token_b_amount = offer.token_b_remaining_amount;
+    let fee = accounts.config.fee_for(token_b_amount)?;
+    let royalties = creator_royalties(&accounts.metadata, token_b_amount)?;
+    let creators = ctx.remaining_accounts.chunks_exact(2);
+    require!(
+        creators.len() == royalties.len() && creators.remainder().is_empty(),
+        ErrorCode::RoyaltyAccountMismatch
+    );
+
+    let slot = Clock::get()?.slot;
+    let mut maker_amount = token_b_amount - fee;
+    for ((creator, amount), remaining) in royalties.into_iter().zip(creators) {
+        require_keys_eq!(
+            remaining[0].key(),
+            creator,
+            ErrorCode::RoyaltyAccountMismatch
+        );
+        // Checks the address is the creator's associated token account.
+        create_idempotent(CpiContext::new(
+            accounts.associated_token_program.to_account_info(),
+            Create {
+                payer: accounts.taker.to_account_info(),
+                associated_token: remaining[1].clone(),
+                authority: remaining[0].clone(),
+                mint: accounts.token_mint_b.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+            },
+        ))?;
+        let creator_token_account_b = InterfaceAccount::<TokenAccount>::try_from(&remaining[1])?;
+
+        maker_amount = maker_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        if amount > 0 {
+            transfer_tokens(
+                &accounts.taker,
+                &accounts.taker_token_account_b,
+                &accounts.token_mint_b,
+                &creator_token_account_b,
+                amount,
+                &accounts.token_program,
+                &[],
+            )?;
+        }
+
+        emit!(RoyaltyPaid {
+            id: offer.id,
+            maker: offer.maker,
+            nft_mint: offer.token_mint_a,
+            token_mint_b: offer.token_mint_b,
+            creator,
+            amount,
+            slot,
+        });
+    }
+
+    transfer_tokens(
+        &accounts.taker,
+        &accounts.taker_token_account_b,
+        &accounts.token_mint_b,
+        &accounts.maker_token_account_b,
+        amount_to_send(
+            &accounts.token_mint_b,
+            maker_amount,
+            offer.token_b_amount_basis,
+        )?,
+        &accounts.token_program,
+        &[],
+    )?;
+
+    if fee > 0 {
+        transfer_tokens(
+            &accounts.taker,
+            &accounts.taker_token_account_b,
+            &accounts.token_mint_b,
+            &accounts.treasury_token_account_b,
+            fee,
+            &accounts.token_program,
+            &[],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Sends the NFT to the taker and closes the vault, as
+/// `withdraw_and_close_vault` does.
+pub fn withdraw_nft_and_close_vault<'info>(
+    ctx: Context<'_, '_, 'info, 'info, TakeNftOffer<'info>>,
+) -> Result<()> {
+    let token_a_amount = ctx.accounts.vault.amount;
+    let token_b_amount = ctx.accounts.offer.token_b_remaining_amount;
+
+    transfer_from_vault(
+        &ctx.accounts.offer,
+        &ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        &ctx.accounts.taker_token_account_a,
+        token_a_amount,
+        &ctx.accounts.token_program,
+        &[],
+    )?;
+
+    close_vault(
+        &ctx.accounts.offer,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        ctx.accounts.taker.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    let offer = &ctx.accounts.offer;
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: ctx.accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount,
+        token_b_amount,
+        fee: ctx.accounts.config.fee_for(token_b_amount)?,
+        token_a_remaining_amount: 0,
+        token_b_remaining_amount: 0,
+        slot: Clock::get()?.slot,
+    });
+
+    let maker = ctx.accounts.maker.key();
+    let offer_id = ctx.accounts.offer.id;
+    let maker_profile = &mut ctx.accounts.maker_profile;
+    maker_profile.adopt_offer(maker, ctx.bumps.maker_profile, offer_id)?;
+    maker_profile.record_fill(token_b_amount);
+    maker_profile.close_offer(true);
+    Ok(())
+}
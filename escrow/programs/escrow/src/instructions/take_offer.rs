@@ -2,13 +2,14 @@ use anchor_lang::prelude::*;
 
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{
-        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-        TransferChecked,
-    },
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::Offer;
+use crate::{
+    amount_to_send, check_mint_extensions, close_vault, error::ErrorCode, load_oracle_price,
+    quote_from_feed, transfer_from_vault, transfer_tokens, Config, MakerProfile, MintAllowance,
+    Offer, OfferTaken, Pricing, ANCHOR_DISCRIMINATOR,
+};
 
 #[derive(Accounts)]
 pub struct TakeOffer<'info> {
@@ -18,6 +19,9 @@ pub struct TakeOffer<'info> {
     #[account(mut)]
     pub maker: SystemAccount<'info>,
 
+    // Writable so transfer fees withheld in the vault can be harvested to it
+    // before the vault is closed.
+    #[account(mut)]
     pub token_mint_a: InterfaceAccount<'info, Mint>,
 
     pub token_mint_b: InterfaceAccount<'info, Mint>,
@@ -48,16 +52,53 @@ pub struct TakeOffer<'info> {
     )]
     pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Created by `initialize`; offers cannot be taken until it exists.
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only used as the authority of `treasury_token_account_b`, and
+    /// checked against `config.treasury`.
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Makers of offers from before profiles existed have none until one of
+    // those offers is taken, refunded or closed.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    // Not `close = maker`: a partial fill has to leave the offer open, so the
+    // offer is closed by hand once it is fully filled.
     #[account(
         mut,
-        close = maker,
         has_one = maker,
         has_one = token_mint_a,
         has_one = token_mint_b,
-        // seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
-        // bump = offer.bump
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        constraint = offer.vesting.is_none() @ ErrorCode::OfferVests,
+        constraint = offer.nft.is_none() @ ErrorCode::OfferIsNft,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
     )]
-    offer: Account<'info, Offer>,
+    pub offer: Account<'info, Offer>,
 
     #[account(
         mut,
@@ -67,69 +108,250 @@ pub struct TakeOffer<'info> {
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: Required for offers with `Pricing::Oracle`, and must be the
+    /// feed the offer was made with.  Read with `load_oracle_price`.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    // Only needed for mints with extensions `check_mint_extensions` rejects.
+    // Checked again on every take and update, so the admin can stop trading
+    // in a mint by closing its allowance.  Refunds are not checked, so makers
+    // can always get their tokens back.  Bundles take no allowances and only
+    // trade mints without these extensions.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>) -> Result<()> {
-    let transfer_accounts = TransferChecked {
-        from: ctx.accounts.taker_token_account_b.to_account_info(),
-        mint: ctx.accounts.token_mint_b.to_account_info(),
-        to: ctx.accounts.maker_token_account_b.to_account_info(),
-        authority: ctx.accounts.taker.to_account_info(),
-    };
-
-    let cpi_ctx = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        transfer_accounts,
-    );
+pub fn check_take_offer_mints(ctx: &Context<TakeOffer>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )
+}
 
-    transfer_checked(
-        cpi_ctx,
-        ctx.accounts.offer.token_b_wanted_amount,
-        ctx.accounts.token_mint_b.decimals,
+/// Prices what is left of an oracle-priced or Dutch auction offer, so the
+/// take that follows is settled at the current price.  Fixed-price offers are
+/// left as they are.
+pub fn requote_offer(ctx: &mut Context<TakeOffer>) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    requote(
+        &mut accounts.offer,
+        accounts.price_feed.as_ref().map(AsRef::as_ref),
+        &accounts.token_mint_a,
+        &accounts.token_mint_b,
     )
 }
 
-pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>) -> Result<()> {
-    let signer_seeds: [&[&[u8]]; 1] = [&[
-        b"offer",
-        ctx.accounts.maker.to_account_info().key.as_ref(),
-        &ctx.accounts.offer.id.to_le_bytes()[..],
-        &[ctx.accounts.offer.bump],
-    ]];
-
-    let accounts = TransferChecked {
-        from: ctx.accounts.vault.to_account_info(),
-        mint: ctx.accounts.token_mint_a.to_account_info(),
-        to: ctx.accounts.taker_token_account_a.to_account_info(),
-        authority: ctx.accounts.offer.to_account_info(),
+/// See `requote_offer`.  `price_feed` has to be the feed the offer was made
+/// with, still carrying the same `feed_id`, if it is oracle-priced.
+pub fn requote(
+    offer: &mut Offer,
+    price_feed: Option<&AccountInfo>,
+    token_mint_a: &InterfaceAccount<Mint>,
+    token_mint_b: &InterfaceAccount<Mint>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let token_b_remaining_amount = match offer.pricing {
+        Pricing::Fixed => return Ok(()),
+        Pricing::Oracle {
+            price_feed: offer_price_feed,
+            feed_id,
+            spread_basis_points,
+        } => {
+            let feed = price_feed.ok_or(ErrorCode::PriceFeedMismatch)?;
+            require_keys_eq!(feed.key(), offer_price_feed, ErrorCode::PriceFeedMismatch);
+            let price = load_oracle_price(feed)?;
+            require!(price.feed_id == feed_id, ErrorCode::PriceFeedMismatch);
+
+            quote_from_feed(
+                &price,
+                now,
+                offer.token_a_remaining_amount,
+                token_mint_a.decimals,
+                token_mint_b.decimals,
+                spread_basis_points,
+            )?
+        }
+        Pricing::Dutch(auction) => auction.quote(now, offer.token_a_remaining_amount)?,
     };
 
-    let cpi_context = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
-    );
+    // As in `update_offer`, amounts already filled stay in
+    // `token_b_wanted_amount`.
+    offer.token_b_wanted_amount =
+        offer.token_b_wanted_amount - offer.token_b_remaining_amount + token_b_remaining_amount;
+    offer.token_b_remaining_amount = token_b_remaining_amount;
+    Ok(())
+}
 
-    transfer_checked(
-        cpi_context,
-        ctx.accounts.vault.amount,
-        ctx.accounts.token_mint_a.decimals,
-    );
+/// Pays `token_b_amount` from the taker, minus the protocol fee that goes to
+/// the treasury.  For offers with `AmountBasis::Net`, the taker also pays the
+/// transfer fee on the maker's share.
+pub fn send_wanted_tokens_to_maker<'info>(
+    ctx: &Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+    token_b_amount: u64,
+) -> Result<()> {
+    let fee = ctx.accounts.config.fee_for(token_b_amount)?;
 
-    let accounts = CloseAccount {
-        account: ctx.accounts.vault.to_account_info(),
-        destination: ctx.accounts.taker.to_account_info(),
-        authority: ctx.accounts.offer.to_account_info(),
-    };
+    let accounts = &ctx.accounts;
 
-    let cpi_context = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
+    transfer_wanted_tokens(
         accounts,
-        &signer_seeds,
+        &accounts.maker_token_account_b,
+        amount_to_send(
+            &accounts.token_mint_b,
+            token_b_amount - fee,
+            accounts.offer.token_b_amount_basis,
+        )?,
+        ctx.remaining_accounts,
+    )?;
+
+    if fee > 0 {
+        transfer_wanted_tokens(
+            accounts,
+            &accounts.treasury_token_account_b,
+            fee,
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn transfer_wanted_tokens<'info>(
+    accounts: &TakeOffer<'info>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    extra_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    transfer_tokens(
+        &accounts.taker,
+        &accounts.taker_token_account_b,
+        &accounts.token_mint_b,
+        to,
+        amount,
+        &accounts.token_program,
+        extra_accounts,
+    )
+}
+
+pub fn withdraw_and_close_vault<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+) -> Result<()> {
+    let token_a_amount = ctx.accounts.vault.amount;
+    let token_b_amount = ctx.accounts.offer.token_b_remaining_amount;
+
+    transfer_from_vault(
+        &ctx.accounts.offer,
+        &ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        &ctx.accounts.taker_token_account_a,
+        token_a_amount,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+    )?;
+
+    close_vault(
+        &ctx.accounts.offer,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        ctx.accounts.taker.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    emit_offer_taken(ctx.accounts, token_a_amount, token_b_amount, 0, 0)?;
+
+    adopt_offer(&mut ctx)?;
+    let maker_profile = &mut ctx.accounts.maker_profile;
+    maker_profile.record_fill(token_b_amount);
+    maker_profile.close_offer(true);
+
+    ctx.accounts
+        .offer
+        .close(ctx.accounts.maker.to_account_info())
+}
+
+/// Fills `token_b_amount` of the offer.  See `Offer::token_a_for_fill` for
+/// how the released token A amount is rounded.
+pub fn fill_offer_partially<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+    token_b_amount: u64,
+    min_token_a_amount: u64,
+) -> Result<()> {
+    check_take_offer_mints(&ctx)?;
+    requote_offer(&mut ctx)?;
+    let token_a_amount = ctx.accounts.offer.token_a_for_fill(token_b_amount)?;
+    require!(
+        token_a_amount >= min_token_a_amount,
+        ErrorCode::TakerLimitExceeded
     );
 
-    close_account(cpi_context)
+    send_wanted_tokens_to_maker(&ctx, token_b_amount)?;
+
+    if token_b_amount == ctx.accounts.offer.token_b_remaining_amount {
+        return withdraw_and_close_vault(ctx);
+    }
+
+    transfer_from_vault(
+        &ctx.accounts.offer,
+        &ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        &ctx.accounts.taker_token_account_a,
+        token_a_amount,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+    )?;
+
+    let offer = &mut ctx.accounts.offer;
+    offer.token_a_remaining_amount -= token_a_amount;
+    offer.token_b_remaining_amount -= token_b_amount;
+    adopt_offer(&mut ctx)?;
+    ctx.accounts.maker_profile.record_fill(token_b_amount);
+
+    emit_offer_taken(
+        ctx.accounts,
+        token_a_amount,
+        token_b_amount,
+        ctx.accounts.offer.token_a_remaining_amount,
+        ctx.accounts.offer.token_b_remaining_amount,
+    )
+}
+
+fn adopt_offer(ctx: &mut Context<TakeOffer>) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    accounts.maker_profile.adopt_offer(
+        accounts.maker.key(),
+        ctx.bumps.maker_profile,
+        accounts.offer.id,
+    )
+}
+
+fn emit_offer_taken(
+    accounts: &TakeOffer,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    token_a_remaining_amount: u64,
+    token_b_remaining_amount: u64,
+) -> Result<()> {
+    emit!(OfferTaken {
+        id: accounts.offer.id,
+        maker: accounts.offer.maker,
+        taker: accounts.taker.key(),
+        token_mint_a: accounts.offer.token_mint_a,
+        token_mint_b: accounts.offer.token_mint_b,
+        token_a_amount,
+        token_b_amount,
+        fee: accounts.config.fee_for(token_b_amount)?,
+        token_a_remaining_amount,
+        token_b_remaining_amount,
+        slot: Clock::get()?.slot,
+    });
+    Ok(())
 }
@@ -0,0 +1,166 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    check_mint_extensions, close_vault, error::ErrorCode, transfer_from_vault, Config,
+    MakerProfile, MintAllowance, Offer, OfferTaken, NATIVE_SOL_MINT,
+};
+
+// Takes an offer made with `make_offer_for_sol` in full, paying in lamports.
+#[derive(Accounts)]
+pub struct TakeOfferForSol<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only receives the protocol fee in lamports, and checked against
+    /// `config.treasury`.
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = token_mint_a,
+        constraint = offer.token_mint_b == NATIVE_SOL_MINT @ ErrorCode::NotNativeSol,
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // See `TakeOffer`.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Pays `lamports` from the taker, minus the protocol fee that goes to the
+/// treasury.
+pub fn send_wanted_lamports_to_maker(
+    context: &Context<TakeOfferForSol>,
+    lamports: u64,
+) -> Result<()> {
+    check_mint_extensions(
+        &context.accounts.token_mint_a,
+        context.accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    let fee = context.accounts.config.fee_for(lamports)?;
+
+    let accounts = &context.accounts;
+
+    transfer_wanted_lamports(accounts, accounts.maker.to_account_info(), lamports - fee)?;
+
+    if fee > 0 {
+        transfer_wanted_lamports(accounts, accounts.treasury.to_account_info(), fee)?;
+    }
+
+    Ok(())
+}
+
+fn transfer_wanted_lamports<'info>(
+    accounts: &TakeOfferForSol<'info>,
+    to: AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    let transfer_accounts = Transfer {
+        from: accounts.taker.to_account_info(),
+        to,
+    };
+
+    let cpi_context = CpiContext::new(accounts.system_program.to_account_info(), transfer_accounts);
+
+    transfer(cpi_context, lamports)
+}
+
+pub fn withdraw_and_close_vault_for_sol<'info>(
+    context: Context<'_, '_, '_, 'info, TakeOfferForSol<'info>>,
+) -> Result<()> {
+    let token_a_amount = context.accounts.vault.amount;
+    let lamports = context.accounts.offer.token_b_remaining_amount;
+
+    transfer_from_vault(
+        &context.accounts.offer,
+        &context.accounts.vault,
+        &context.accounts.token_mint_a,
+        &context.accounts.taker_token_account_a,
+        token_a_amount,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    close_vault(
+        &context.accounts.offer,
+        &mut context.accounts.vault,
+        &context.accounts.token_mint_a,
+        context.accounts.taker.to_account_info(),
+        &context.accounts.token_program,
+    )?;
+
+    let offer = &context.accounts.offer;
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: context.accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount,
+        token_b_amount: lamports,
+        fee: context.accounts.config.fee_for(lamports)?,
+        token_a_remaining_amount: 0,
+        token_b_remaining_amount: 0,
+        slot: Clock::get()?.slot,
+    });
+
+    let maker_profile = &mut context.accounts.maker_profile;
+    maker_profile.record_fill(lamports);
+    maker_profile.close_offer(true);
+    Ok(())
+}
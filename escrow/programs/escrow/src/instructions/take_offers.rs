@@ -0,0 +1,393 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::{
+        create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    amount_to_send, check_mint_extensions, close_vault, error::ErrorCode, requote, token_b_spent,
+    transfer_from_vault, transfer_tokens, Config, MakerProfile, MintAllowance, Offer, OfferTaken,
+    Pricing,
+};
+
+// Offer, vault, maker, maker profile, maker's token account for token B and
+// price feed.
+const ACCOUNTS_PER_OFFER: usize = 6;
+
+// Everything `TakeOffer` has that is shared by all offers of the pair.  The
+// rest comes in `remaining_accounts`, see `sweep_offers`.
+#[derive(Accounts)]
+pub struct TakeOffers<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only used as the authority of `treasury_token_account_b`, and
+    /// checked against `config.treasury`.
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// One offer of the sweep, checked the way `TakeOffer` checks its accounts.
+struct SweptOffer<'info> {
+    offer: Account<'info, Offer>,
+    vault: InterfaceAccount<'info, TokenAccount>,
+    maker: SystemAccount<'info>,
+    // `None` for makers whose offers predate profiles.  A sweep cannot create
+    // one, so it leaves their stats alone.
+    maker_profile: Option<Account<'info, MakerProfile>>,
+    maker_token_account_b: InterfaceAccount<'info, TokenAccount>,
+}
+
+/// Fills the offers in `remaining_accounts` in order, in full, until paying
+/// for the next one would take the taker over `max_token_b_amount`.  That one
+/// is filled partially with what is left, and the offers after it are left
+/// alone.  The transfer fee paid on top for `AmountBasis::Net` offers counts
+/// against `max_token_b_amount`.  Fails as a whole if any offer that is
+/// reached fails to settle, or if less than `min_token_a_amount` is bought in
+/// total.
+///
+/// `remaining_accounts` holds `ACCOUNTS_PER_OFFER` accounts for each offer:
+/// the offer, its vault, its maker, the maker's profile, the maker's
+/// associated token account for token B, created if needed, and the offer's
+/// price feed, or the escrow program for offers without one.  Mints with a
+/// transfer hook cannot be swept.
+pub fn sweep_offers<'info>(
+    context: Context<'_, '_, 'info, 'info, TakeOffers<'info>>,
+    max_token_b_amount: u64,
+    min_token_a_amount: u64,
+) -> Result<()> {
+    require!(max_token_b_amount > 0, ErrorCode::InvalidAmount);
+    let offers = context.remaining_accounts.chunks_exact(ACCOUNTS_PER_OFFER);
+    require!(
+        offers.len() > 0 && offers.remainder().is_empty(),
+        ErrorCode::SweepAccountMismatch
+    );
+
+    let accounts = &context.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )?;
+
+    let mut token_b_left = max_token_b_amount;
+    let mut token_a_bought: u64 = 0;
+    for remaining in offers {
+        if token_b_left == 0 {
+            break;
+        }
+
+        // Each offer is loaded only once the one before it has been written
+        // back, so offers of the same maker see each other's profile updates.
+        // An offer listed twice is closed by the time it is loaded again.
+        let mut swept = load_swept_offer(accounts, remaining)?;
+
+        let price_feed = match swept.offer.pricing {
+            Pricing::Fixed | Pricing::Dutch(_) => None,
+            Pricing::Oracle { .. } => Some(&remaining[5]),
+        };
+        requote(
+            &mut swept.offer,
+            price_feed,
+            &accounts.token_mint_a,
+            &accounts.token_mint_b,
+        )?;
+
+        let mut token_b_amount = token_b_left.min(swept.offer.token_b_remaining_amount);
+        // The transfer fee added for `Net` offers never shrinks as the fill
+        // grows, so taking the overshoot off once is enough to fit.
+        let overshoot = token_b_spent(
+            &accounts.config,
+            &accounts.token_mint_b,
+            &swept.offer,
+            token_b_amount,
+        )?
+        .saturating_sub(token_b_left);
+        token_b_amount = token_b_amount.saturating_sub(overshoot);
+        if token_b_amount == 0 {
+            break;
+        }
+        let token_a_amount = match swept.offer.token_a_for_fill(token_b_amount) {
+            Ok(token_a_amount) => token_a_amount,
+            // What is left to spend does not buy any of this offer.
+            Err(error) if error == ErrorCode::FillTooSmall.into() => break,
+            Err(error) => return Err(error),
+        };
+
+        let token_b_spent = token_b_spent(
+            &accounts.config,
+            &accounts.token_mint_b,
+            &swept.offer,
+            token_b_amount,
+        )?;
+        settle_swept_offer(accounts, &mut swept, token_a_amount, token_b_amount)?;
+        token_b_left -= token_b_spent;
+        token_a_bought += token_a_amount;
+    }
+
+    require!(
+        token_b_left < max_token_b_amount,
+        ErrorCode::SweepFilledNothing
+    );
+    require!(
+        token_a_bought >= min_token_a_amount,
+        ErrorCode::TakerLimitExceeded
+    );
+    Ok(())
+}
+
+fn load_swept_offer<'info>(
+    accounts: &TakeOffers<'info>,
+    remaining: &'info [AccountInfo<'info>],
+) -> Result<SweptOffer<'info>> {
+    let offer = Account::<Offer>::try_from(&remaining[0])?;
+    let maker = SystemAccount::try_from(&remaining[2])?;
+
+    require_keys_eq!(offer.maker, maker.key(), ErrorCode::SweepAccountMismatch);
+    require_keys_eq!(
+        offer.token_mint_a,
+        accounts.token_mint_a.key(),
+        ErrorCode::SweepAccountMismatch
+    );
+    require_keys_eq!(
+        offer.token_mint_b,
+        accounts.token_mint_b.key(),
+        ErrorCode::SweepAccountMismatch
+    );
+    require!(
+        !offer.is_expired(Clock::get()?.unix_timestamp),
+        ErrorCode::OfferExpired
+    );
+    require!(
+        offer.can_be_taken_by(&accounts.taker.key()),
+        ErrorCode::TakerNotAllowed
+    );
+    require!(offer.vesting.is_none(), ErrorCode::OfferVests);
+    require!(offer.nft.is_none(), ErrorCode::OfferIsNft);
+
+    let offer_address = Pubkey::create_program_address(
+        &[
+            b"offer",
+            maker.key().as_ref(),
+            offer.id.to_le_bytes().as_ref(),
+            &[offer.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::SweepAccountMismatch)?;
+    require_keys_eq!(offer.key(), offer_address, ErrorCode::SweepAccountMismatch);
+
+    let (maker_profile_address, _) =
+        Pubkey::find_program_address(&[b"maker_profile", maker.key().as_ref()], &crate::ID);
+    require_keys_eq!(
+        remaining[3].key(),
+        maker_profile_address,
+        ErrorCode::SweepAccountMismatch
+    );
+    let maker_profile = if remaining[3].data_is_empty() {
+        None
+    } else {
+        Some(Account::<MakerProfile>::try_from(&remaining[3])?)
+    };
+
+    let token_program = accounts.token_program.key();
+    let vault_address = get_associated_token_address_with_program_id(
+        &offer.key(),
+        &offer.token_mint_a,
+        &token_program,
+    );
+    require_keys_eq!(
+        remaining[1].key(),
+        vault_address,
+        ErrorCode::SweepAccountMismatch
+    );
+    let vault = InterfaceAccount::<TokenAccount>::try_from(&remaining[1])?;
+
+    require_keys_eq!(
+        remaining[4].key(),
+        get_associated_token_address_with_program_id(
+            &maker.key(),
+            &accounts.token_mint_b.key(),
+            &token_program,
+        ),
+        ErrorCode::SweepAccountMismatch
+    );
+    // Past the first offer of a maker the account exists, and the maker has
+    // to stay out of any CPI: offers closed earlier in the sweep paid their
+    // rent to the maker directly, which the runtime would take for lamports
+    // appearing from nowhere once the maker is passed on.
+    if remaining[4].data_is_empty() {
+        create_idempotent(CpiContext::new(
+            accounts.associated_token_program.to_account_info(),
+            Create {
+                payer: accounts.taker.to_account_info(),
+                associated_token: remaining[4].clone(),
+                authority: maker.to_account_info(),
+                mint: accounts.token_mint_b.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+            },
+        ))?;
+    }
+    let maker_token_account_b = InterfaceAccount::<TokenAccount>::try_from(&remaining[4])?;
+
+    Ok(SweptOffer {
+        offer,
+        vault,
+        maker,
+        maker_profile,
+        maker_token_account_b,
+    })
+}
+
+/// Pays `token_b_amount` for `token_a_amount` of the offer, as `take_offer`
+/// or `take_offer_partial` would, and writes the offer and maker profile
+/// back.
+fn settle_swept_offer<'info>(
+    accounts: &TakeOffers<'info>,
+    swept: &mut SweptOffer<'info>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let fee = accounts.config.fee_for(token_b_amount)?;
+
+    transfer_tokens(
+        &accounts.taker,
+        &accounts.taker_token_account_b,
+        &accounts.token_mint_b,
+        &swept.maker_token_account_b,
+        amount_to_send(
+            &accounts.token_mint_b,
+            token_b_amount - fee,
+            swept.offer.token_b_amount_basis,
+        )?,
+        &accounts.token_program,
+        &[],
+    )?;
+
+    if fee > 0 {
+        transfer_tokens(
+            &accounts.taker,
+            &accounts.taker_token_account_b,
+            &accounts.token_mint_b,
+            &accounts.treasury_token_account_b,
+            fee,
+            &accounts.token_program,
+            &[],
+        )?;
+    }
+
+    let filled = token_b_amount == swept.offer.token_b_remaining_amount;
+    // A full fill empties the vault, as `withdraw_and_close_vault` does.
+    let token_a_amount = if filled {
+        swept.vault.amount
+    } else {
+        token_a_amount
+    };
+
+    transfer_from_vault(
+        &swept.offer,
+        &swept.vault,
+        &accounts.token_mint_a,
+        &accounts.taker_token_account_a,
+        token_a_amount,
+        &accounts.token_program,
+        &[],
+    )?;
+
+    let offer = &mut swept.offer;
+    if filled {
+        offer.token_a_remaining_amount = 0;
+    } else {
+        offer.token_a_remaining_amount -= token_a_amount;
+    }
+    offer.token_b_remaining_amount -= token_b_amount;
+
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount,
+        token_b_amount,
+        fee,
+        token_a_remaining_amount: offer.token_a_remaining_amount,
+        token_b_remaining_amount: offer.token_b_remaining_amount,
+        slot: Clock::get()?.slot,
+    });
+
+    if filled {
+        close_vault(
+            &swept.offer,
+            &mut swept.vault,
+            &accounts.token_mint_a,
+            accounts.taker.to_account_info(),
+            &accounts.token_program,
+        )?;
+        swept.offer.close(swept.maker.to_account_info())?;
+    } else {
+        swept.offer.exit(&crate::ID)?;
+    }
+
+    match &mut swept.maker_profile {
+        Some(maker_profile) => {
+            maker_profile.record_fill(token_b_amount);
+            if filled {
+                maker_profile.close_offer(true);
+            }
+            maker_profile.exit(&crate::ID)
+        }
+        None => Ok(()),
+    }
+}
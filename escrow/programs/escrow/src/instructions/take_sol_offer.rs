@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    amount_to_send, check_mint_extensions, error::ErrorCode, transfer_tokens, Config, MakerProfile,
+    MintAllowance, Offer, OfferTaken, NATIVE_SOL_MINT,
+};
+
+// Takes an offer made with `make_sol_offer` in full, paying in token B.
+#[derive(Accounts)]
+pub struct TakeSolOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only used as the authority of `treasury_token_account_b`, and
+    /// checked against `config.treasury`.
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Closing the offer returns its rent to the maker.  The offered lamports
+    // are moved to the taker before that.
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = token_mint_b,
+        constraint = offer.token_mint_a == NATIVE_SOL_MINT @ ErrorCode::NotNativeSol,
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // See `TakeOffer`.
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Pays `token_b_amount` from the taker, minus the protocol fee that goes to
+/// the treasury.  As in `take_offer`, the taker also pays the transfer fee on
+/// the maker's share for offers with `AmountBasis::Net`.
+pub fn send_wanted_tokens_for_lamports<'info>(
+    context: &Context<'_, '_, '_, 'info, TakeSolOffer<'info>>,
+    token_b_amount: u64,
+) -> Result<()> {
+    check_mint_extensions(
+        &context.accounts.token_mint_b,
+        context.accounts.token_mint_b_allowance.as_ref(),
+    )?;
+    let fee = context.accounts.config.fee_for(token_b_amount)?;
+
+    let accounts = &context.accounts;
+
+    transfer_tokens(
+        &accounts.taker,
+        &accounts.taker_token_account_b,
+        &accounts.token_mint_b,
+        &accounts.maker_token_account_b,
+        amount_to_send(
+            &accounts.token_mint_b,
+            token_b_amount - fee,
+            accounts.offer.token_b_amount_basis,
+        )?,
+        &accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    if fee > 0 {
+        transfer_tokens(
+            &accounts.taker,
+            &accounts.taker_token_account_b,
+            &accounts.token_mint_b,
+            &accounts.treasury_token_account_b,
+            fee,
+            &accounts.token_program,
+            context.remaining_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Moves the offered lamports from the offer account to the taker.  The offer
+/// itself is closed to the maker once the instruction ends.
+pub fn withdraw_offered_lamports(context: Context<TakeSolOffer>) -> Result<()> {
+    let lamports = context.accounts.offer.token_a_remaining_amount;
+    let token_b_amount = context.accounts.offer.token_b_remaining_amount;
+
+    context.accounts.offer.sub_lamports(lamports)?;
+    context.accounts.taker.add_lamports(lamports)?;
+
+    let offer = &context.accounts.offer;
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: context.accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount: lamports,
+        token_b_amount,
+        fee: context.accounts.config.fee_for(token_b_amount)?,
+        token_a_remaining_amount: 0,
+        token_b_remaining_amount: 0,
+        slot: Clock::get()?.slot,
+    });
+
+    let maker_profile = &mut context.accounts.maker_profile;
+    maker_profile.record_fill(token_b_amount);
+    maker_profile.close_offer(true);
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    amount_to_send, check_mint_extensions, close_vault, deposited_amount, error::ErrorCode,
+    transfer_from_vault, transfer_tokens, Config, MakerProfile, MintAllowance, Offer, OfferTaken,
+    Vesting, ANCHOR_DISCRIMINATOR,
+};
+
+// `TakeOffer` for vesting offers, with token A going to `vesting_vault`
+// instead of the taker.  Vesting offers are fixed-price, so there is no price
+// feed.
+#[derive(Accounts)]
+pub struct TakeVestingOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    // Writable so transfer fees withheld in the vault can be harvested to it
+    // before the vault is closed.
+    #[account(mut)]
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: Only used as the authority of `treasury_token_account_b`, and
+    /// checked against `config.treasury`.
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump = maker_profile.bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = token_mint_a,
+        has_one = token_mint_b,
+        constraint = !offer.is_expired(Clock::get()?.unix_timestamp) @ ErrorCode::OfferExpired,
+        constraint = offer.can_be_taken_by(&taker.key()) @ ErrorCode::TakerNotAllowed,
+        constraint = offer.vesting.is_some() @ ErrorCode::OfferDoesNotVest,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = taker,
+        space = ANCHOR_DISCRIMINATOR + Vesting::INIT_SPACE,
+        seeds = [b"vesting", offer.key().as_ref()],
+        bump
+    )]
+    pub vesting: Box<Account<'info, Vesting>>,
+
+    // `init_if_needed` for the reason given on `MakeOffer::vault`: the
+    // vesting PDA, and so this address, is known as soon as the offer exists.
+    // Tokens already in it are counted by `deposited_amount`.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = vesting,
+        associated_token::token_program = token_program,
+    )]
+    pub vesting_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+    pub token_mint_b_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn check_take_vesting_offer_mints(ctx: &Context<TakeVestingOffer>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    check_mint_extensions(
+        &accounts.token_mint_a,
+        accounts.token_mint_a_allowance.as_ref(),
+    )?;
+    check_mint_extensions(
+        &accounts.token_mint_b,
+        accounts.token_mint_b_allowance.as_ref(),
+    )
+}
+
+/// Pays what is left of the offer to the maker, less the protocol fee, as
+/// `send_wanted_tokens_to_maker` does.
+pub fn send_wanted_tokens_for_vesting<'info>(
+    ctx: &Context<'_, '_, '_, 'info, TakeVestingOffer<'info>>,
+) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let token_b_amount = accounts.offer.token_b_remaining_amount;
+    let fee = accounts.config.fee_for(token_b_amount)?;
+
+    transfer_tokens(
+        &accounts.taker,
+        &accounts.taker_token_account_b,
+        &accounts.token_mint_b,
+        &accounts.maker_token_account_b,
+        amount_to_send(
+            &accounts.token_mint_b,
+            token_b_amount - fee,
+            accounts.offer.token_b_amount_basis,
+        )?,
+        &accounts.token_program,
+        ctx.remaining_accounts,
+    )?;
+
+    if fee > 0 {
+        transfer_tokens(
+            &accounts.taker,
+            &accounts.taker_token_account_b,
+            &accounts.token_mint_b,
+            &accounts.treasury_token_account_b,
+            fee,
+            &accounts.token_program,
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Empties the vault into `vesting_vault` and starts the schedule.  The
+/// vesting account is for what actually landed, after any transfer fee.
+pub fn move_vault_into_vesting<'info>(
+    ctx: Context<'_, '_, '_, 'info, TakeVestingOffer<'info>>,
+) -> Result<()> {
+    let token_a_amount = ctx.accounts.vault.amount;
+    let token_b_amount = ctx.accounts.offer.token_b_remaining_amount;
+
+    transfer_from_vault(
+        &ctx.accounts.offer,
+        &ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        &ctx.accounts.vesting_vault,
+        token_a_amount,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+    )?;
+
+    close_vault(
+        &ctx.accounts.offer,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.token_mint_a,
+        ctx.accounts.taker.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    let total_amount = deposited_amount(&mut ctx.accounts.vesting_vault)?;
+    let schedule = ctx
+        .accounts
+        .offer
+        .vesting
+        .ok_or(ErrorCode::OfferDoesNotVest)?;
+    let clock = Clock::get()?;
+    let start_at = clock.unix_timestamp;
+    let cliff_at = start_at
+        .checked_add(schedule.cliff_seconds)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let end_at = start_at
+        .checked_add(schedule.duration_seconds)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    ctx.accounts.vesting.set_inner(Vesting {
+        beneficiary: ctx.accounts.taker.key(),
+        mint: ctx.accounts.token_mint_a.key(),
+        offer: ctx.accounts.offer.key(),
+        total_amount,
+        claimed_amount: 0,
+        start_at,
+        cliff_at,
+        end_at,
+        bump: ctx.bumps.vesting,
+    });
+
+    let offer = &ctx.accounts.offer;
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: ctx.accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_amount,
+        token_b_amount,
+        fee: ctx.accounts.config.fee_for(token_b_amount)?,
+        token_a_remaining_amount: 0,
+        token_b_remaining_amount: 0,
+        slot: clock.slot,
+    });
+
+    let maker_profile = &mut ctx.accounts.maker_profile;
+    maker_profile.record_fill(token_b_amount);
+    maker_profile.close_offer(true);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, treasury_is_rent_exempt, Config, MAX_FEE_BASIS_POINTS};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTreasury<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = treasury_is_rent_exempt(&treasury)? @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury: SystemAccount<'info>,
+}
+
+pub fn set_fee(context: Context<UpdateConfig>, fee_basis_points: u16) -> Result<()> {
+    require!(
+        fee_basis_points <= MAX_FEE_BASIS_POINTS,
+        ErrorCode::FeeTooHigh
+    );
+
+    context.accounts.config.fee_basis_points = fee_basis_points;
+    Ok(())
+}
+
+pub fn set_treasury(context: Context<UpdateTreasury>) -> Result<()> {
+    context.accounts.config.treasury = context.accounts.treasury.key();
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    check_mint_extensions, error::ErrorCode, transfer_from_vault, transfer_tokens, MintAllowance,
+    Offer, OfferUpdated, Pricing,
+};
+
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // See `TakeOffer`.
+    pub token_mint_a_allowance: Option<Account<'info, MintAllowance>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Replaces what is left of the offer with `token_a_amount` for
+/// `token_b_amount`, moving the difference in token A between the maker and
+/// the vault.  Amounts already filled are kept in `token_b_wanted_amount`.
+///
+/// Offers that are not fixed-price are quoted again on every take, so for
+/// them `token_b_amount` has to be the current `token_b_remaining_amount`.
+pub fn update_offer_terms<'info>(
+    context: Context<'_, '_, '_, 'info, UpdateOffer<'info>>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    require!(
+        token_a_amount > 0 && token_b_amount > 0,
+        ErrorCode::InvalidAmount
+    );
+    check_mint_extensions(
+        &context.accounts.token_mint_a,
+        context.accounts.token_mint_a_allowance.as_ref(),
+    )?;
+
+    let old_token_a_amount = context.accounts.offer.token_a_remaining_amount;
+    let old_token_b_amount = context.accounts.offer.token_b_remaining_amount;
+    require!(
+        context.accounts.offer.pricing == Pricing::Fixed || token_b_amount == old_token_b_amount,
+        ErrorCode::OfferIsQuoted
+    );
+
+    let token_a_amount = if token_a_amount > old_token_a_amount {
+        let vault_balance = context.accounts.vault.amount;
+        transfer_tokens(
+            &context.accounts.maker,
+            &context.accounts.maker_token_account_a,
+            &context.accounts.token_mint_a,
+            &context.accounts.vault,
+            token_a_amount - old_token_a_amount,
+            &context.accounts.token_program,
+            context.remaining_accounts,
+        )?;
+
+        // As when the offer was made, a top-up only counts for what landed
+        // in the vault after any transfer fee.
+        context.accounts.vault.reload()?;
+        old_token_a_amount + (context.accounts.vault.amount - vault_balance)
+    } else {
+        if token_a_amount < old_token_a_amount {
+            transfer_from_vault(
+                &context.accounts.offer,
+                &context.accounts.vault,
+                &context.accounts.token_mint_a,
+                &context.accounts.maker_token_account_a,
+                old_token_a_amount - token_a_amount,
+                &context.accounts.token_program,
+                context.remaining_accounts,
+            )?;
+        }
+        token_a_amount
+    };
+
+    let offer = &mut context.accounts.offer;
+    offer.token_b_wanted_amount = (offer.token_b_wanted_amount - old_token_b_amount)
+        .checked_add(token_b_amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    offer.token_a_remaining_amount = token_a_amount;
+    offer.token_b_remaining_amount = token_b_amount;
+
+    emit!(OfferUpdated {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        old_token_a_amount,
+        new_token_a_amount: token_a_amount,
+        old_token_b_amount,
+        new_token_b_amount: token_b_amount,
+        slot: Clock::get()?.slot,
+    });
+    Ok(())
+}
@@ -1,11 +1,13 @@
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
 use anchor_lang::prelude::*;
 
 pub use constants::*;
+pub use events::*;
 pub use instructions::*;
 pub use state::*;
 
@@ -15,22 +17,393 @@ declare_id!("DtCEEm9wHE5Upbeqbpduf5gGzfz1isa9m66q8wWkWyNw");
 pub mod escrow {
     use super::*;
 
-    // pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-    //     initialize::handler(ctx)
-    // }
+    /// Creates the `Config` holding the protocol fee and treasury. Has to be
+    /// run by the program's upgrade authority right after deploying: every
+    /// take reads the fee from the config, so no offer can be taken before it
+    /// exists.
+    ///
+    /// The treasury has to be a system account holding at least the
+    /// rent-exempt minimum, so that fees paid in lamports can always land.
+    pub fn initialize(context: Context<Initialize>, fee_basis_points: u16) -> Result<()> {
+        instructions::initialize::save_config(context, fee_basis_points)
+    }
+
+    pub fn update_fee(context: Context<UpdateConfig>, fee_basis_points: u16) -> Result<()> {
+        instructions::update_config::set_fee(context, fee_basis_points)
+    }
+
+    /// Sends fees to the `treasury` account from now on, which is held to the
+    /// same rule as in `initialize`.
+    pub fn update_treasury(context: Context<UpdateTreasury>) -> Result<()> {
+        instructions::update_config::set_treasury(context)
+    }
+
+    /// Lets offers trade `mint` even though it has extensions that are
+    /// rejected by default.
+    ///
+    /// Instructions moving a mint with a transfer hook take the hook program,
+    /// its extra account metas account and the accounts it lists, in that
+    /// order, as `remaining_accounts`.
+    pub fn allow_mint(context: Context<AllowMint>) -> Result<()> {
+        instructions::allow_mint::save_mint_allowance(context)
+    }
+
+    pub fn disallow_mint(_context: Context<DisallowMint>) -> Result<()> {
+        // Closing the allowance is all there is to it.
+        Ok(())
+    }
+
+    pub fn make_offer<'info>(
+        context: Context<'_, '_, '_, 'info, MakeOffer<'info>>,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_offer::check_make_offer_mints(&context)?;
+        instructions::make_offer::send_offered_tokens_to_vault(&context, token_a_offered_amount)?;
+        instructions::make_offer::save_offer(
+            context,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+            Pricing::Fixed,
+            None,
+        )
+    }
+
+    /// Like `make_offer`, but sells `auction.token_a_amount` of token A for an
+    /// amount of token B that falls over the auction window, quoted again
+    /// every time the offer is taken.
+    pub fn make_dutch_offer<'info>(
+        context: Context<'_, '_, '_, 'info, MakeOffer<'info>>,
+        auction: DutchAuction,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_offer::check_make_offer_mints(&context)?;
+        let token_b_wanted_amount = instructions::make_offer::dutch_auction_price(&auction)?;
+        instructions::make_offer::send_offered_tokens_to_vault(&context, auction.token_a_amount)?;
+        instructions::make_offer::save_offer(
+            context,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+            Pricing::Dutch(auction),
+            None,
+        )
+    }
 
-    pub fn make_offer(
-        context: Context<MakeOffer>,
-        id: u64,
+    /// Like `make_offer`, but the taker's token A vests on `vesting` rather
+    /// than being paid out on the take.  The offer can only be taken in full,
+    /// with `take_vesting_offer`.
+    pub fn make_vesting_offer<'info>(
+        context: Context<'_, '_, '_, 'info, MakeOffer<'info>>,
         token_a_offered_amount: u64,
         token_b_wanted_amount: u64,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+        vesting: VestingSchedule,
     ) -> Result<()> {
+        instructions::make_offer::check_make_offer_mints(&context)?;
         instructions::make_offer::send_offered_tokens_to_vault(&context, token_a_offered_amount)?;
-        instructions::make_offer::save_offer(context, id, token_b_wanted_amount)
+        instructions::make_offer::save_offer(
+            context,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+            Pricing::Fixed,
+            Some(vesting),
+        )
     }
 
-    pub fn take_offer(context: Context<TakeOffer>) -> Result<()> {
-        instructions::take_offer::send_wanted_tokens_to_maker(&context)?;
+    /// Like `make_offer`, but the amount of token B wanted is quoted from
+    /// `price_feed` every time the offer is taken, `spread_basis_points` above
+    /// (or, if negative, below) the feed price.  `price_feed` has to be a
+    /// fully verified Pyth `PriceUpdateV2` account.
+    pub fn make_oracle_offer<'info>(
+        context: Context<'_, '_, '_, 'info, MakeOffer<'info>>,
+        token_a_offered_amount: u64,
+        spread_basis_points: i16,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_offer::check_make_offer_mints(&context)?;
+        let (pricing, token_b_wanted_amount) = instructions::make_offer::oracle_pricing(
+            &context,
+            token_a_offered_amount,
+            spread_basis_points,
+        )?;
+        instructions::make_offer::send_offered_tokens_to_vault(&context, token_a_offered_amount)?;
+        instructions::make_offer::save_offer(
+            context,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+            pricing,
+            None,
+        )
+    }
+
+    /// Takes the offer in full, failing if it costs the taker more than
+    /// `max_token_b_amount`, counting the transfer fee paid on top for
+    /// `AmountBasis::Net` offers.  Oracle-priced and Dutch auction offers are
+    /// quoted when they are taken, so the quote can differ from what the taker
+    /// saw.
+    pub fn take_offer<'info>(
+        mut context: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+        max_token_b_amount: u64,
+    ) -> Result<()> {
+        instructions::take_offer::check_take_offer_mints(&context)?;
+        instructions::take_offer::requote_offer(&mut context)?;
+        let accounts = &context.accounts;
+        let token_b_amount = accounts.offer.token_b_remaining_amount;
+        require!(
+            token_b_spent(
+                &accounts.config,
+                &accounts.token_mint_b,
+                &accounts.offer,
+                token_b_amount
+            )? <= max_token_b_amount,
+            error::ErrorCode::TakerLimitExceeded
+        );
+        instructions::take_offer::send_wanted_tokens_to_maker(&context, token_b_amount)?;
         instructions::take_offer::withdraw_and_close_vault(context)
     }
+
+    /// Pays `token_b_amount` for part of the offer, failing if that buys less
+    /// than `min_token_a_amount` at the current quote.
+    pub fn take_offer_partial<'info>(
+        context: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+        token_b_amount: u64,
+        min_token_a_amount: u64,
+    ) -> Result<()> {
+        instructions::take_offer::fill_offer_partially(context, token_b_amount, min_token_a_amount)
+    }
+
+    /// Takes several offers of the same pair in one go, cheapest first if the
+    /// taker lists them that way, spending at most `max_token_b_amount` and
+    /// failing unless that buys at least `min_token_a_amount`.
+    pub fn take_offers<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeOffers<'info>>,
+        max_token_b_amount: u64,
+        min_token_a_amount: u64,
+    ) -> Result<()> {
+        instructions::take_offers::sweep_offers(context, max_token_b_amount, min_token_a_amount)
+    }
+
+    /// Takes a vesting offer in full.  The maker is paid as by `take_offer`,
+    /// and token A goes to a `Vesting` account the taker claims it from.
+    pub fn take_vesting_offer<'info>(
+        context: Context<'_, '_, '_, 'info, TakeVestingOffer<'info>>,
+    ) -> Result<()> {
+        instructions::take_vesting_offer::check_take_vesting_offer_mints(&context)?;
+        instructions::take_vesting_offer::send_wanted_tokens_for_vesting(&context)?;
+        instructions::take_vesting_offer::move_vault_into_vesting(context)
+    }
+
+    /// Withdraws whatever has vested since the last claim.
+    pub fn claim_vested<'info>(
+        context: Context<'_, '_, '_, 'info, ClaimVested<'info>>,
+    ) -> Result<()> {
+        instructions::claim_vested::withdraw_vested_tokens(context)
+    }
+
+    /// Sells a single Metaplex NFT, token A, for a fixed amount of token B.
+    /// If `collection` is given, the NFT has to be a verified member of it.
+    /// The offer can only be taken in full, with `take_nft_offer`.
+    pub fn make_nft_offer<'info>(
+        context: Context<'_, '_, '_, 'info, MakeNftOffer<'info>>,
+        token_b_wanted_amount: u64,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+        collection: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_nft_offer::list_nft(
+            context,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+            collection,
+        )
+    }
+
+    /// Takes an NFT offer in full.  The NFT's creators are paid its
+    /// `seller_fee_basis_points` of the price, out of what the maker gets.
+    pub fn take_nft_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeNftOffer<'info>>,
+    ) -> Result<()> {
+        instructions::take_nft_offer::check_take_nft_offer(&context)?;
+        instructions::take_nft_offer::pay_maker_and_creators(&context)?;
+        instructions::take_nft_offer::withdraw_nft_and_close_vault(context)
+    }
+
+    pub fn update_offer<'info>(
+        context: Context<'_, '_, '_, 'info, UpdateOffer<'info>>,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<()> {
+        instructions::update_offer::update_offer_terms(context, token_a_amount, token_b_amount)
+    }
+
+    pub fn refund_offer<'info>(
+        context: Context<'_, '_, '_, 'info, RefundOffer<'info>>,
+    ) -> Result<()> {
+        instructions::refund_offer::return_offered_tokens_and_close_vault(context)
+    }
+
+    pub fn close_expired_offer<'info>(
+        context: Context<'_, '_, '_, 'info, CloseExpiredOffer<'info>>,
+    ) -> Result<()> {
+        instructions::close_expired_offer::return_expired_tokens_and_close_vault(context)
+    }
+
+    /// Moves an offer made by the first deployment of the program to the
+    /// current `Offer` layout, and moves its maker's `next_offer_id` past it.
+    /// Anyone can migrate an offer; the signer pays for the rent of the bytes
+    /// it grows by, and for the maker's profile if there is none yet.
+    pub fn migrate_offer(context: Context<MigrateOffer>) -> Result<()> {
+        instructions::migrate_offer::rewrite_legacy_offer(context)
+    }
+
+    pub fn make_offer_for_sol<'info>(
+        context: Context<'_, '_, '_, 'info, MakeOfferForSol<'info>>,
+        token_a_offered_amount: u64,
+        lamports_wanted: u64,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_offer_for_sol::deposit_offered_tokens(&context, token_a_offered_amount)?;
+        instructions::make_offer_for_sol::save_offer_for_sol(
+            context,
+            lamports_wanted,
+            expires_at,
+            allowed_taker,
+        )
+    }
+
+    pub fn make_sol_offer(
+        context: Context<MakeSolOffer>,
+        lamports_offered: u64,
+        token_b_wanted_amount: u64,
+        token_b_amount_basis: AmountBasis,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_sol_offer::deposit_offered_lamports(&context, lamports_offered)?;
+        instructions::make_sol_offer::save_sol_offer(
+            context,
+            lamports_offered,
+            token_b_wanted_amount,
+            token_b_amount_basis,
+            expires_at,
+            allowed_taker,
+        )
+    }
+
+    pub fn take_offer_for_sol<'info>(
+        context: Context<'_, '_, '_, 'info, TakeOfferForSol<'info>>,
+    ) -> Result<()> {
+        let lamports = context.accounts.offer.token_b_remaining_amount;
+        instructions::take_offer_for_sol::send_wanted_lamports_to_maker(&context, lamports)?;
+        instructions::take_offer_for_sol::withdraw_and_close_vault_for_sol(context)
+    }
+
+    pub fn take_sol_offer<'info>(
+        context: Context<'_, '_, '_, 'info, TakeSolOffer<'info>>,
+    ) -> Result<()> {
+        let token_b_amount = context.accounts.offer.token_b_remaining_amount;
+        instructions::take_sol_offer::send_wanted_tokens_for_lamports(&context, token_b_amount)?;
+        instructions::take_sol_offer::withdraw_offered_lamports(context)
+    }
+
+    pub fn refund_sol_offer(context: Context<RefundSolOffer>) -> Result<()> {
+        instructions::refund_sol_offer::return_offered_lamports(context)
+    }
+
+    pub fn close_expired_sol_offer(context: Context<CloseExpiredSolOffer>) -> Result<()> {
+        instructions::close_expired_sol_offer::return_expired_lamports(context)
+    }
+
+    pub fn make_bundle_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, MakeBundleOffer<'info>>,
+        offered: Vec<BundleAsset>,
+        wanted: Vec<BundleAsset>,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::make_bundle_offer::deposit_bundle(&context, &offered)?;
+        instructions::make_bundle_offer::save_bundle_offer(
+            context,
+            offered,
+            wanted,
+            expires_at,
+            allowed_taker,
+        )
+    }
+
+    pub fn take_bundle_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, TakeBundleOffer<'info>>,
+    ) -> Result<()> {
+        instructions::take_bundle_offer::settle_bundle(context)
+    }
+
+    pub fn refund_bundle_offer<'info>(
+        context: Context<'_, '_, 'info, 'info, RefundBundleOffer<'info>>,
+    ) -> Result<()> {
+        instructions::refund_bundle_offer::return_bundle_and_close_vaults(context)
+    }
+
+    /// Puts `token_b_amount` from the buyer in escrow for `seller`, with
+    /// `arbiter` to settle disputes.  Refunded to the buyer if not settled by
+    /// `expires_at`, or within `arbitration_seconds` of a dispute.
+    pub fn open_arbitrated_escrow<'info>(
+        context: Context<'_, '_, '_, 'info, OpenArbitratedEscrow<'info>>,
+        seller: Pubkey,
+        arbiter: Pubkey,
+        token_b_amount: u64,
+        expires_at: i64,
+        arbitration_seconds: i64,
+    ) -> Result<()> {
+        instructions::open_arbitrated_escrow::deposit_and_open_escrow(
+            context,
+            seller,
+            arbiter,
+            token_b_amount,
+            expires_at,
+            arbitration_seconds,
+        )
+    }
+
+    pub fn confirm_delivery(context: Context<ConfirmDelivery>) -> Result<()> {
+        instructions::confirm_delivery::record_delivery(context)
+    }
+
+    pub fn dispute_arbitrated_escrow(context: Context<DisputeArbitratedEscrow>) -> Result<()> {
+        instructions::dispute_arbitrated_escrow::raise_dispute(context)
+    }
+
+    /// Pays the escrowed tokens to the seller.
+    pub fn release_arbitrated_escrow<'info>(
+        context: Context<'_, '_, '_, 'info, SettleArbitratedEscrow<'info>>,
+    ) -> Result<()> {
+        instructions::settle_arbitrated_escrow::release_to_seller(context)
+    }
+
+    /// Returns the escrowed tokens to the buyer.
+    pub fn refund_arbitrated_escrow<'info>(
+        context: Context<'_, '_, '_, 'info, SettleArbitratedEscrow<'info>>,
+    ) -> Result<()> {
+        instructions::settle_arbitrated_escrow::refund_to_buyer(context)
+    }
 }
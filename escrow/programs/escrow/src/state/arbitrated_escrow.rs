@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+// Token B a buyer has put up for something the seller delivers off-chain,
+// held in the associated token account of this account.  The buyer releases
+// it to the seller once satisfied; if the two disagree, `arbiter` decides.
+// Whatever is still in escrow at `refund_after` goes back to the buyer.
+//
+// The buyer's maker profile gives the escrow its id, as it does for offers.
+#[account]
+#[derive(InitSpace)]
+pub struct ArbitratedEscrow {
+    pub id: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub token_mint_b: Pubkey,
+    // What landed in the vault, after any transfer fee.
+    pub token_b_amount: u64,
+    // Unix timestamp from which anyone can refund the buyer.  Moved to
+    // `arbitration_seconds` after a dispute is raised.
+    pub refund_after: i64,
+    pub arbitration_seconds: i64,
+    pub delivery_confirmed: bool,
+    pub disputed: bool,
+    pub bump: u8,
+}
+
+impl ArbitratedEscrow {
+    pub fn is_timed_out(&self, now: i64) -> bool {
+        now >= self.refund_after
+    }
+
+    /// The buyer can always pay the seller.  The arbiter can only do so for a
+    /// dispute over a delivery the seller has confirmed, and only until the
+    /// escrow times out.
+    pub fn can_be_released_by(&self, signer: &Pubkey, now: i64) -> bool {
+        *signer == self.buyer
+            || (*signer == self.arbiter
+                && self.disputed
+                && self.delivery_confirmed
+                && !self.is_timed_out(now))
+    }
+
+    /// The seller can always give the buyer their money back, and the arbiter
+    /// can once there is a dispute.  After the timeout, anyone can.
+    pub fn can_be_refunded_by(&self, signer: &Pubkey, now: i64) -> bool {
+        *signer == self.seller
+            || (*signer == self.arbiter && self.disputed)
+            || self.is_timed_out(now)
+    }
+}
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, MAX_BUNDLE_ASSETS};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct BundleAsset {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+// Several offered mints, each in its own vault owned by the bundle offer, for
+// several wanted mints.  Bundles are always taken in full.
+#[account]
+#[derive(InitSpace)]
+pub struct BundleOffer {
+    pub id: u64,
+    pub maker: Pubkey,
+    #[max_len(MAX_BUNDLE_ASSETS)]
+    pub offered: Vec<BundleAsset>,
+    #[max_len(MAX_BUNDLE_ASSETS)]
+    pub wanted: Vec<BundleAsset>,
+    // Same meaning as on `Offer`.
+    pub expires_at: i64,
+    pub allowed_taker: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl BundleOffer {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    pub fn can_be_taken_by(&self, taker: &Pubkey) -> bool {
+        match self.allowed_taker {
+            Some(allowed_taker) => allowed_taker == *taker,
+            None => true,
+        }
+    }
+}
+
+/// Checks one side of a bundle: between one and `MAX_BUNDLE_ASSETS` assets,
+/// no zero amounts and no mint listed twice.
+pub fn validate_bundle_assets(assets: &[BundleAsset]) -> Result<()> {
+    require!(
+        !assets.is_empty() && assets.len() <= MAX_BUNDLE_ASSETS,
+        ErrorCode::InvalidBundleSize
+    );
+
+    for (index, asset) in assets.iter().enumerate() {
+        require!(asset.amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            assets[..index].iter().all(|seen| seen.mint != asset.mint),
+            ErrorCode::DuplicateBundleMint
+        );
+    }
+
+    Ok(())
+}
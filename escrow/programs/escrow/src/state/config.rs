@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, BASIS_POINTS_DENOMINATOR};
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    // Protocol fee taken from the token B leg of every fill.
+    pub fee_basis_points: u16,
+    // Wallet that owns the fee token accounts.
+    pub treasury: Pubkey,
+    pub bump: u8,
+}
+
+impl Config {
+    /// Protocol fee on `token_b_amount`, rounded down in favor of the maker.
+    pub fn fee_for(&self, token_b_amount: u64) -> Result<u64> {
+        u128::from(token_b_amount)
+            .checked_mul(u128::from(self.fee_basis_points))
+            .map(|fee| fee / u128::from(BASIS_POINTS_DENOMINATOR))
+            .and_then(|fee| u64::try_from(fee).ok())
+            .ok_or(ErrorCode::ArithmeticOverflow.into())
+    }
+}
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// One per maker, created along with their first offer.  Every offer the maker
+// makes, of any kind, takes its id from `next_offer_id`; the rest is kept for
+// UIs to display.  Makers of offers from before profiles existed get theirs
+// when one of those offers is taken, refunded or closed.
+#[account]
+#[derive(InitSpace)]
+pub struct MakerProfile {
+    pub maker: Pubkey,
+    pub next_offer_id: u64,
+    // Offers made and not yet filled, refunded or closed.
+    pub open_offers: u64,
+    // Offers filled in full, by one take or several partial ones.
+    pub filled_offers: u64,
+    // Token B paid for the maker's offers over every fill, protocol fee
+    // included.  Amounts are in base units of whatever each offer wanted, so
+    // different mints are simply added up.
+    pub volume: u128,
+    pub bump: u8,
+}
+
+impl MakerProfile {
+    /// Records a new offer by `maker`, returning its id.  `maker` and `bump`
+    /// are set every time since they are still zeroed on the first offer.
+    pub fn open_offer(&mut self, maker: Pubkey, bump: u8) -> Result<u64> {
+        let id = self.next_offer_id;
+        self.maker = maker;
+        self.bump = bump;
+        self.next_offer_id = id.checked_add(1).ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.open_offers += 1;
+        Ok(id)
+    }
+
+    /// Sets up the profile of `maker` if it was just created by a take,
+    /// refund or close of offer `offer_id`.  Offers from before profiles
+    /// existed have ids the maker picked, so `next_offer_id` is moved past
+    /// `offer_id` to keep new offers from landing on its address.
+    pub fn adopt_offer(&mut self, maker: Pubkey, bump: u8, offer_id: u64) -> Result<()> {
+        let next_id = offer_id
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.maker = maker;
+        self.bump = bump;
+        self.next_offer_id = self.next_offer_id.max(next_id);
+        Ok(())
+    }
+
+    /// Records a fill paying `token_b_amount` for one of the maker's offers.
+    pub fn record_fill(&mut self, token_b_amount: u64) {
+        self.volume = self.volume.saturating_add(u128::from(token_b_amount));
+    }
+
+    /// Records that one of the maker's offers was closed, either because it
+    /// was filled in full or because it was refunded or expired.
+    pub fn close_offer(&mut self, filled: bool) {
+        self.open_offers = self.open_offers.saturating_sub(1);
+        if filled {
+            self.filled_offers += 1;
+        }
+    }
+}
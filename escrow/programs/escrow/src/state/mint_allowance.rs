@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+// Lets the admin vouch for a mint whose extensions `check_mint_extensions`
+// would otherwise reject.  Only `allow_mint` creates these.
+#[account]
+#[derive(InitSpace)]
+pub struct MintAllowance {
+    pub mint: Pubkey,
+    pub bump: u8,
+}
@@ -1,2 +1,29 @@
+pub mod config;
+pub use config::*;
+
 pub mod offer;
 pub use offer::*;
+
+pub mod bundle_offer;
+pub use bundle_offer::*;
+
+pub mod pricing;
+pub use pricing::*;
+
+pub mod oracle;
+pub use oracle::*;
+
+pub mod mint_allowance;
+pub use mint_allowance::*;
+
+pub mod maker_profile;
+pub use maker_profile::*;
+
+pub mod vesting;
+pub use vesting::*;
+
+pub mod arbitrated_escrow;
+pub use arbitrated_escrow::*;
+
+pub mod nft;
+pub use nft::*;
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{metadata::MetadataAccount, token_interface::Mint};
+
+use crate::{error::ErrorCode, BASIS_POINTS_DENOMINATOR};
+
+// Set on offers made with `make_nft_offer`, which sell a single Metaplex NFT
+// and can only be taken with `take_nft_offer`, so its creators are paid
+// their royalties.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct NftTerms {
+    // Collection the NFT has to be a verified member of, if any.
+    pub collection: Option<Pubkey>,
+}
+
+impl NftTerms {
+    /// Checks that `mint` is an NFT, that `metadata` is its Metaplex metadata
+    /// and that it belongs to `collection`.
+    ///
+    /// Only the token metadata program can write accounts it owns, and it
+    /// only stores a mint in the metadata account derived from that mint, so
+    /// matching `metadata.mint` is enough to trust the account.
+    pub fn check(&self, mint: &InterfaceAccount<Mint>, metadata: &MetadataAccount) -> Result<()> {
+        require!(mint.decimals == 0 && mint.supply == 1, ErrorCode::NotAnNft);
+        require_keys_eq!(metadata.mint, mint.key(), ErrorCode::NftMetadataMismatch);
+
+        if let Some(collection) = self.collection {
+            require!(
+                metadata
+                    .collection
+                    .as_ref()
+                    .is_some_and(|member_of| member_of.verified && member_of.key == collection),
+                ErrorCode::NftNotInCollection
+            );
+        }
+        Ok(())
+    }
+}
+
+/// What each creator in `metadata` is owed on a sale for `token_b_amount`:
+/// `seller_fee_basis_points` of it, split by share and rounded down in favor
+/// of the seller.  Creators without a share are left out.
+pub fn creator_royalties(
+    metadata: &MetadataAccount,
+    token_b_amount: u64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    let royalty = u128::from(token_b_amount) * u128::from(metadata.seller_fee_basis_points)
+        / u128::from(BASIS_POINTS_DENOMINATOR);
+
+    metadata
+        .creators
+        .iter()
+        .flatten()
+        .filter(|creator| creator.share > 0)
+        .map(|creator| {
+            let amount = u64::try_from(royalty * u128::from(creator.share) / 100)
+                .map_err(|_| ErrorCode::ArithmeticOverflow)?;
+            Ok((creator.address, amount))
+        })
+        .collect()
+}
@@ -1,8 +1,55 @@
 use anchor_lang::prelude::*;
 
+use crate::{
+    error::ErrorCode, NftTerms, Pricing, VestingSchedule, ANCHOR_DISCRIMINATOR,
+    OFFER_RESERVED_SPACE,
+};
+
+// Every field up to `allowed_taker` has a fixed size, so clients can filter
+// `getProgramAccounts` on them at the offsets below.  Do not reorder them;
+// new fields go right before `reserved`, which shrinks by their size, and
+// bump `Offer::VERSION`.  Zeroed reserved bytes read as `None` for an
+// `Option`, so existing offers need no migration.  Offers of the first
+// deployment are `LegacyOffer`s, which `migrate_offer` rewrites as `Offer`s.
 #[account]
 #[derive(InitSpace)]
 pub struct Offer {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_b_wanted_amount: u64,
+    // What is left to trade after partial fills.  Both start at the full
+    // offered / wanted amounts and reach zero together.
+    pub token_a_remaining_amount: u64,
+    pub token_b_remaining_amount: u64,
+    // Unix timestamp after which the offer can no longer be taken, or `0` if
+    // the offer never expires.
+    pub expires_at: i64,
+    // `Offer::VERSION` as of when the offer was made or migrated.  Ahead of
+    // the variable-size fields so it is always at `Offer::VERSION_OFFSET`.
+    pub version: u8,
+    // The only wallet allowed to take the offer, or `None` if anyone can.
+    pub allowed_taker: Option<Pubkey>,
+    pub pricing: Pricing,
+    // Whether the token B amounts are what the taker sends or what the maker
+    // receives after the mint's transfer fee.
+    pub token_b_amount_basis: AmountBasis,
+    pub bump: u8,
+    // Set on offers made with `make_vesting_offer`, which can only be taken
+    // in full with `take_vesting_offer`.
+    pub vesting: Option<VestingSchedule>,
+    // Set on offers made with `make_nft_offer`, which can only be taken with
+    // `take_nft_offer`.
+    pub nft: Option<NftTerms>,
+    pub reserved: [u8; OFFER_RESERVED_SPACE],
+}
+
+// `Offer` as the first deployment laid it out, before partial fills and
+// everything after them.  Those accounts are too small for `Offer`, so they
+// are only read by `migrate_offer`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct LegacyOffer {
     pub id: u64,
     pub maker: Pubkey,
     pub token_mint_a: Pubkey,
@@ -10,3 +57,89 @@ pub struct Offer {
     pub token_b_wanted_amount: u64,
     pub bump: u8,
 }
+
+impl LegacyOffer {
+    /// The offer in the current layout, with `token_a_remaining_amount` read
+    /// from its vault.  Legacy offers could only be taken in full, so all of
+    /// `token_b_wanted_amount` is still wanted, and every field they predate
+    /// takes the value offers are made with by default.
+    pub fn into_offer(self, token_a_remaining_amount: u64) -> Offer {
+        Offer {
+            id: self.id,
+            maker: self.maker,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_b_wanted_amount: self.token_b_wanted_amount,
+            token_a_remaining_amount,
+            token_b_remaining_amount: self.token_b_wanted_amount,
+            expires_at: 0,
+            version: Offer::VERSION,
+            allowed_taker: None,
+            pricing: Pricing::Fixed,
+            token_b_amount_basis: AmountBasis::Gross,
+            bump: self.bump,
+            vesting: None,
+            nft: None,
+            reserved: [0; OFFER_RESERVED_SPACE],
+        }
+    }
+}
+
+/// How an amount relates to the Token-2022 transfer fee of its mint.  Both
+/// are the same for mints without a transfer fee.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum AmountBasis {
+    // The amount sent; the recipient is credited that minus the fee.
+    Gross,
+    // The amount the recipient is credited; the sender pays the fee on top.
+    Net,
+}
+
+impl Offer {
+    pub const VERSION: u8 = 1;
+
+    // Offsets into the account data, discriminator included.
+    pub const MAKER_OFFSET: usize = ANCHOR_DISCRIMINATOR + 8;
+    pub const TOKEN_MINT_A_OFFSET: usize = Self::MAKER_OFFSET + 32;
+    pub const TOKEN_MINT_B_OFFSET: usize = Self::TOKEN_MINT_A_OFFSET + 32;
+    // Past the end of a `LegacyOffer` account.
+    pub const VERSION_OFFSET: usize = Self::TOKEN_MINT_B_OFFSET + 32 + 4 * 8;
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    pub fn can_be_taken_by(&self, taker: &Pubkey) -> bool {
+        match self.allowed_taker {
+            Some(allowed_taker) => allowed_taker == *taker,
+            None => true,
+        }
+    }
+
+    /// Amount of token A released to a taker paying `token_b_amount`.
+    ///
+    /// Partial fills are priced pro rata against the remaining amounts and
+    /// rounded down, so every fill pays the maker at least the offer price.
+    /// The fill that pays the last of token B releases everything left in the
+    /// vault, so in total the maker gets exactly `token_b_wanted_amount`.
+    pub fn token_a_for_fill(&self, token_b_amount: u64) -> Result<u64> {
+        require!(
+            token_b_amount <= self.token_b_remaining_amount,
+            ErrorCode::FillExceedsRemaining
+        );
+
+        if token_b_amount == self.token_b_remaining_amount {
+            return Ok(self.token_a_remaining_amount);
+        }
+
+        let token_a_amount = u128::from(token_b_amount)
+            .checked_mul(u128::from(self.token_a_remaining_amount))
+            .and_then(|amount| amount.checked_div(u128::from(self.token_b_remaining_amount)))
+            .and_then(|amount| u64::try_from(amount).ok())
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        require!(token_a_amount > 0, ErrorCode::FillTooSmall);
+
+        Ok(token_a_amount)
+    }
+}
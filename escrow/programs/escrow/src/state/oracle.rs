@@ -0,0 +1,130 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{error::ErrorCode, PYTH_RECEIVER_PROGRAM_ID};
+
+// One whole token A is worth `price * 10^exponent` whole tokens B, as of
+// `publish_time`.  `feed_id` names the price series, which whoever writes the
+// account can change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+/// An oracle account that `Pricing::Oracle` offers can be quoted from.
+pub trait PriceSource {
+    /// The latest price in the account, failing if the oracle does not
+    /// vouch for it.
+    fn oracle_price(&self) -> Result<OraclePrice>;
+}
+
+/// Reads the price in `price_feed`, which has to be owned by an oracle the
+/// program supports.  Only Pyth is supported on-chain; the `mock-feed`
+/// feature also accepts feeds of the mock feed program, for local testing.
+pub fn load_oracle_price(price_feed: &AccountInfo) -> Result<OraclePrice> {
+    let data = price_feed.try_borrow_data()?;
+
+    if *price_feed.owner == PYTH_RECEIVER_PROGRAM_ID {
+        return PriceUpdateV2::try_deserialize(&mut &data[..])?.oracle_price();
+    }
+
+    #[cfg(feature = "mock-feed")]
+    if *price_feed.owner == mock_feed::ID {
+        return mock_feed::PriceFeed::try_deserialize(&mut &data[..])?.oracle_price();
+    }
+
+    err!(ErrorCode::PriceFeedMismatch)
+}
+
+// The account layout of the Pyth Solana receiver program.  Decoded here
+// rather than through `pyth-sdk-solana`, which pins `solana-program` to 1.16
+// or older.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationLevel {
+    // Checked against fewer Wormhole guardian signatures than a quorum.
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl Discriminator for PriceUpdateV2 {
+    // The first 8 bytes of sha256("account:PriceUpdateV2").
+    const DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+}
+
+impl Owner for PriceUpdateV2 {
+    fn owner() -> Pubkey {
+        PYTH_RECEIVER_PROGRAM_ID
+    }
+}
+
+impl AccountSerialize for PriceUpdateV2 {}
+
+impl AccountDeserialize for PriceUpdateV2 {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        require!(
+            buf.starts_with(&Self::DISCRIMINATOR),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data = buf
+            .get(Self::DISCRIMINATOR.len()..)
+            .ok_or(anchor_lang::error::ErrorCode::AccountDidNotDeserialize)?;
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl PriceSource for PriceUpdateV2 {
+    fn oracle_price(&self) -> Result<OraclePrice> {
+        // Partially verified updates were not signed by enough guardians to
+        // be trusted with funds.
+        require!(
+            self.verification_level == VerificationLevel::Full,
+            ErrorCode::InvalidPrice
+        );
+        Ok(OraclePrice {
+            feed_id: self.price_message.feed_id,
+            price: self.price_message.price,
+            exponent: self.price_message.exponent,
+            publish_time: self.price_message.publish_time,
+        })
+    }
+}
+
+#[cfg(feature = "mock-feed")]
+impl PriceSource for mock_feed::PriceFeed {
+    fn oracle_price(&self) -> Result<OraclePrice> {
+        // A mock feed only ever carries one price series.
+        Ok(OraclePrice {
+            feed_id: [0; 32],
+            price: self.price,
+            exponent: self.exponent,
+            publish_time: self.publish_time,
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, OraclePrice, BASIS_POINTS_DENOMINATOR, MAX_PRICE_AGE_SECONDS};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum Pricing {
+    // `token_b_remaining_amount` is set by the maker.
+    Fixed,
+    // `token_b_remaining_amount` is quoted again from `price_feed` on every
+    // take, `spread_basis_points` above the feed price (below, if negative).
+    // The feed has to still carry the `feed_id` it had when the offer was
+    // made.
+    Oracle {
+        price_feed: Pubkey,
+        feed_id: [u8; 32],
+        spread_basis_points: i16,
+    },
+    // `token_b_remaining_amount` is quoted again from the auction on every
+    // take.
+    Dutch(DutchAuction),
+}
+
+// A price falling linearly from `start_token_b_amount` at `start_at` to
+// `end_token_b_amount` at `end_at`, and staying there.  Both amounts are for
+// `token_a_amount` of token A, the amount the offer was made with; what is
+// left of the offer is priced pro rata.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DutchAuction {
+    pub start_token_b_amount: u64,
+    pub end_token_b_amount: u64,
+    pub token_a_amount: u64,
+    pub start_at: i64,
+    pub end_at: i64,
+}
+
+impl DutchAuction {
+    /// Checks the auction falls to a non-zero floor over a window that has
+    /// not ended by `now`.
+    pub fn validate(&self, now: i64) -> Result<()> {
+        require!(
+            self.start_token_b_amount > self.end_token_b_amount
+                && self.end_token_b_amount > 0
+                && self.token_a_amount > 0
+                && self.start_at < self.end_at
+                && now < self.end_at,
+            ErrorCode::InvalidAuction
+        );
+        Ok(())
+    }
+
+    /// Price of `token_a_amount` at `now`.  The decline is rounded down, in
+    /// favor of the maker.
+    pub fn token_b_amount_at(&self, now: i64) -> u64 {
+        if now <= self.start_at {
+            return self.start_token_b_amount;
+        }
+        if now >= self.end_at {
+            return self.end_token_b_amount;
+        }
+
+        let elapsed = (now - self.start_at) as u128;
+        let window = (self.end_at - self.start_at) as u128;
+        let decline =
+            u128::from(self.start_token_b_amount - self.end_token_b_amount) * elapsed / window;
+        // `decline` is less than the difference between the two amounts.
+        self.start_token_b_amount - decline as u64
+    }
+
+    /// Price of `token_a_remaining_amount` at `now`, rounded up in favor of
+    /// the maker.
+    pub fn quote(&self, now: i64, token_a_remaining_amount: u64) -> Result<u64> {
+        u128::from(self.token_b_amount_at(now))
+            .checked_mul(u128::from(token_a_remaining_amount))
+            .map(|value| value.div_ceil(u128::from(self.token_a_amount)))
+            .and_then(|amount| u64::try_from(amount).ok())
+            .ok_or_else(|| ErrorCode::ArithmeticOverflow.into())
+    }
+}
+
+/// Amount of token B that `token_a_amount` of token A is worth at `feed`,
+/// adjusted by `spread_basis_points` and rounded up in favor of the
+/// maker.  Fails if the price was published more than `MAX_PRICE_AGE_SECONDS`
+/// ago.
+pub fn quote_from_feed(
+    feed: &OraclePrice,
+    now: i64,
+    token_a_amount: u64,
+    token_a_decimals: u8,
+    token_b_decimals: u8,
+    spread_basis_points: i16,
+) -> Result<u64> {
+    require!(
+        now.saturating_sub(feed.publish_time) <= MAX_PRICE_AGE_SECONDS,
+        ErrorCode::StalePrice
+    );
+    require!(feed.price > 0, ErrorCode::InvalidPrice);
+
+    // Both amounts are in base units, so the feed exponent is shifted by the
+    // difference in decimals.
+    let exponent = feed.exponent + i32::from(token_b_decimals) - i32::from(token_a_decimals);
+    let scale = 10u128
+        .checked_pow(exponent.unsigned_abs())
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let spread_factor =
+        i128::from(BASIS_POINTS_DENOMINATOR as i64 + i64::from(spread_basis_points));
+
+    let value = u128::from(token_a_amount)
+        .checked_mul(feed.price as u128)
+        .and_then(|value| value.checked_mul(spread_factor as u128))
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let (numerator, denominator) = if exponent >= 0 {
+        (
+            value.checked_mul(scale),
+            Some(u128::from(BASIS_POINTS_DENOMINATOR)),
+        )
+    } else {
+        (
+            Some(value),
+            scale.checked_mul(u128::from(BASIS_POINTS_DENOMINATOR)),
+        )
+    };
+
+    let token_b_amount = numerator
+        .zip(denominator)
+        .map(|(numerator, denominator)| numerator.div_ceil(denominator))
+        .and_then(|amount| u64::try_from(amount).ok())
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    require!(token_b_amount > 0, ErrorCode::InvalidPrice);
+
+    Ok(token_b_amount)
+}
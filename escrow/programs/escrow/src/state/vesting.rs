@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// How the token A of a vesting offer is released to its taker, counted from
+// the take: nothing until `cliff_seconds` have passed, then linearly from the
+// take until all of it is released after `duration_seconds`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct VestingSchedule {
+    pub cliff_seconds: i64,
+    pub duration_seconds: i64,
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.duration_seconds > 0
+                && self.cliff_seconds >= 0
+                && self.cliff_seconds <= self.duration_seconds,
+            ErrorCode::InvalidVestingSchedule
+        );
+        Ok(())
+    }
+}
+
+// Token A bought from a vesting offer, held for the taker in the associated
+// token account of this account until it vests.  Closed, along with that
+// token account, by the claim that withdraws the last of it.
+#[account]
+#[derive(InitSpace)]
+pub struct Vesting {
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    // The offer the tokens were bought from.  Offers are never reopened, so
+    // it is a unique seed.
+    pub offer: Pubkey,
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub start_at: i64,
+    pub cliff_at: i64,
+    pub end_at: i64,
+    pub bump: u8,
+}
+
+impl Vesting {
+    /// Amount vested by `now`, claimed or not.  Rounded down, so the last of
+    /// it only vests at `end_at`.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        if now < self.cliff_at {
+            return Ok(0);
+        }
+        if now >= self.end_at {
+            return Ok(self.total_amount);
+        }
+
+        // `start_at <= cliff_at <= now < end_at`, so neither is negative.
+        let elapsed = (now - self.start_at) as u128;
+        let duration = (self.end_at - self.start_at) as u128;
+        u64::try_from(u128::from(self.total_amount) * elapsed / duration)
+            .map_err(|_| ErrorCode::ArithmeticOverflow.into())
+    }
+
+    pub fn claimable_amount(&self, now: i64) -> Result<u64> {
+        Ok(self.vested_amount(now)? - self.claimed_amount)
+    }
+}
@@ -0,0 +1,411 @@
+mod common;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode, prelude::Pubkey, solana_program::system_program,
+    AccountDeserialize,
+};
+use anchor_spl::associated_token::spl_associated_token_account;
+use escrow::{error::ErrorCode, ArbitratedEscrow, ArbitratedEscrowSettled};
+
+use common::*;
+
+// Alice buys from Bob, with Carol as the arbiter.  Alice holds 100 USDC.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        wif_balances: [0; 3],
+        ..MarketOptions::default()
+    })
+}
+
+const DAY: i64 = 24 * 60 * 60;
+
+// 50 USDC for a week, with a day for the arbiter to rule on a dispute.
+fn terms(seller: Pubkey, arbiter: Pubkey, now: i64) -> escrow::instruction::OpenArbitratedEscrow {
+    escrow::instruction::OpenArbitratedEscrow {
+        seller,
+        arbiter,
+        token_b_amount: 50_000_000,
+        expires_at: now + 7 * DAY,
+        arbitration_seconds: DAY,
+    }
+}
+
+fn arbitrated_escrow_address(buyer: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"arbitrated_escrow",
+            buyer.as_ref(),
+            id.to_le_bytes().as_ref(),
+        ],
+        &escrow::ID,
+    )
+    .0
+}
+
+impl EscrowTest {
+    fn arbitrated_escrow(&self, address: &Pubkey) -> Option<ArbitratedEscrow> {
+        let account = self.runtime.get_account(address)?;
+        Some(ArbitratedEscrow::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    fn open_arbitrated_escrow(
+        &mut self,
+        buyer: &Pubkey,
+        token_mint_b: &Pubkey,
+        args: escrow::instruction::OpenArbitratedEscrow,
+    ) -> Result<Pubkey, TransactionError> {
+        let escrow = arbitrated_escrow_address(buyer, self.next_offer_id(buyer));
+        let accounts = escrow::accounts::OpenArbitratedEscrow {
+            buyer: *buyer,
+            token_mint_b: *token_mint_b,
+            buyer_token_account_b: self.token_account_address(token_mint_b, buyer),
+            maker_profile: maker_profile_address(buyer),
+            escrow,
+            vault: self.token_account_address(token_mint_b, &escrow),
+            token_mint_b_allowance: self.mint_allowance(token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(token_mint_b),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, args, &[*buyer])?;
+        Ok(escrow)
+    }
+
+    fn confirm_delivery(
+        &mut self,
+        seller: &Pubkey,
+        escrow: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::ConfirmDelivery {
+            seller: *seller,
+            escrow: *escrow,
+        };
+        self.process(
+            accounts,
+            escrow::instruction::ConfirmDelivery {},
+            &[*seller],
+        )
+    }
+
+    fn dispute_arbitrated_escrow(
+        &mut self,
+        party: &Pubkey,
+        escrow: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::DisputeArbitratedEscrow {
+            party: *party,
+            escrow: *escrow,
+        };
+        self.process(
+            accounts,
+            escrow::instruction::DisputeArbitratedEscrow {},
+            &[*party],
+        )
+    }
+
+    /// Accounts for `release_arbitrated_escrow` and
+    /// `refund_arbitrated_escrow`, derived from the data stored in `escrow`.
+    fn settle_arbitrated_escrow_accounts(
+        &self,
+        authority: &Pubkey,
+        escrow: &Pubkey,
+    ) -> escrow::accounts::SettleArbitratedEscrow {
+        let state = self.arbitrated_escrow(escrow).unwrap();
+        let mint = state.token_mint_b;
+        escrow::accounts::SettleArbitratedEscrow {
+            authority: *authority,
+            buyer: state.buyer,
+            seller: state.seller,
+            token_mint_b: mint,
+            buyer_token_account_b: self.token_account_address(&mint, &state.buyer),
+            seller_token_account_b: self.token_account_address(&mint, &state.seller),
+            maker_profile: maker_profile_address(&state.buyer),
+            escrow: *escrow,
+            vault: self.token_account_address(&mint, escrow),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&mint),
+            system_program: system_program::ID,
+        }
+    }
+
+    fn release_arbitrated_escrow(
+        &mut self,
+        authority: &Pubkey,
+        escrow: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let accounts = self.settle_arbitrated_escrow_accounts(authority, escrow);
+        self.process(
+            accounts,
+            escrow::instruction::ReleaseArbitratedEscrow {},
+            &[*authority],
+        )
+    }
+
+    fn refund_arbitrated_escrow(
+        &mut self,
+        authority: &Pubkey,
+        escrow: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let accounts = self.settle_arbitrated_escrow_accounts(authority, escrow);
+        self.process(
+            accounts,
+            escrow::instruction::RefundArbitratedEscrow {},
+            &[*authority],
+        )
+    }
+}
+
+#[test]
+fn buyer_releases_to_the_seller_after_delivery() {
+    let Market {
+        mut test,
+        alice: buyer,
+        bob: seller,
+        carol: arbiter,
+        usdc,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let escrow = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    assert_eq!(escrow, arbitrated_escrow_address(&buyer, 0));
+    assert_eq!(test.token_balance(&usdc, &buyer), 50_000_000);
+    assert_eq!(test.token_balance(&usdc, &escrow), 50_000_000);
+
+    let state = test.arbitrated_escrow(&escrow).unwrap();
+    assert_eq!(state.seller, seller);
+    assert_eq!(state.arbiter, arbiter);
+    assert_eq!(state.refund_after, now + 7 * DAY);
+
+    test.confirm_delivery(&seller, &escrow).unwrap();
+    assert!(test.arbitrated_escrow(&escrow).unwrap().delivery_confirmed);
+    test.release_arbitrated_escrow(&buyer, &escrow).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &seller), 50_000_000);
+    assert!(test.arbitrated_escrow(&escrow).is_none());
+    assert!(test
+        .runtime
+        .get_account(&test.token_account_address(&usdc, &escrow))
+        .is_none());
+
+    let settled = &test.events::<ArbitratedEscrowSettled>()[0];
+    assert!(settled.released);
+    assert_eq!(settled.settled_by, buyer);
+
+    let profile = test.maker_profile(&buyer).unwrap();
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 1);
+    assert_eq!(profile.volume, 50_000_000);
+}
+
+#[test]
+fn undisputed_escrow_is_refunded_by_the_seller_or_after_the_timeout() {
+    let Market {
+        mut test,
+        alice: buyer,
+        bob: seller,
+        carol: arbiter,
+        usdc,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+    let stranger = test.create_user();
+
+    let refunded = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    test.refund_arbitrated_escrow(&seller, &refunded).unwrap();
+    assert_eq!(test.token_balance(&usdc, &buyer), 100_000_000);
+
+    let timed_out = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    test.confirm_delivery(&seller, &timed_out).unwrap();
+    for authority in [stranger, arbiter] {
+        assert_eq!(
+            test.refund_arbitrated_escrow(&authority, &timed_out),
+            Err(program_error(ErrorCode::RefundNotAllowed))
+        );
+    }
+    assert_eq!(
+        test.release_arbitrated_escrow(&seller, &timed_out),
+        Err(program_error(ErrorCode::ReleaseNotAllowed))
+    );
+
+    // The seller confirmed delivery, but never disputed the buyer's silence.
+    test.runtime.warp_to_timestamp(now + 7 * DAY);
+    assert_eq!(
+        test.dispute_arbitrated_escrow(&seller, &timed_out),
+        Err(program_error(ErrorCode::EscrowTimedOut))
+    );
+    test.refund_arbitrated_escrow(&stranger, &timed_out)
+        .unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &buyer), 100_000_000);
+    assert_eq!(test.token_balance(&usdc, &seller), 0);
+    let profile = test.maker_profile(&buyer).unwrap();
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 0);
+}
+
+#[test]
+fn arbiter_releases_a_disputed_delivery_to_the_seller() {
+    let Market {
+        mut test,
+        alice: buyer,
+        bob: seller,
+        carol: arbiter,
+        usdc,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let escrow = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+
+    // Nothing for the arbiter to rule on yet.
+    assert_eq!(
+        test.release_arbitrated_escrow(&arbiter, &escrow),
+        Err(program_error(ErrorCode::ReleaseNotAllowed))
+    );
+    assert_eq!(
+        test.dispute_arbitrated_escrow(&seller, &escrow),
+        Err(program_error(ErrorCode::DeliveryNotConfirmed))
+    );
+
+    test.confirm_delivery(&seller, &escrow).unwrap();
+    test.runtime.warp_to_timestamp(now + 6 * DAY + DAY / 2);
+    test.dispute_arbitrated_escrow(&seller, &escrow).unwrap();
+
+    // The arbiter gets a day from the dispute, past the original expiry.
+    let state = test.arbitrated_escrow(&escrow).unwrap();
+    assert!(state.disputed);
+    assert_eq!(state.refund_after, now + 7 * DAY + DAY / 2);
+    assert_eq!(
+        test.dispute_arbitrated_escrow(&buyer, &escrow),
+        Err(program_error(ErrorCode::EscrowAlreadyDisputed))
+    );
+
+    test.runtime.warp_to_timestamp(now + 7 * DAY);
+    test.release_arbitrated_escrow(&arbiter, &escrow).unwrap();
+    assert_eq!(test.token_balance(&usdc, &seller), 50_000_000);
+    assert_eq!(
+        test.events::<ArbitratedEscrowSettled>()[0].settled_by,
+        arbiter
+    );
+}
+
+#[test]
+fn disputes_without_a_ruling_default_to_refunding_the_buyer() {
+    let Market {
+        mut test,
+        alice: buyer,
+        bob: seller,
+        carol: arbiter,
+        usdc,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+    let stranger = test.create_user();
+
+    // Nothing was delivered, so the arbiter can only refund.
+    let undelivered = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    test.dispute_arbitrated_escrow(&buyer, &undelivered)
+        .unwrap();
+    assert_eq!(
+        test.release_arbitrated_escrow(&arbiter, &undelivered),
+        Err(program_error(ErrorCode::ReleaseNotAllowed))
+    );
+    test.refund_arbitrated_escrow(&arbiter, &undelivered)
+        .unwrap();
+    assert_eq!(test.token_balance(&usdc, &buyer), 100_000_000);
+
+    // The arbiter does not rule in time.
+    let unruled = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    test.confirm_delivery(&seller, &unruled).unwrap();
+    test.dispute_arbitrated_escrow(&buyer, &unruled).unwrap();
+    test.runtime.warp_to_timestamp(now + DAY);
+    assert_eq!(
+        test.release_arbitrated_escrow(&arbiter, &unruled),
+        Err(program_error(ErrorCode::ReleaseNotAllowed))
+    );
+    test.refund_arbitrated_escrow(&stranger, &unruled).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &buyer), 100_000_000);
+    assert_eq!(test.token_balance(&usdc, &seller), 0);
+}
+
+#[test]
+fn only_the_parties_can_act_on_their_side_of_the_escrow() {
+    let Market {
+        mut test,
+        alice: buyer,
+        bob: seller,
+        carol: arbiter,
+        usdc,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    for invalid in [
+        escrow::instruction::OpenArbitratedEscrow {
+            seller: buyer,
+            ..terms(seller, arbiter, now)
+        },
+        escrow::instruction::OpenArbitratedEscrow {
+            arbiter: seller,
+            ..terms(seller, arbiter, now)
+        },
+    ] {
+        assert_eq!(
+            test.open_arbitrated_escrow(&buyer, &usdc, invalid),
+            Err(program_error(ErrorCode::InvalidEscrowParties))
+        );
+    }
+    assert_eq!(
+        test.open_arbitrated_escrow(
+            &buyer,
+            &usdc,
+            escrow::instruction::OpenArbitratedEscrow {
+                arbitration_seconds: 0,
+                ..terms(seller, arbiter, now)
+            }
+        ),
+        Err(program_error(ErrorCode::InvalidArbitrationPeriod))
+    );
+    assert_eq!(
+        test.open_arbitrated_escrow(
+            &buyer,
+            &usdc,
+            escrow::instruction::OpenArbitratedEscrow {
+                expires_at: now,
+                ..terms(seller, arbiter, now)
+            }
+        ),
+        Err(program_error(ErrorCode::ExpiryInPast))
+    );
+
+    let escrow = test
+        .open_arbitrated_escrow(&buyer, &usdc, terms(seller, arbiter, now))
+        .unwrap();
+    assert_eq!(
+        test.confirm_delivery(&buyer, &escrow),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+    assert_eq!(
+        test.dispute_arbitrated_escrow(&arbiter, &escrow),
+        Err(program_error(ErrorCode::NotAnEscrowParty))
+    );
+    test.confirm_delivery(&seller, &escrow).unwrap();
+    assert_eq!(
+        test.confirm_delivery(&seller, &escrow),
+        Err(program_error(ErrorCode::DeliveryAlreadyConfirmed))
+    );
+}
@@ -0,0 +1,354 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::instruction::Instruction,
+    AccountDeserialize,
+};
+use anchor_spl::{associated_token::spl_associated_token_account, token::spl_token};
+use escrow::{error::ErrorCode, BundleAsset, BundleOffer};
+
+use common::*;
+
+fn asset(mint: Pubkey, amount: u64) -> BundleAsset {
+    BundleAsset { mint, amount }
+}
+
+// Alice holds USDC and BONK and wants WIF and POPCAT, which Bob holds.
+fn setup() -> (Market, Vec<BundleAsset>, Vec<BundleAsset>) {
+    let mut market = Market::new();
+    let test = &mut market.test;
+    let bonk = test.create_mint(&spl_token::ID, 6);
+    let popcat = test.create_mint(&spl_token::ID, 6);
+    test.mint_to(&bonk, &market.alice, 100_000_000);
+    test.mint_to(&popcat, &market.bob, 100_000_000);
+
+    let offered = vec![asset(market.usdc, 10_000_000), asset(bonk, 20_000_000)];
+    let wanted = vec![asset(market.wif, 30_000_000), asset(popcat, 40_000_000)];
+    (market, offered, wanted)
+}
+
+impl EscrowTest {
+    fn bundle_offer(&self, address: &Pubkey) -> Option<BundleOffer> {
+        let account = self.runtime.get_account(address)?;
+        Some(BundleOffer::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    /// `take_bundle_offer`, with the taker's, maker's and treasury's
+    /// associated token accounts as the remaining accounts.
+    fn take_bundle_offer_instruction(&self, taker: &Pubkey, bundle_offer: &Pubkey) -> Instruction {
+        let state = self.bundle_offer(bundle_offer).unwrap();
+        let treasury = self.config().treasury;
+        let accounts = escrow::accounts::TakeBundleOffer {
+            taker: *taker,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            config: config_address(),
+            bundle_offer: *bundle_offer,
+            token_program: self.token_program_of(&state.offered[0].mint),
+        };
+        let offered = state.offered.iter().flat_map(|asset| {
+            [
+                AccountMeta::new(asset.mint, false),
+                AccountMeta::new(self.token_account_address(&asset.mint, bundle_offer), false),
+                AccountMeta::new(self.token_account_address(&asset.mint, taker), false),
+            ]
+        });
+        let wanted = state.wanted.iter().flat_map(|asset| {
+            [
+                AccountMeta::new_readonly(asset.mint, false),
+                AccountMeta::new(self.token_account_address(&asset.mint, taker), false),
+                AccountMeta::new(self.token_account_address(&asset.mint, &state.maker), false),
+                AccountMeta::new(self.token_account_address(&asset.mint, &treasury), false),
+            ]
+        });
+        escrow_instruction(
+            accounts,
+            offered.chain(wanted).collect(),
+            escrow::instruction::TakeBundleOffer {},
+        )
+    }
+
+    /// Creates whichever token accounts the taker, maker and treasury are
+    /// missing, then takes the bundle offer in the same transaction.
+    fn take_bundle_offer(
+        &mut self,
+        taker: &Pubkey,
+        bundle_offer: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let state = self.bundle_offer(bundle_offer).unwrap();
+        let treasury = self.config().treasury;
+        let owners_and_mints = state
+            .offered
+            .iter()
+            .map(|asset| (*taker, asset.mint))
+            .chain(
+                state
+                    .wanted
+                    .iter()
+                    .flat_map(|asset| [(state.maker, asset.mint), (treasury, asset.mint)]),
+            );
+        let mut instructions: Vec<Instruction> = owners_and_mints
+            .map(|(owner, mint)| {
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    taker,
+                    &owner,
+                    &mint,
+                    &self.token_program_of(&mint),
+                )
+            })
+            .collect();
+        instructions.push(self.take_bundle_offer_instruction(taker, bundle_offer));
+        self.runtime.process_transaction(&instructions, &[*taker])
+    }
+
+    fn refund_bundle_offer(
+        &mut self,
+        maker: &Pubkey,
+        bundle_offer: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let state = self.bundle_offer(bundle_offer).unwrap();
+        let accounts = escrow::accounts::RefundBundleOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            bundle_offer: *bundle_offer,
+            token_program: self.token_program_of(&state.offered[0].mint),
+        };
+        let remaining_accounts = state
+            .offered
+            .iter()
+            .flat_map(|asset| {
+                [
+                    AccountMeta::new(asset.mint, false),
+                    AccountMeta::new(self.token_account_address(&asset.mint, bundle_offer), false),
+                    AccountMeta::new(self.token_account_address(&asset.mint, maker), false),
+                ]
+            })
+            .collect();
+        let instruction = escrow_instruction(
+            accounts,
+            remaining_accounts,
+            escrow::instruction::RefundBundleOffer {},
+        );
+        self.runtime.process_transaction(&[instruction], &[*maker])
+    }
+}
+
+#[test]
+fn bundle_offer_is_settled_atomically() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            ..
+        },
+        offered,
+        wanted,
+    ) = setup();
+
+    let bundle_offer = test.make_bundle_offer(&alice, &offered, &wanted).unwrap();
+
+    for asset in &offered {
+        let vault = test.token_account_address(&asset.mint, &bundle_offer);
+        assert_eq!(test.token_account(&vault).unwrap().amount, asset.amount);
+        assert_eq!(
+            test.token_balance(&asset.mint, &alice),
+            100_000_000 - asset.amount
+        );
+    }
+
+    test.take_bundle_offer(&bob, &bundle_offer).unwrap();
+
+    for asset in &offered {
+        assert_eq!(test.token_balance(&asset.mint, &bob), asset.amount);
+        let vault = test.token_account_address(&asset.mint, &bundle_offer);
+        assert!(test.runtime.get_account(&vault).is_none());
+    }
+    for asset in &wanted {
+        assert_eq!(test.token_balance(&asset.mint, &alice), asset.amount);
+        assert_eq!(
+            test.token_balance(&asset.mint, &bob),
+            100_000_000 - asset.amount
+        );
+    }
+    assert!(test.runtime.get_account(&bundle_offer).is_none());
+}
+
+#[test]
+fn protocol_fee_is_taken_from_every_wanted_asset() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            ..
+        },
+        offered,
+        wanted,
+    ) = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    let bundle_offer = test.make_bundle_offer(&alice, &offered, &wanted).unwrap();
+    test.take_bundle_offer(&bob, &bundle_offer).unwrap();
+
+    let treasury = test.treasury;
+    for asset in &wanted {
+        let fee = asset.amount / 100;
+        assert_eq!(test.token_balance(&asset.mint, &treasury), fee);
+        assert_eq!(test.token_balance(&asset.mint, &alice), asset.amount - fee);
+    }
+}
+
+#[test]
+fn nothing_moves_if_one_wanted_asset_cannot_be_paid() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            ..
+        },
+        offered,
+        mut wanted,
+    ) = setup();
+    wanted[1].amount = 100_000_001;
+
+    let bundle_offer = test.make_bundle_offer(&alice, &offered, &wanted).unwrap();
+
+    assert!(test.take_bundle_offer(&bob, &bundle_offer).is_err());
+
+    assert_eq!(test.token_balance(&wanted[0].mint, &bob), 100_000_000);
+    assert_eq!(test.token_balance(&wanted[0].mint, &alice), 0);
+    for asset in &offered {
+        assert_eq!(test.token_balance(&asset.mint, &bob), 0);
+    }
+    assert!(test.bundle_offer(&bundle_offer).is_some());
+}
+
+#[test]
+fn bundle_offer_can_be_refunded_by_the_maker() {
+    let (
+        Market {
+            mut test, alice, ..
+        },
+        offered,
+        wanted,
+    ) = setup();
+
+    let bundle_offer = test.make_bundle_offer(&alice, &offered, &wanted).unwrap();
+
+    test.refund_bundle_offer(&alice, &bundle_offer).unwrap();
+
+    for asset in &offered {
+        assert_eq!(test.token_balance(&asset.mint, &alice), 100_000_000);
+    }
+    for asset in &offered {
+        let vault = test.token_account_address(&asset.mint, &bundle_offer);
+        assert!(test.runtime.get_account(&vault).is_none());
+    }
+    assert!(test.runtime.get_account(&bundle_offer).is_none());
+}
+
+#[test]
+fn take_bundle_offer_rejects_accounts_that_do_not_match_the_bundle() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            ..
+        },
+        offered,
+        wanted,
+    ) = setup();
+
+    let bundle_offer = test.make_bundle_offer(&alice, &offered, &wanted).unwrap();
+    for asset in &offered {
+        test.mint_to(&asset.mint, &bob, 0);
+    }
+    let treasury = test.treasury;
+    for asset in &wanted {
+        test.mint_to(&asset.mint, &alice, 0);
+        test.mint_to(&asset.mint, &treasury, 0);
+    }
+    let remaining_start = 6;
+
+    // Offered assets listed in the wrong order.
+    let mut instruction = test.take_bundle_offer_instruction(&bob, &bundle_offer);
+    let (first, second) = instruction.accounts[remaining_start..].split_at_mut(3);
+    first.swap_with_slice(&mut second[..3]);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::BundleAccountMismatch))
+    );
+
+    // Payment for the first wanted asset sent to Bob instead of Alice.
+    let mut instruction = test.take_bundle_offer_instruction(&bob, &bundle_offer);
+    let maker_account = remaining_start + 3 * offered.len() + 2;
+    instruction.accounts[maker_account].pubkey = test.token_account_address(&wanted[0].mint, &bob);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::BundleAccountMismatch))
+    );
+
+    // A wanted asset left out.
+    let mut instruction = test.take_bundle_offer_instruction(&bob, &bundle_offer);
+    instruction
+        .accounts
+        .truncate(instruction.accounts.len() - 4);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::BundleAccountMismatch))
+    );
+
+    // An offered asset sent back into its own vault, which the token program
+    // allows, so the vault would still hold it when it is closed.
+    let mut instruction = test.take_bundle_offer_instruction(&bob, &bundle_offer);
+    let vault = instruction.accounts[remaining_start + 1].pubkey;
+    instruction.accounts[remaining_start + 2].pubkey = vault;
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::VaultNotEmpty))
+    );
+
+    let instruction = test.take_bundle_offer_instruction(&bob, &bundle_offer);
+    test.runtime
+        .process_transaction(&[instruction], &[bob])
+        .unwrap();
+}
+
+#[test]
+fn make_bundle_offer_validates_both_sides() {
+    let (
+        Market {
+            mut test, alice, ..
+        },
+        offered,
+        wanted,
+    ) = setup();
+
+    let duplicated = vec![wanted[0], wanted[0]];
+    assert_eq!(
+        test.make_bundle_offer(&alice, &offered, &duplicated),
+        Err(program_error(ErrorCode::DuplicateBundleMint))
+    );
+
+    assert_eq!(
+        test.make_bundle_offer(&alice, &offered, &[]),
+        Err(program_error(ErrorCode::InvalidBundleSize))
+    );
+
+    let too_many = vec![wanted[0]; escrow::MAX_BUNDLE_ASSETS + 1];
+    assert_eq!(
+        test.make_bundle_offer(&alice, &offered, &too_many),
+        Err(program_error(ErrorCode::InvalidBundleSize))
+    );
+
+    let zero = vec![asset(wanted[0].mint, 0)];
+    assert_eq!(
+        test.make_bundle_offer(&alice, &offered, &zero),
+        Err(program_error(ErrorCode::InvalidAmount))
+    );
+}
@@ -0,0 +1,60 @@
+//! A transfer hook program for the tests: every transfer of a hooked mint bumps
+//! a counter kept in an account the hook lists as its only extra account.
+
+use anchor_lang::{
+    prelude::{AccountInfo, Pubkey},
+    pubkey,
+    solana_program::{entrypoint::ProgramResult, program_error::ProgramError},
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address,
+    instruction::{ExecuteInstruction, TransferHookInstruction},
+};
+
+pub const COUNTER_HOOK_ID: Pubkey = pubkey!("HookCounter11111111111111111111111111111111");
+
+pub fn counter_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"counter", mint.as_ref()], &COUNTER_HOOK_ID).0
+}
+
+pub fn extra_account_metas_address(mint: &Pubkey) -> Pubkey {
+    get_extra_account_metas_address(mint, &COUNTER_HOOK_ID)
+}
+
+/// Data of the extra account metas account for `mint`, listing its counter.
+pub fn extra_account_metas_data(mint: &Pubkey) -> Vec<u8> {
+    let metas = [ExtraAccountMeta::new_with_pubkey(&counter_address(mint), false, true).unwrap()];
+    let mut data = vec![0; ExtraAccountMetaList::size_of(metas.len()).unwrap()];
+    ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &metas).unwrap();
+    data
+}
+
+pub fn process_counter_hook_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let TransferHookInstruction::Execute { .. } = TransferHookInstruction::unpack(data)? else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    // Source, mint, destination, authority, extra account metas, counter.
+    let [_, mint, _, _, extra_account_metas, counter, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if *extra_account_metas.key != extra_account_metas_address(mint.key) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    ExtraAccountMetaList::check_account_infos::<ExecuteInstruction>(
+        accounts,
+        data,
+        program_id,
+        &extra_account_metas.try_borrow_data()?,
+    )?;
+
+    let mut counter = counter.try_borrow_mut_data()?;
+    let count = u64::from_le_bytes(counter[..8].try_into().unwrap());
+    counter[..8].copy_from_slice(&(count + 1).to_le_bytes());
+    Ok(())
+}
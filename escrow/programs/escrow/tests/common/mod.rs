@@ -0,0 +1,985 @@
+// Each integration test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
+pub mod counter_hook;
+pub mod runtime;
+
+pub use counter_hook::*;
+pub use runtime::*;
+
+use anchor_lang::{
+    prelude::{AccountInfo, AccountMeta, Pubkey, Rent},
+    solana_program::{
+        entrypoint::ProgramResult,
+        instruction::{Instruction, InstructionError},
+        native_token::LAMPORTS_PER_SOL,
+        system_instruction, system_program,
+    },
+    AccountDeserialize, AnchorSerialize, Discriminator, Event, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id, spl_associated_token_account,
+    },
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_hook, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+        state::{Account as TokenAccount, Mint},
+    },
+};
+use escrow::{
+    AmountBasis, BundleAsset, Config, MakerProfile, Offer, PriceFeedMessage, PriceUpdateV2,
+    Pricing, VerificationLevel, PYTH_RECEIVER_PROGRAM_ID,
+};
+use solana_program_test::{processor, ProgramTest};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+pub fn process_escrow_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // SAFETY: Anchor ties the account infos and the slice holding them to a
+    // single lifetime, but only uses them for the duration of the call.
+    let accounts: &[AccountInfo] = unsafe { std::mem::transmute(accounts) };
+    escrow::entry(program_id, accounts, data)
+}
+
+/// `TransactionError` for a custom error returned by the first instruction of
+/// a transaction, such as an `ErrorCode` of the escrow program.
+pub fn program_error(error: impl Into<u32>) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error.into()))
+}
+
+/// An escrow instruction with `remaining_accounts` appended after the ones
+/// Anchor knows about.
+pub fn escrow_instruction(
+    accounts: impl ToAccountMetas,
+    remaining_accounts: Vec<AccountMeta>,
+    data: impl InstructionData,
+) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(remaining_accounts);
+    Instruction {
+        program_id: escrow::ID,
+        accounts: metas,
+        data: data.data(),
+    }
+}
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &escrow::ID).0
+}
+
+pub fn bundle_offer_address(maker: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"bundle_offer", maker.as_ref(), id.to_le_bytes().as_ref()],
+        &escrow::ID,
+    )
+    .0
+}
+
+pub fn mint_allowance_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint_allowance", mint.as_ref()], &escrow::ID).0
+}
+
+pub fn maker_profile_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"maker_profile", maker.as_ref()], &escrow::ID).0
+}
+
+pub fn offer_address(maker: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"offer", maker.as_ref(), id.to_le_bytes().as_ref()],
+        &escrow::ID,
+    )
+    .0
+}
+
+/// The escrow program with an initialized `Config` and a funded mint
+/// authority, on top of a fresh `TestRuntime`.
+pub struct EscrowTest {
+    pub runtime: TestRuntime,
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub mint_authority: Pubkey,
+}
+
+impl Default for EscrowTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscrowTest {
+    pub fn new() -> Self {
+        let mut program_test = ProgramTest::default();
+        program_test.add_program("escrow", escrow::ID, processor!(process_escrow_instruction));
+        program_test.add_program(
+            "counter_hook",
+            COUNTER_HOOK_ID,
+            processor!(process_counter_hook_instruction),
+        );
+
+        let mut runtime = TestRuntime::start(program_test);
+        let admin = runtime.new_keypair();
+        let treasury = runtime.new_keypair();
+        let mint_authority = runtime.new_keypair();
+        runtime.airdrop(&admin, 10 * LAMPORTS_PER_SOL);
+        runtime.airdrop(&treasury, LAMPORTS_PER_SOL);
+        runtime.airdrop(&mint_authority, 10 * LAMPORTS_PER_SOL);
+
+        let mut test = Self {
+            runtime,
+            admin,
+            treasury,
+            mint_authority,
+        };
+        test.set_config(&Config {
+            admin,
+            fee_basis_points: 0,
+            treasury,
+            bump: Pubkey::find_program_address(&[b"config"], &escrow::ID).1,
+        });
+        test
+    }
+
+    /// Writes `config` as `initialize` would.  `initialize` itself only
+    /// accepts the upgrade authority of a program deployed through the
+    /// upgradeable loader, which a builtin is not.
+    pub fn set_config(&mut self, config: &Config) {
+        let mut data = Config::DISCRIMINATOR.to_vec();
+        config.serialize(&mut data).unwrap();
+        self.runtime.set_account(
+            config_address(),
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: escrow::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    pub fn update_fee(
+        &mut self,
+        admin: &Pubkey,
+        fee_basis_points: u16,
+    ) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::UpdateConfig {
+            admin: *admin,
+            config: config_address(),
+        };
+        self.process(
+            accounts,
+            escrow::instruction::UpdateFee { fee_basis_points },
+            &[*admin],
+        )
+    }
+
+    pub fn process(
+        &mut self,
+        accounts: impl ToAccountMetas,
+        data: impl InstructionData,
+        signers: &[Pubkey],
+    ) -> Result<(), TransactionError> {
+        let remaining_accounts = self.transfer_hook_accounts_for(&accounts);
+        let instruction = escrow_instruction(accounts, remaining_accounts, data);
+        self.runtime.process_transaction(&[instruction], signers)
+    }
+
+    /// `transfer_hook_accounts` for every mint among `accounts`, which is
+    /// what single-offer instructions expect in `remaining_accounts`.
+    pub fn transfer_hook_accounts_for(&self, accounts: &impl ToAccountMetas) -> Vec<AccountMeta> {
+        accounts
+            .to_account_metas(None)
+            .iter()
+            .flat_map(|meta| self.transfer_hook_accounts(&meta.pubkey))
+            .collect()
+    }
+
+    /// The hook program of `mint`, its extra account metas account and the
+    /// accounts those list, in the order transfers of `mint` need them.  Empty
+    /// for mints without a transfer hook, and without the listed accounts if
+    /// the hook program never set up its extra account metas.
+    pub fn transfer_hook_accounts(&self, mint: &Pubkey) -> Vec<AccountMeta> {
+        let Some(account) = self.runtime.get_account(mint) else {
+            return Vec::new();
+        };
+        if account.owner != spl_token_2022::ID {
+            return Vec::new();
+        }
+        let Ok(state) = StateWithExtensions::<Mint>::unpack(&account.data) else {
+            return Vec::new();
+        };
+        let Some(program_id) = state
+            .get_extension::<transfer_hook::TransferHook>()
+            .ok()
+            .and_then(|hook| Option::<Pubkey>::from(hook.program_id))
+        else {
+            return Vec::new();
+        };
+
+        let extra_account_metas =
+            spl_transfer_hook_interface::get_extra_account_metas_address(mint, &program_id);
+        let mut metas = vec![
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(extra_account_metas, false),
+        ];
+        let Some(account) = self.runtime.get_account(&extra_account_metas) else {
+            return metas;
+        };
+        let data = &account.data;
+        let tlv_state = spl_type_length_value::state::TlvStateBorrowed::unpack(data).unwrap();
+        let extra_metas =
+            ExtraAccountMetaList::unpack_with_tlv_state::<ExecuteInstruction>(&tlv_state).unwrap();
+        metas.extend(extra_metas.data().iter().map(|meta: &ExtraAccountMeta| {
+            // The counter hook only lists fixed addresses.
+            assert_eq!(meta.discriminator, 0);
+            AccountMeta {
+                pubkey: Pubkey::new_from_array(meta.address_config),
+                is_signer: meta.is_signer.into(),
+                is_writable: meta.is_writable.into(),
+            }
+        }));
+        metas
+    }
+
+    pub fn create_user(&mut self) -> Pubkey {
+        let user = self.runtime.new_keypair();
+        self.runtime.airdrop(&user, 10 * LAMPORTS_PER_SOL);
+        user
+    }
+
+    /// A mint owned by `token_program`, with `mint_authority` as both its mint
+    /// and freeze authority.
+    pub fn create_mint(&mut self, token_program: &Pubkey, decimals: u8) -> Pubkey {
+        self.create_mint_with_extensions(token_program, decimals, &[], |_| Vec::new())
+    }
+
+    /// A mint with room for `extensions`, which are set up by the
+    /// instructions `initialize_extensions` returns before the mint itself is
+    /// initialized.
+    pub fn create_mint_with_extensions(
+        &mut self,
+        token_program: &Pubkey,
+        decimals: u8,
+        extensions: &[ExtensionType],
+        initialize_extensions: impl FnOnce(&Pubkey) -> Vec<Instruction>,
+    ) -> Pubkey {
+        let mint = self.runtime.new_keypair();
+        let space = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+        let mut instructions = vec![system_instruction::create_account(
+            &self.mint_authority,
+            &mint,
+            Rent::default().minimum_balance(space),
+            space as u64,
+            token_program,
+        )];
+        instructions.extend(initialize_extensions(&mint));
+        instructions.push(
+            spl_token_2022::instruction::initialize_mint2(
+                token_program,
+                &mint,
+                &self.mint_authority,
+                Some(&self.mint_authority),
+                decimals,
+            )
+            .unwrap(),
+        );
+        self.runtime
+            .process_transaction(&instructions, &[self.mint_authority, mint])
+            .unwrap();
+        mint
+    }
+
+    pub fn token_program_of(&self, mint: &Pubkey) -> Pubkey {
+        self.runtime.get_account(mint).unwrap().owner
+    }
+
+    pub fn token_account_address(&self, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program_of(mint))
+    }
+
+    /// Mints `amount` into the associated token account of `owner`, creating
+    /// it if needed.
+    pub fn mint_to(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) {
+        let token_program = self.token_program_of(mint);
+        let instructions = [
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &self.mint_authority,
+                owner,
+                mint,
+                &token_program,
+            ),
+            spl_token_2022::instruction::mint_to(
+                &token_program,
+                mint,
+                &self.token_account_address(mint, owner),
+                &self.mint_authority,
+                &[],
+                amount,
+            )
+            .unwrap(),
+        ];
+        self.runtime
+            .process_transaction(&instructions, &[self.mint_authority])
+            .unwrap();
+    }
+
+    pub fn token_account(&self, address: &Pubkey) -> Option<TokenAccount> {
+        let account = self.runtime.get_account(address)?;
+        Some(
+            StateWithExtensions::<TokenAccount>::unpack(&account.data)
+                .unwrap()
+                .base,
+        )
+    }
+
+    /// Balance of the associated token account of `owner`, zero if it does
+    /// not exist.
+    pub fn token_balance(&self, mint: &Pubkey, owner: &Pubkey) -> u64 {
+        self.token_account(&self.token_account_address(mint, owner))
+            .map_or(0, |account| account.amount)
+    }
+
+    /// Events of type `T` emitted by the last processed transaction.
+    pub fn events<T: Event>(&self) -> Vec<T> {
+        self.runtime
+            .events()
+            .iter()
+            .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+            .map(|data| T::try_from_slice(&data[T::DISCRIMINATOR.len()..]).unwrap())
+            .collect()
+    }
+
+    pub fn config(&self) -> Config {
+        let account = self.runtime.get_account(&config_address()).unwrap();
+        Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub fn offer(&self, address: &Pubkey) -> Option<Offer> {
+        let account = self.runtime.get_account(address)?;
+        Some(Offer::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    pub fn maker_profile(&self, maker: &Pubkey) -> Option<MakerProfile> {
+        let account = self.runtime.get_account(&maker_profile_address(maker))?;
+        Some(MakerProfile::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    /// Id the next offer by `maker` gets, `0` before their first offer.
+    pub fn next_offer_id(&self, maker: &Pubkey) -> u64 {
+        self.maker_profile(maker)
+            .map_or(0, |profile| profile.next_offer_id)
+    }
+
+    /// Allowance address for `mint` if the admin has allowed it.
+    pub fn mint_allowance(&self, mint: &Pubkey) -> Option<Pubkey> {
+        let address = mint_allowance_address(mint);
+        self.runtime.get_account(&address).map(|_| address)
+    }
+
+    pub fn allow_mint(&mut self, admin: &Pubkey, mint: &Pubkey) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::AllowMint {
+            admin: *admin,
+            config: config_address(),
+            mint: *mint,
+            mint_allowance: mint_allowance_address(mint),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, escrow::instruction::AllowMint {}, &[*admin])
+    }
+
+    pub fn make_offer_accounts(
+        &self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+        price_feed: Option<Pubkey>,
+    ) -> escrow::accounts::MakeOffer {
+        let offer = offer_address(maker, self.next_offer_id(maker));
+        escrow::accounts::MakeOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            token_mint_a: *token_mint_a,
+            token_mint_b: *token_mint_b,
+            maker_token_account_a: self.token_account_address(token_mint_a, maker),
+            offer,
+            vault: self.token_account_address(token_mint_a, &offer),
+            price_feed,
+            token_mint_a_allowance: self.mint_allowance(token_mint_a),
+            token_mint_b_allowance: self.mint_allowance(token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(token_mint_a),
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn make_offer_with(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+        args: escrow::instruction::MakeOffer,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts = self.make_offer_accounts(maker, token_mint_a, token_mint_b, None);
+        let offer = accounts.offer;
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    /// Makes an offer priced from `price_feed`, with no expiry or taker
+    /// restriction.
+    pub fn make_oracle_offer(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_a_offered_amount: u64,
+        token_mint_b: &Pubkey,
+        price_feed: &Pubkey,
+        spread_basis_points: i16,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts =
+            self.make_offer_accounts(maker, token_mint_a, token_mint_b, Some(*price_feed));
+        let offer = accounts.offer;
+        let args = escrow::instruction::MakeOracleOffer {
+            token_a_offered_amount,
+            spread_basis_points,
+            token_b_amount_basis: AmountBasis::Gross,
+            expires_at: None,
+            allowed_taker: None,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    /// A Pyth price update published now.
+    pub fn create_feed(&mut self, price: i64, exponent: i32) -> Pubkey {
+        let feed = Pubkey::new_unique();
+        self.set_price(&feed, price, exponent);
+        feed
+    }
+
+    /// Writes `update` to `feed` as an account of `owner`.
+    pub fn set_price_update(&mut self, feed: &Pubkey, update: &PriceUpdateV2, owner: Pubkey) {
+        let mut data = PriceUpdateV2::DISCRIMINATOR.to_vec();
+        update.serialize(&mut data).unwrap();
+        self.runtime.set_account(
+            *feed,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    /// Posts a new, fully verified price to `feed`, timestamped now.
+    pub fn set_price(&mut self, feed: &Pubkey, price: i64, exponent: i32) {
+        let update = PriceUpdateV2 {
+            write_authority: self.admin,
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: [7; 32],
+                price,
+                conf: 0,
+                exponent,
+                publish_time: self.runtime.clock().unix_timestamp,
+                prev_publish_time: self.runtime.clock().unix_timestamp,
+                ema_price: price,
+                ema_conf: 0,
+            },
+            posted_slot: self.runtime.clock().slot,
+        };
+        self.set_price_update(feed, &update, PYTH_RECEIVER_PROGRAM_ID);
+    }
+
+    pub fn make_offer(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_a_offered_amount: u64,
+        token_mint_b: &Pubkey,
+        token_b_wanted_amount: u64,
+    ) -> Result<Pubkey, TransactionError> {
+        self.make_offer_with(
+            maker,
+            token_mint_a,
+            token_mint_b,
+            escrow::instruction::MakeOffer {
+                token_a_offered_amount,
+                token_b_wanted_amount,
+                token_b_amount_basis: AmountBasis::Gross,
+                expires_at: None,
+                allowed_taker: None,
+            },
+        )
+    }
+
+    /// Accounts for `take_offer` and `take_offer_partial`, derived from the
+    /// data stored in `offer`.
+    pub fn take_offer_accounts(
+        &self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> escrow::accounts::TakeOffer {
+        let state = self.offer(offer).unwrap();
+        let treasury = self.config().treasury;
+        escrow::accounts::TakeOffer {
+            taker: *taker,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            token_mint_a: state.token_mint_a,
+            token_mint_b: state.token_mint_b,
+            taker_token_account_a: self.token_account_address(&state.token_mint_a, taker),
+            taker_token_account_b: self.token_account_address(&state.token_mint_b, taker),
+            maker_token_account_b: self.token_account_address(&state.token_mint_b, &state.maker),
+            config: config_address(),
+            treasury,
+            treasury_token_account_b: self.token_account_address(&state.token_mint_b, &treasury),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            price_feed: match state.pricing {
+                Pricing::Fixed | Pricing::Dutch(_) => None,
+                Pricing::Oracle { price_feed, .. } => Some(price_feed),
+            },
+            token_mint_a_allowance: self.mint_allowance(&state.token_mint_a),
+            token_mint_b_allowance: self.mint_allowance(&state.token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&state.token_mint_a),
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn take_offer(&mut self, taker: &Pubkey, offer: &Pubkey) -> Result<(), TransactionError> {
+        let accounts = self.take_offer_accounts(taker, offer);
+        let data = escrow::instruction::TakeOffer {
+            max_token_b_amount: u64::MAX,
+        };
+        self.process(accounts, data, &[*taker])
+    }
+
+    pub fn take_offer_partial(
+        &mut self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+        token_b_amount: u64,
+    ) -> Result<(), TransactionError> {
+        let accounts = self.take_offer_accounts(taker, offer);
+        self.process(
+            accounts,
+            escrow::instruction::TakeOfferPartial {
+                token_b_amount,
+                min_token_a_amount: 0,
+            },
+            &[*taker],
+        )
+    }
+
+    /// `take_offers` for `offers`, which all trade the pair of the first one,
+    /// with each offer's accounts as the remaining accounts.
+    pub fn take_offers_instruction(
+        &self,
+        taker: &Pubkey,
+        offers: &[Pubkey],
+        max_token_b_amount: u64,
+        min_token_a_amount: u64,
+    ) -> Instruction {
+        let first = self.offer(&offers[0]).unwrap();
+        let treasury = self.config().treasury;
+        let accounts = escrow::accounts::TakeOffers {
+            taker: *taker,
+            token_mint_a: first.token_mint_a,
+            token_mint_b: first.token_mint_b,
+            taker_token_account_a: self.token_account_address(&first.token_mint_a, taker),
+            taker_token_account_b: self.token_account_address(&first.token_mint_b, taker),
+            config: config_address(),
+            treasury,
+            treasury_token_account_b: self.token_account_address(&first.token_mint_b, &treasury),
+            token_mint_a_allowance: self.mint_allowance(&first.token_mint_a),
+            token_mint_b_allowance: self.mint_allowance(&first.token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&first.token_mint_a),
+            system_program: system_program::ID,
+        };
+        let remaining_accounts = offers
+            .iter()
+            .flat_map(|offer| {
+                let state = self.offer(offer).unwrap();
+                let price_feed = match state.pricing {
+                    Pricing::Fixed | Pricing::Dutch(_) => escrow::ID,
+                    Pricing::Oracle { price_feed, .. } => price_feed,
+                };
+                [
+                    AccountMeta::new(*offer, false),
+                    AccountMeta::new(
+                        self.token_account_address(&state.token_mint_a, offer),
+                        false,
+                    ),
+                    AccountMeta::new(state.maker, false),
+                    AccountMeta::new(maker_profile_address(&state.maker), false),
+                    AccountMeta::new(
+                        self.token_account_address(&state.token_mint_b, &state.maker),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(price_feed, false),
+                ]
+            })
+            .collect();
+        escrow_instruction(
+            accounts,
+            remaining_accounts,
+            escrow::instruction::TakeOffers {
+                max_token_b_amount,
+                min_token_a_amount,
+            },
+        )
+    }
+
+    pub fn take_offers(
+        &mut self,
+        taker: &Pubkey,
+        offers: &[Pubkey],
+        max_token_b_amount: u64,
+    ) -> Result<(), TransactionError> {
+        let instruction = self.take_offers_instruction(taker, offers, max_token_b_amount, 0);
+        self.runtime.process_transaction(&[instruction], &[*taker])
+    }
+
+    pub fn update_offer(
+        &mut self,
+        maker: &Pubkey,
+        offer: &Pubkey,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let accounts = escrow::accounts::UpdateOffer {
+            maker: *maker,
+            token_mint_a: state.token_mint_a,
+            maker_token_account_a: self.token_account_address(&state.token_mint_a, maker),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            token_mint_a_allowance: self.mint_allowance(&state.token_mint_a),
+            token_program: self.token_program_of(&state.token_mint_a),
+        };
+        self.process(
+            accounts,
+            escrow::instruction::UpdateOffer {
+                token_a_amount,
+                token_b_amount,
+            },
+            &[*maker],
+        )
+    }
+
+    pub fn refund_offer(&mut self, maker: &Pubkey, offer: &Pubkey) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let accounts = escrow::accounts::RefundOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            token_mint_a: state.token_mint_a,
+            maker_token_account_a: self.token_account_address(&state.token_mint_a, maker),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            token_program: self.token_program_of(&state.token_mint_a),
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        };
+        self.process(accounts, escrow::instruction::RefundOffer {}, &[*maker])
+    }
+
+    pub fn close_expired_offer(
+        &mut self,
+        caller: &Pubkey,
+        offer: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let accounts = escrow::accounts::CloseExpiredOffer {
+            payer: *caller,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            token_mint_a: state.token_mint_a,
+            maker_token_account_a: self.token_account_address(&state.token_mint_a, &state.maker),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            token_program: self.token_program_of(&state.token_mint_a),
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::ID,
+        };
+        self.process(
+            accounts,
+            escrow::instruction::CloseExpiredOffer {},
+            &[*caller],
+        )
+    }
+
+    pub fn make_offer_for_sol_with(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        args: escrow::instruction::MakeOfferForSol,
+    ) -> Result<Pubkey, TransactionError> {
+        let offer = offer_address(maker, self.next_offer_id(maker));
+        let accounts = escrow::accounts::MakeOfferForSol {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            token_mint_a: *token_mint_a,
+            maker_token_account_a: self.token_account_address(token_mint_a, maker),
+            offer,
+            vault: self.token_account_address(token_mint_a, &offer),
+            token_mint_a_allowance: self.mint_allowance(token_mint_a),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(token_mint_a),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    pub fn make_offer_for_sol(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_a_offered_amount: u64,
+        lamports_wanted: u64,
+    ) -> Result<Pubkey, TransactionError> {
+        self.make_offer_for_sol_with(
+            maker,
+            token_mint_a,
+            escrow::instruction::MakeOfferForSol {
+                token_a_offered_amount,
+                lamports_wanted,
+                expires_at: None,
+                allowed_taker: None,
+            },
+        )
+    }
+
+    pub fn make_sol_offer_with(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_b: &Pubkey,
+        args: escrow::instruction::MakeSolOffer,
+    ) -> Result<Pubkey, TransactionError> {
+        let offer = offer_address(maker, self.next_offer_id(maker));
+        let accounts = escrow::accounts::MakeSolOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            token_mint_b: *token_mint_b,
+            offer,
+            token_mint_b_allowance: self.mint_allowance(token_mint_b),
+            token_program: self.token_program_of(token_mint_b),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    pub fn make_sol_offer(
+        &mut self,
+        maker: &Pubkey,
+        lamports_offered: u64,
+        token_mint_b: &Pubkey,
+        token_b_wanted_amount: u64,
+    ) -> Result<Pubkey, TransactionError> {
+        self.make_sol_offer_with(
+            maker,
+            token_mint_b,
+            escrow::instruction::MakeSolOffer {
+                lamports_offered,
+                token_b_wanted_amount,
+                token_b_amount_basis: AmountBasis::Gross,
+                expires_at: None,
+                allowed_taker: None,
+            },
+        )
+    }
+
+    pub fn take_offer_for_sol(
+        &mut self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let accounts = escrow::accounts::TakeOfferForSol {
+            taker: *taker,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            token_mint_a: state.token_mint_a,
+            taker_token_account_a: self.token_account_address(&state.token_mint_a, taker),
+            config: config_address(),
+            treasury: self.config().treasury,
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            token_mint_a_allowance: self.mint_allowance(&state.token_mint_a),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&state.token_mint_a),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, escrow::instruction::TakeOfferForSol {}, &[*taker])
+    }
+
+    pub fn take_sol_offer(
+        &mut self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let treasury = self.config().treasury;
+        let accounts = escrow::accounts::TakeSolOffer {
+            taker: *taker,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            token_mint_b: state.token_mint_b,
+            taker_token_account_b: self.token_account_address(&state.token_mint_b, taker),
+            maker_token_account_b: self.token_account_address(&state.token_mint_b, &state.maker),
+            config: config_address(),
+            treasury,
+            treasury_token_account_b: self.token_account_address(&state.token_mint_b, &treasury),
+            offer: *offer,
+            token_mint_b_allowance: self.mint_allowance(&state.token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&state.token_mint_b),
+            system_program: system_program::ID,
+        };
+        self.process(accounts, escrow::instruction::TakeSolOffer {}, &[*taker])
+    }
+
+    /// Nobody signs `close_expired_sol_offer`, anyone can send it.
+    pub fn close_expired_sol_offer(&mut self, offer: &Pubkey) -> Result<(), TransactionError> {
+        let state = self.offer(offer).unwrap();
+        let accounts = escrow::accounts::CloseExpiredSolOffer {
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            offer: *offer,
+        };
+        self.process(accounts, escrow::instruction::CloseExpiredSolOffer {}, &[])
+    }
+
+    pub fn make_bundle_offer(
+        &mut self,
+        maker: &Pubkey,
+        offered: &[BundleAsset],
+        wanted: &[BundleAsset],
+    ) -> Result<Pubkey, TransactionError> {
+        let bundle_offer = bundle_offer_address(maker, self.next_offer_id(maker));
+        let token_program = self.token_program_of(&offered[0].mint);
+        let accounts = escrow::accounts::MakeBundleOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            bundle_offer,
+            associated_token_program: spl_associated_token_account::ID,
+            token_program,
+            system_program: system_program::ID,
+        };
+        let remaining_accounts = offered
+            .iter()
+            .flat_map(|asset| {
+                [
+                    AccountMeta::new_readonly(asset.mint, false),
+                    AccountMeta::new(self.token_account_address(&asset.mint, maker), false),
+                    AccountMeta::new(
+                        self.token_account_address(&asset.mint, &bundle_offer),
+                        false,
+                    ),
+                ]
+            })
+            .collect();
+        let data = escrow::instruction::MakeBundleOffer {
+            offered: offered.to_vec(),
+            wanted: wanted.to_vec(),
+            expires_at: None,
+            allowed_taker: None,
+        };
+        let instruction = escrow_instruction(accounts, remaining_accounts, data);
+        self.runtime
+            .process_transaction(&[instruction], &[*maker])?;
+        Ok(bundle_offer)
+    }
+}
+
+/// How `Market::with` sets up a market.  The default is the market of
+/// `Market::new`.
+pub struct MarketOptions {
+    /// Program owning both mints.
+    pub token_program: Pubkey,
+    pub usdc_decimals: u8,
+    pub wif_decimals: u8,
+    /// USDC held by Alice, Bob and Carol.
+    pub usdc_balances: [u64; 3],
+    /// WIF held by Alice, Bob and Carol.
+    pub wif_balances: [u64; 3],
+}
+
+impl Default for MarketOptions {
+    fn default() -> Self {
+        Self {
+            token_program: spl_token::ID,
+            usdc_decimals: 6,
+            wif_decimals: 6,
+            usdc_balances: [100_000_000, 0, 0],
+            wif_balances: [0, 100_000_000, 0],
+        }
+    }
+}
+
+/// The market most tests trade in: Alice holds 100 USDC and Bob 100 WIF,
+/// both classic SPL mints with 6 decimals.  Carol holds nothing.
+pub struct Market {
+    pub test: EscrowTest,
+    pub alice: Pubkey,
+    pub bob: Pubkey,
+    pub carol: Pubkey,
+    pub usdc: Pubkey,
+    pub wif: Pubkey,
+}
+
+impl Market {
+    pub fn new() -> Self {
+        Self::with(MarketOptions::default())
+    }
+
+    pub fn with(options: MarketOptions) -> Self {
+        Self::with_mints(options, |test, token_program, decimals| {
+            test.create_mint(token_program, decimals)
+        })
+    }
+
+    /// Like `with`, with both mints made by `create_mint` from the token
+    /// program and decimals in `options`.
+    pub fn with_mints(
+        options: MarketOptions,
+        mut create_mint: impl FnMut(&mut EscrowTest, &Pubkey, u8) -> Pubkey,
+    ) -> Self {
+        let mut test = EscrowTest::new();
+        let alice = test.create_user();
+        let bob = test.create_user();
+        let carol = test.create_user();
+        let usdc = create_mint(&mut test, &options.token_program, options.usdc_decimals);
+        let wif = create_mint(&mut test, &options.token_program, options.wif_decimals);
+
+        let parties = [alice, bob, carol];
+        for (mint, balances) in [(usdc, options.usdc_balances), (wif, options.wif_balances)] {
+            for (owner, amount) in parties.iter().zip(balances) {
+                if amount > 0 {
+                    test.mint_to(&mint, owner, amount);
+                }
+            }
+        }
+
+        Self {
+            test,
+            alice,
+            bob,
+            carol,
+            usdc,
+            wif,
+        }
+    }
+}
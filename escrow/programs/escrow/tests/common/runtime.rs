@@ -0,0 +1,262 @@
+//! A synchronous wrapper around `solana-program-test` for the escrow tests.
+//!
+//! Transactions run in a real bank: the SPL Token, Token-2022 and Associated
+//! Token Account programs are the BPF builds `solana-program-test` ships with,
+//! while the programs under test are registered as native builtins.  Every
+//! transaction is paid for by the bank's own payer, so the lamport balances of
+//! the accounts under test only change by what the programs move, and is
+//! followed by a new slot so that retried transactions get a new blockhash.
+//!
+//! Signers are tracked by address: keys made with `TestRuntime::new_keypair`
+//! can be listed as signers of `TestRuntime::process_transaction`.
+
+use std::{cell::RefCell, collections::HashMap, sync::Once};
+
+use anchor_lang::{
+    prelude::{AccountInfo, Clock, Pubkey},
+    solana_program::{
+        entrypoint::ProgramResult,
+        instruction::Instruction,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        system_program,
+    },
+};
+use solana_program_test::{tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    message::Message,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+pub use solana_sdk::{account::Account, transaction::TransactionError};
+
+pub struct TestRuntime {
+    tokio: tokio::runtime::Runtime,
+    context: ProgramTestContext,
+    keypairs: HashMap<Pubkey, Keypair>,
+    clock: Clock,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+impl TestRuntime {
+    /// Starts a bank with the programs added to `program_test`.
+    pub fn start(program_test: ProgramTest) -> Self {
+        install_event_stubs();
+
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let mut context = tokio.block_on(program_test.start_with_context());
+        let clock = tokio
+            .block_on(context.banks_client.get_sysvar::<Clock>())
+            .unwrap();
+
+        Self {
+            tokio,
+            context,
+            keypairs: HashMap::new(),
+            clock,
+            logs: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// A new keypair that can sign transactions, by its address.
+    pub fn new_keypair(&mut self) -> Pubkey {
+        let keypair = Keypair::new();
+        let address = keypair.pubkey();
+        self.keypairs.insert(address, keypair);
+        address
+    }
+
+    /// Overwrites `address`, or closes it if `account` has no lamports.
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.context
+            .set_account(&address, &AccountSharedData::from(account));
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Option<Account> {
+        self.tokio
+            .block_on(self.context.banks_client.clone().get_account(*address))
+            .unwrap()
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.get_account(address)
+            .map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        let mut account = self.get_account(address).unwrap_or(Account {
+            owner: system_program::ID,
+            ..Account::default()
+        });
+        account.lamports += lamports;
+        self.set_account(*address, account);
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Moves to the next slot, with the clock reading `unix_timestamp`.
+    pub fn warp_to_timestamp(&mut self, unix_timestamp: i64) {
+        self.clock.unix_timestamp = unix_timestamp;
+        self.next_slot();
+    }
+
+    /// Logs of the last processed transaction.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    /// `sol_log_data` payloads (Anchor events) of the last processed
+    /// transaction.
+    pub fn events(&self) -> &[Vec<u8>] {
+        &self.events
+    }
+
+    /// Runs `instructions` as one transaction signed by `signers`, each of
+    /// which must have been made with `new_keypair`.
+    pub fn process_transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<(), TransactionError> {
+        let message = Message::new(instructions, Some(&self.context.payer.pubkey()));
+        let mut keypairs = vec![&self.context.payer];
+        keypairs.extend(signers.iter().map(|signer| {
+            self.keypairs
+                .get(signer)
+                .unwrap_or_else(|| panic!("no keypair for signer {signer}"))
+        }));
+        let transaction = Transaction::new(&keypairs, message, self.context.last_blockhash);
+
+        EVENTS.with(|events| events.borrow_mut().clear());
+        let result = self
+            .tokio
+            .block_on(
+                self.context
+                    .banks_client
+                    .process_transaction_with_metadata(transaction),
+            )
+            .unwrap();
+        self.events = EVENTS.with(|events| events.take());
+        self.logs = result
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+
+        self.next_slot();
+        result.result
+    }
+
+    // A new slot also brings a new blockhash.  The bank sets its own clock for
+    // every slot, which is put back to the one the tests control.
+    fn next_slot(&mut self) {
+        self.clock.slot += 1;
+        self.context.warp_to_slot(self.clock.slot).unwrap();
+        self.context.set_sysvar(&self.clock);
+    }
+}
+
+thread_local! {
+    // Banks run transactions on the thread driving the test's runtime, so the
+    // events of one test's transactions end up here.
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Syscall stubs that record `sol_log_data` and pass everything else on to
+/// the ones `solana-program-test` installs, which have no way to surface it.
+struct EventStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for EventStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(fields.concat()));
+    }
+
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+fn install_event_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        // `solana-program-test` installs its stubs when the first bank
+        // starts.  Nothing else runs until this returns, so the swap below
+        // cannot race a transaction.
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(ProgramTest::default().start());
+        let program_test_stubs = set_syscall_stubs(Box::new(NoStubs));
+        set_syscall_stubs(Box::new(EventStubs(program_test_stubs)));
+    });
+}
+
+struct NoStubs;
+
+impl SyscallStubs for NoStubs {}
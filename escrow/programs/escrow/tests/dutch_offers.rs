@@ -0,0 +1,246 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use escrow::{error::ErrorCode, AmountBasis, DutchAuction, Pricing};
+
+use common::*;
+
+// Bob holds 1,000 WIF.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        wif_balances: [0, 1_000_000_000, 0],
+        ..MarketOptions::default()
+    })
+}
+
+// 10 USDC, from 100 WIF down to 40 WIF.
+fn auction(start_at: i64, end_at: i64) -> DutchAuction {
+    DutchAuction {
+        start_token_b_amount: 100_000_000,
+        end_token_b_amount: 40_000_000,
+        token_a_amount: 10_000_000,
+        start_at,
+        end_at,
+    }
+}
+
+impl EscrowTest {
+    /// Makes a Dutch auction offer with no expiry or taker restriction.
+    fn make_dutch_offer(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_mint_b: &Pubkey,
+        auction: DutchAuction,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts = self.make_offer_accounts(maker, token_mint_a, token_mint_b, None);
+        let offer = accounts.offer;
+        let args = escrow::instruction::MakeDutchOffer {
+            auction,
+            token_b_amount_basis: AmountBasis::Gross,
+            expires_at: None,
+            allowed_taker: None,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+}
+
+#[test]
+fn dutch_offer_is_taken_at_the_price_it_has_fallen_to() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let offer = test
+        .make_dutch_offer(&alice, &usdc, &wif, auction(now, now + 600))
+        .unwrap();
+
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(
+        state.pricing,
+        Pricing::Dutch(DutchAuction {
+            start_token_b_amount: 100_000_000,
+            end_token_b_amount: 40_000_000,
+            token_a_amount: 10_000_000,
+            start_at: now,
+            end_at: now + 600,
+        })
+    );
+    assert_eq!(state.token_b_remaining_amount, 100_000_000);
+
+    // Halfway down.
+    test.runtime.warp_to_timestamp(now + 300);
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&wif, &alice), 70_000_000);
+    assert_eq!(test.token_balance(&wif, &bob), 930_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn price_holds_before_the_start_and_after_the_end() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let offer = test
+        .make_dutch_offer(&alice, &usdc, &wif, auction(now + 100, now + 700))
+        .unwrap();
+
+    test.runtime.warp_to_timestamp(now + 50);
+    test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 2_000_000);
+
+    // What is left is priced pro rata at the floor.
+    test.runtime.warp_to_timestamp(now + 10_000);
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_a_remaining_amount, 8_000_000);
+    test.take_offer_partial(&bob, &offer, 16_000_000).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 6_000_000);
+
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_b_remaining_amount, 16_000_000);
+    assert_eq!(state.token_b_wanted_amount, 20_000_000 + 16_000_000 * 2);
+
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 52_000_000);
+}
+
+#[test]
+fn partial_quotes_round_up_for_the_maker() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let offer = test
+        .make_dutch_offer(
+            &alice,
+            &usdc,
+            &wif,
+            DutchAuction {
+                start_token_b_amount: 10,
+                end_token_b_amount: 1,
+                token_a_amount: 3,
+                ..auction(now, now + 9)
+            },
+        )
+        .unwrap();
+
+    // At 7 WIF for all 3 USDC, 3 WIF buys 1 USDC.
+    test.runtime.warp_to_timestamp(now + 3);
+    test.take_offer_partial(&bob, &offer, 3).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 1);
+    assert_eq!(test.offer(&offer).unwrap().token_b_remaining_amount, 4);
+
+    // At 5 WIF for all 3, the 2 left are worth 3.33 WIF.
+    test.runtime.warp_to_timestamp(now + 5);
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&wif, &alice), 3 + 4);
+}
+
+#[test]
+fn sweep_quotes_dutch_offers_at_the_current_price() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let fixed = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let dutch = test
+        .make_dutch_offer(&alice, &usdc, &wif, auction(now, now + 600))
+        .unwrap();
+
+    test.runtime.warp_to_timestamp(now + 600);
+    test.take_offers(&bob, &[dutch, fixed], 90_000_000).unwrap();
+
+    assert!(test.offer(&dutch).is_none());
+    assert!(test.offer(&fixed).is_none());
+    assert_eq!(test.token_balance(&wif, &alice), 90_000_000);
+}
+
+#[test]
+fn auction_has_to_fall_over_a_window_that_has_not_ended() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    for invalid in [
+        DutchAuction {
+            end_token_b_amount: 100_000_000,
+            ..auction(now, now + 600)
+        },
+        DutchAuction {
+            end_token_b_amount: 0,
+            ..auction(now, now + 600)
+        },
+        auction(now + 600, now + 600),
+        auction(now - 600, now),
+    ] {
+        assert_eq!(
+            test.make_dutch_offer(&alice, &usdc, &wif, invalid),
+            Err(program_error(ErrorCode::InvalidAuction))
+        );
+    }
+    assert_eq!(test.token_balance(&usdc, &alice), 100_000_000);
+}
+
+#[test]
+fn token_b_amount_of_a_dutch_offer_cannot_be_updated() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let now = test.runtime.clock().unix_timestamp;
+
+    let offer = test
+        .make_dutch_offer(&alice, &usdc, &wif, auction(now, now + 600))
+        .unwrap();
+
+    assert_eq!(
+        test.update_offer(&alice, &offer, 10_000_000, 10_000_000),
+        Err(program_error(ErrorCode::OfferIsQuoted))
+    );
+
+    test.update_offer(&alice, &offer, 5_000_000, 100_000_000)
+        .unwrap();
+    assert_eq!(
+        test.offer(&offer).unwrap().token_a_remaining_amount,
+        5_000_000
+    );
+}
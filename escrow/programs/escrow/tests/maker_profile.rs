@@ -0,0 +1,285 @@
+mod common;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode, prelude::Pubkey,
+    solana_program::native_token::LAMPORTS_PER_SOL,
+};
+use escrow::{BundleAsset, OfferMade};
+
+use common::*;
+
+// Stands in for a maker whose offers were made before profiles existed.
+fn delete_maker_profile(test: &mut EscrowTest, maker: &Pubkey) {
+    test.runtime
+        .set_account(maker_profile_address(maker), Account::default());
+}
+
+// Bob also holds 100 USDC, to make offers of his own.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        usdc_balances: [100_000_000, 100_000_000, 0],
+        ..MarketOptions::default()
+    })
+}
+
+#[test]
+fn offers_of_every_kind_take_the_next_id_from_the_maker_profile() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    assert!(test.maker_profile(&alice).is_none());
+
+    let first = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    assert_eq!(first, offer_address(&alice, 0));
+    assert_eq!(test.events::<OfferMade>()[0].id, 0);
+
+    let second = test
+        .make_sol_offer(&alice, LAMPORTS_PER_SOL, &wif, 50_000_000)
+        .unwrap();
+    assert_eq!(second, offer_address(&alice, 1));
+
+    let bundle = test
+        .make_bundle_offer(
+            &alice,
+            &[BundleAsset {
+                mint: usdc,
+                amount: 10_000_000,
+            }],
+            &[BundleAsset {
+                mint: wif,
+                amount: 50_000_000,
+            }],
+        )
+        .unwrap();
+    assert_eq!(bundle, bundle_offer_address(&alice, 2));
+
+    // Bob has a counter of his own.
+    let bobs = test
+        .make_offer(&bob, &wif, 10_000_000, &usdc, 50_000_000)
+        .unwrap();
+    assert_eq!(bobs, offer_address(&bob, 0));
+
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.maker, alice);
+    assert_eq!(profile.next_offer_id, 3);
+    assert_eq!(profile.open_offers, 3);
+}
+
+#[test]
+fn offer_address_has_to_match_the_next_id() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    test.make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    let mut accounts = test.make_offer_accounts(&alice, &usdc, &wif, None);
+    accounts.offer = offer_address(&alice, 5);
+    accounts.vault = test.token_account_address(&usdc, &accounts.offer);
+    let args = escrow::instruction::MakeOffer {
+        token_a_offered_amount: 10_000_000,
+        token_b_wanted_amount: 50_000_000,
+        token_b_amount_basis: escrow::AmountBasis::Gross,
+        expires_at: None,
+        allowed_taker: None,
+    };
+    assert_eq!(
+        test.process(accounts, args, &[alice]),
+        Err(program_error(AnchorErrorCode::ConstraintSeeds))
+    );
+    assert_eq!(test.next_offer_id(&alice), 1);
+}
+
+#[test]
+fn profile_tracks_open_and_filled_offers_and_volume() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let partially_taken = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let refunded = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 30_000_000)
+        .unwrap();
+    let sold_for_sol = test
+        .make_offer_for_sol(&alice, &usdc, 10_000_000, LAMPORTS_PER_SOL)
+        .unwrap();
+
+    test.take_offer_partial(&bob, &partially_taken, 20_000_000)
+        .unwrap();
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.open_offers, 3);
+    assert_eq!(profile.filled_offers, 0);
+    assert_eq!(profile.volume, 20_000_000);
+
+    test.take_offer_partial(&bob, &partially_taken, 30_000_000)
+        .unwrap();
+    test.take_offer_for_sol(&bob, &sold_for_sol).unwrap();
+    test.refund_offer(&alice, &refunded).unwrap();
+
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.next_offer_id, 3);
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 2);
+    assert_eq!(profile.volume, 50_000_000 + u128::from(LAMPORTS_PER_SOL));
+}
+
+#[test]
+fn expired_offers_are_no_longer_counted_as_open() {
+    let Market {
+        mut test,
+        alice,
+        wif,
+        ..
+    } = setup();
+
+    let expires_at = test.runtime.clock().unix_timestamp + 60;
+    let offer = test
+        .make_sol_offer_with(
+            &alice,
+            &wif,
+            escrow::instruction::MakeSolOffer {
+                lamports_offered: LAMPORTS_PER_SOL,
+                token_b_wanted_amount: 50_000_000,
+                token_b_amount_basis: escrow::AmountBasis::Gross,
+                expires_at: Some(expires_at),
+                allowed_taker: None,
+            },
+        )
+        .unwrap();
+    test.runtime.warp_to_timestamp(expires_at);
+    test.close_expired_sol_offer(&offer).unwrap();
+
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 0);
+    assert_eq!(profile.next_offer_id, 1);
+}
+
+#[test]
+fn take_rejects_another_makers_profile() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    test.make_offer(&bob, &wif, 10_000_000, &usdc, 50_000_000)
+        .unwrap();
+
+    let mut accounts = test.take_offer_accounts(&bob, &offer);
+    accounts.maker_profile = maker_profile_address(&bob);
+    assert_eq!(
+        test.process(
+            accounts,
+            escrow::instruction::TakeOffer {
+                max_token_b_amount: u64::MAX,
+            },
+            &[bob]
+        ),
+        Err(program_error(AnchorErrorCode::ConstraintSeeds))
+    );
+}
+
+#[test]
+fn makers_without_a_profile_get_one_when_an_offer_is_settled() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let taken = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let refunded = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    delete_maker_profile(&mut test, &alice);
+
+    // Refunding offer 1 moves the counter past it.
+    test.refund_offer(&alice, &refunded).unwrap();
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.maker, alice);
+    assert_eq!(profile.next_offer_id, 2);
+    assert_eq!(profile.open_offers, 0);
+
+    test.take_offer(&bob, &taken).unwrap();
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.next_offer_id, 2);
+    assert_eq!(profile.filled_offers, 1);
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    assert_eq!(offer, offer_address(&alice, 2));
+}
+
+#[test]
+fn sweeps_take_offers_of_makers_without_a_profile() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    delete_maker_profile(&mut test, &alice);
+
+    test.take_offers(&bob, &[offer], 50_000_000).unwrap();
+    assert!(test.offer(&offer).is_none());
+    assert!(test.maker_profile(&alice).is_none());
+}
+
+#[test]
+fn a_vault_created_before_the_offer_does_not_block_it() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    // Anyone can create the associated token account of the next offer.
+    test.mint_to(&usdc, &offer_address(&alice, 0), 1);
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 110_000_001);
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode,
+    prelude::{Pubkey, Rent},
+    solana_program::system_program,
+    Discriminator, Space,
+};
+use escrow::{
+    error::ErrorCode, AmountBasis, LegacyOffer, Offer, OfferMigrated, Pricing, ANCHOR_DISCRIMINATOR,
+};
+
+use common::*;
+
+// Writes an offer the way the first deployment of the program stored it,
+// with `token_a_amount` in its vault.  Its makers chose their own ids.
+fn create_legacy_offer(
+    test: &mut EscrowTest,
+    maker: &Pubkey,
+    id: u64,
+    token_mint_a: &Pubkey,
+    token_a_amount: u64,
+    token_mint_b: &Pubkey,
+) -> Pubkey {
+    let (offer, bump) = Pubkey::find_program_address(
+        &[b"offer", maker.as_ref(), id.to_le_bytes().as_ref()],
+        &escrow::ID,
+    );
+    let mut data = Offer::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&id.to_le_bytes());
+    data.extend_from_slice(maker.as_ref());
+    data.extend_from_slice(token_mint_a.as_ref());
+    data.extend_from_slice(token_mint_b.as_ref());
+    data.extend_from_slice(&50_000_000u64.to_le_bytes());
+    data.push(bump);
+    assert_eq!(data.len(), ANCHOR_DISCRIMINATOR + LegacyOffer::INIT_SPACE);
+
+    test.runtime.set_account(
+        offer,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: escrow::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    test.mint_to(token_mint_a, &offer, token_a_amount);
+    offer
+}
+
+fn migrate_offer(
+    test: &mut EscrowTest,
+    payer: &Pubkey,
+    offer: &Pubkey,
+    maker: &Pubkey,
+    token_mint_a: &Pubkey,
+) -> Result<(), TransactionError> {
+    let accounts = escrow::accounts::MigrateOffer {
+        payer: *payer,
+        maker: *maker,
+        maker_profile: maker_profile_address(maker),
+        offer: *offer,
+        token_mint_a: *token_mint_a,
+        vault: test.token_account_address(token_mint_a, offer),
+        token_program: test.token_program_of(token_mint_a),
+        system_program: system_program::ID,
+    };
+    test.process(accounts, escrow::instruction::MigrateOffer {}, &[*payer])
+}
+
+fn account_len(test: &EscrowTest, address: &Pubkey) -> usize {
+    test.runtime.get_account(address).unwrap().data.len()
+}
+
+#[test]
+fn legacy_offer_is_rewritten_in_the_current_layout() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+    let payer = test.create_user();
+
+    let offer = create_legacy_offer(&mut test, &alice, 7, &usdc, 10_000_000, &wif);
+    let offer_lamports = test.runtime.lamports(&offer);
+    let payer_lamports = test.runtime.lamports(&payer);
+    migrate_offer(&mut test, &payer, &offer, &alice, &usdc).unwrap();
+
+    let space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE;
+    assert_eq!(account_len(&test, &offer), space);
+    let rent = Rent::default().minimum_balance(space);
+    assert_eq!(test.runtime.lamports(&offer), rent);
+    let profile_rent = test.runtime.lamports(&maker_profile_address(&alice));
+    assert_eq!(
+        test.runtime.lamports(&payer),
+        payer_lamports - (rent - offer_lamports) - profile_rent
+    );
+
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.version, Offer::VERSION);
+    assert_eq!(state.id, 7);
+    assert_eq!(state.maker, alice);
+    assert_eq!(state.token_mint_b, wif);
+    assert_eq!(state.token_a_remaining_amount, 10_000_000);
+    assert_eq!(state.token_b_remaining_amount, 50_000_000);
+    assert_eq!(state.expires_at, 0);
+    assert_eq!(state.pricing, Pricing::Fixed);
+    assert_eq!(state.token_b_amount_basis, AmountBasis::Gross);
+    assert_eq!(test.events::<OfferMigrated>()[0].version, Offer::VERSION);
+
+    // The maker's next offer skips the id the legacy one took.
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.next_offer_id, 8);
+    assert_eq!(profile.open_offers, 1);
+
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert!(test.offer(&offer).is_none());
+    assert_eq!(test.maker_profile(&alice).unwrap().open_offers, 0);
+
+    let next = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    assert_eq!(next, offer_address(&alice, 8));
+}
+
+#[test]
+fn version_sits_past_the_end_of_legacy_offers() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let legacy = create_legacy_offer(&mut test, &alice, 5, &usdc, 10_000_000, &wif);
+    assert!(account_len(&test, &legacy) <= Offer::VERSION_OFFSET);
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let data = &test.runtime.get_account(&offer).unwrap().data;
+    assert_eq!(data[Offer::VERSION_OFFSET], Offer::VERSION);
+}
+
+#[test]
+fn only_legacy_offers_can_be_migrated() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &offer, &alice, &usdc),
+        Err(program_error(ErrorCode::OfferAlreadyMigrated))
+    );
+
+    let legacy = create_legacy_offer(&mut test, &alice, 9, &usdc, 10_000_000, &wif);
+    migrate_offer(&mut test, &alice, &legacy, &alice, &usdc).unwrap();
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &legacy, &alice, &usdc),
+        Err(program_error(ErrorCode::OfferAlreadyMigrated))
+    );
+
+    let profile = maker_profile_address(&alice);
+    test.mint_to(&usdc, &profile, 1);
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &profile, &alice, &usdc),
+        Err(program_error(AnchorErrorCode::AccountDiscriminatorMismatch))
+    );
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &alice, &alice, &usdc),
+        Err(program_error(AnchorErrorCode::ConstraintOwner))
+    );
+}
+
+#[test]
+fn migration_checks_the_maker_and_mint_of_the_offer() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = create_legacy_offer(&mut test, &alice, 3, &usdc, 10_000_000, &wif);
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &offer, &bob, &usdc),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+
+    test.mint_to(&wif, &offer, 1);
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &offer, &alice, &wif),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+    assert!(test.maker_profile(&bob).is_none());
+}
+
+#[test]
+fn a_legacy_offer_with_the_last_id_cannot_be_adopted() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    // No id would be left for the maker's next offer.
+    let offer = create_legacy_offer(&mut test, &alice, u64::MAX, &usdc, 10_000_000, &wif);
+    assert_eq!(
+        migrate_offer(&mut test, &alice, &offer, &alice, &usdc),
+        Err(program_error(ErrorCode::ArithmeticOverflow))
+    );
+    assert!(test.maker_profile(&alice).is_none());
+}
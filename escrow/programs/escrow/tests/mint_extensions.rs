@@ -0,0 +1,293 @@
+mod common;
+
+use anchor_lang::{error::ErrorCode as AnchorErrorCode, prelude::Pubkey};
+use anchor_spl::{
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{confidential_transfer, transfer_hook, ExtensionType},
+        instruction::{initialize_non_transferable_mint, initialize_permanent_delegate},
+    },
+};
+use escrow::error::ErrorCode;
+
+use common::*;
+
+// Alice and Bob both hold 100 USDC, a Token-2022 mint without extensions.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        token_program: spl_token_2022::ID,
+        usdc_balances: [100_000_000, 100_000_000, 0],
+        wif_balances: [0; 3],
+        ..MarketOptions::default()
+    })
+}
+
+fn create_mint_with_permanent_delegate(test: &mut EscrowTest) -> Pubkey {
+    let delegate = Pubkey::new_unique();
+    test.create_mint_with_extensions(
+        &spl_token_2022::ID,
+        6,
+        &[ExtensionType::PermanentDelegate],
+        |mint| vec![initialize_permanent_delegate(&spl_token_2022::ID, mint, &delegate).unwrap()],
+    )
+}
+
+impl EscrowTest {
+    fn disallow_mint(&mut self, admin: &Pubkey, mint: &Pubkey) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::DisallowMint {
+            admin: *admin,
+            config: config_address(),
+            mint_allowance: mint_allowance_address(mint),
+        };
+        self.process(accounts, escrow::instruction::DisallowMint {}, &[*admin])
+    }
+}
+
+#[test]
+fn offers_reject_mints_with_dangerous_extensions() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        ..
+    } = setup();
+
+    let permanent_delegate = create_mint_with_permanent_delegate(&mut test);
+    let transfer_hook = test.create_mint_with_extensions(
+        &spl_token_2022::ID,
+        6,
+        &[ExtensionType::TransferHook],
+        |mint| {
+            vec![transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                mint,
+                None,
+                Some(Pubkey::new_unique()),
+            )
+            .unwrap()]
+        },
+    );
+    let non_transferable = test.create_mint_with_extensions(
+        &spl_token_2022::ID,
+        6,
+        &[ExtensionType::NonTransferable],
+        |mint| vec![initialize_non_transferable_mint(&spl_token_2022::ID, mint).unwrap()],
+    );
+    let confidential = test.create_mint_with_extensions(
+        &spl_token_2022::ID,
+        6,
+        &[ExtensionType::ConfidentialTransferMint],
+        |mint| {
+            vec![confidential_transfer::instruction::initialize_mint(
+                &spl_token_2022::ID,
+                mint,
+                None,
+                true,
+                None,
+            )
+            .unwrap()]
+        },
+    );
+
+    for (mint, error) in [
+        (permanent_delegate, ErrorCode::PermanentDelegateNotAllowed),
+        (transfer_hook, ErrorCode::TransferHookNotAllowed),
+        (non_transferable, ErrorCode::NonTransferableMint),
+        (confidential, ErrorCode::ConfidentialTransfersNotAllowed),
+    ] {
+        test.mint_to(&mint, &alice, 10_000_000);
+        assert_eq!(
+            test.make_offer(&alice, &usdc, 10_000_000, &mint, 50_000_000),
+            Err(program_error(error))
+        );
+        assert_eq!(
+            test.make_offer(&alice, &mint, 10_000_000, &usdc, 50_000_000),
+            Err(program_error(error))
+        );
+    }
+
+    assert_eq!(
+        test.make_sol_offer(&alice, 1_000_000_000, &permanent_delegate, 50_000_000),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+}
+
+#[test]
+fn admin_can_allow_a_mint_with_dangerous_extensions() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    let pyusd = create_mint_with_permanent_delegate(&mut test);
+    test.mint_to(&pyusd, &bob, 100_000_000);
+
+    test.allow_mint(&admin, &pyusd).unwrap();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &pyusd, 50_000_000)
+        .unwrap();
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&pyusd, &alice), 50_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 110_000_000);
+}
+
+#[test]
+fn disallowing_a_mint_stops_takes_but_not_refunds() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    let pyusd = create_mint_with_permanent_delegate(&mut test);
+    test.mint_to(&pyusd, &bob, 100_000_000);
+    test.allow_mint(&admin, &pyusd).unwrap();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &pyusd, 50_000_000)
+        .unwrap();
+
+    test.disallow_mint(&admin, &pyusd).unwrap();
+    assert!(test.mint_allowance(&pyusd).is_none());
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+    assert_eq!(
+        test.take_offer_partial(&bob, &offer, 10_000_000),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+
+    test.refund_offer(&alice, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &alice), 100_000_000);
+}
+
+#[test]
+fn disallowing_a_mint_stops_sol_takes_and_updates() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    let pyusd = create_mint_with_permanent_delegate(&mut test);
+    test.mint_to(&pyusd, &alice, 100_000_000);
+    test.mint_to(&pyusd, &bob, 100_000_000);
+    test.allow_mint(&admin, &pyusd).unwrap();
+
+    let for_sol = test
+        .make_offer_for_sol(&alice, &pyusd, 10_000_000, 1_000_000_000)
+        .unwrap();
+    let sol_offer = test
+        .make_sol_offer(&alice, 1_000_000_000, &pyusd, 50_000_000)
+        .unwrap();
+    let offer = test
+        .make_offer(&alice, &pyusd, 10_000_000, &usdc, 50_000_000)
+        .unwrap();
+
+    test.disallow_mint(&admin, &pyusd).unwrap();
+
+    assert_eq!(
+        test.take_offer_for_sol(&bob, &for_sol),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+    assert_eq!(
+        test.take_sol_offer(&bob, &sol_offer),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+    assert_eq!(
+        test.update_offer(&alice, &offer, 20_000_000, 50_000_000),
+        Err(program_error(ErrorCode::PermanentDelegateNotAllowed))
+    );
+}
+
+#[test]
+fn allowance_for_another_mint_is_rejected() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    let pyusd = create_mint_with_permanent_delegate(&mut test);
+    test.mint_to(&pyusd, &bob, 100_000_000);
+    let wif = test.create_mint(&spl_token_2022::ID, 6);
+    test.mint_to(&wif, &bob, 100_000_000);
+    test.allow_mint(&admin, &wif).unwrap();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let mut accounts = test.take_offer_accounts(&bob, &offer);
+    accounts.token_mint_a_allowance = Some(mint_allowance_address(&wif));
+    assert_eq!(
+        test.process(
+            accounts,
+            escrow::instruction::TakeOffer {
+                max_token_b_amount: u64::MAX,
+            },
+            &[bob]
+        ),
+        Err(program_error(ErrorCode::MintAllowanceMismatch))
+    );
+
+    let mut accounts = test.make_offer_accounts(&alice, &usdc, &pyusd, None);
+    accounts.token_mint_b_allowance = Some(mint_allowance_address(&wif));
+    let args = escrow::instruction::MakeOffer {
+        token_a_offered_amount: 10_000_000,
+        token_b_wanted_amount: 50_000_000,
+        token_b_amount_basis: escrow::AmountBasis::Gross,
+        expires_at: None,
+        allowed_taker: None,
+    };
+    assert_eq!(
+        test.process(accounts, args, &[alice]),
+        Err(program_error(ErrorCode::MintAllowanceMismatch))
+    );
+}
+
+#[test]
+fn only_the_admin_can_allow_mints() {
+    let Market {
+        mut test, alice, ..
+    } = setup();
+    let pyusd = create_mint_with_permanent_delegate(&mut test);
+
+    assert_eq!(
+        test.allow_mint(&alice, &pyusd),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+
+    let admin = test.admin;
+    test.allow_mint(&admin, &pyusd).unwrap();
+    assert_eq!(
+        test.disallow_mint(&alice, &pyusd),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+}
+
+#[test]
+fn classic_token_mints_pass_the_policy() {
+    let Market {
+        mut test, alice, ..
+    } = setup();
+    let bonk = test.create_mint(&spl_token::ID, 5);
+    let wif = test.create_mint(&spl_token::ID, 6);
+    test.mint_to(&bonk, &alice, 100_000);
+
+    test.make_offer(&alice, &bonk, 100_000, &wif, 1_000_000)
+        .unwrap();
+}
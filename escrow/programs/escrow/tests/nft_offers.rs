@@ -0,0 +1,434 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey, Rent},
+    solana_program::{instruction::Instruction, system_program},
+    AccountDeserialize, AnchorSerialize,
+};
+use anchor_spl::{
+    associated_token::spl_associated_token_account,
+    metadata::{mpl_token_metadata, MetadataAccount},
+    token::spl_token,
+};
+use escrow::{error::ErrorCode, AmountBasis, NftTerms, RoyaltyPaid};
+
+use common::*;
+
+// Bob holds 1,000 USDC to buy Alice's NFTs with.
+fn setup() -> (Market, [Pubkey; 3]) {
+    let market = Market::with(MarketOptions {
+        usdc_balances: [0, 1_000_000_000, 0],
+        wif_balances: [0; 3],
+        ..MarketOptions::default()
+    });
+    let creators = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    (market, creators)
+}
+
+// 5% royalties, split 70/30 between the first two creators.
+fn metadata(creators: [Pubkey; 3]) -> NftMetadata {
+    NftMetadata {
+        seller_fee_basis_points: 500,
+        creators: vec![(creators[0], 70), (creators[1], 30), (creators[2], 0)],
+        collection: None,
+    }
+}
+
+fn metadata_address(mint: &Pubkey) -> Pubkey {
+    mpl_token_metadata::accounts::Metadata::find_pda(mint).0
+}
+
+/// The parts of an NFT's Metaplex metadata the escrow reads.
+#[derive(Clone, Default)]
+struct NftMetadata {
+    seller_fee_basis_points: u16,
+    // Address and share of each creator.
+    creators: Vec<(Pubkey, u8)>,
+    // Key of the collection, and whether the NFT is verified as a member.
+    collection: Option<(Pubkey, bool)>,
+}
+
+impl NftMetadata {
+    /// A `Metadata` account for `mint`, laid out as the token metadata
+    /// program writes it.
+    fn account_data(&self, mint: &Pubkey) -> Vec<u8> {
+        let mut data = Vec::new();
+        let creators: Vec<(Pubkey, bool, u8)> = self
+            .creators
+            .iter()
+            .map(|(address, share)| (*address, true, *share))
+            .collect();
+        let collection = self.collection.map(|(key, verified)| (verified, key));
+
+        (mpl_token_metadata::types::Key::MetadataV1 as u8)
+            .serialize(&mut data)
+            .unwrap();
+        Pubkey::new_unique().serialize(&mut data).unwrap();
+        mint.serialize(&mut data).unwrap();
+        ("NFT".to_string(), "NFT".to_string(), String::new())
+            .serialize(&mut data)
+            .unwrap();
+        self.seller_fee_basis_points.serialize(&mut data).unwrap();
+        Some(creators).serialize(&mut data).unwrap();
+        // Primary sale happened, is mutable, edition nonce.
+        (true, true, None::<u8>).serialize(&mut data).unwrap();
+        Some(mpl_token_metadata::types::TokenStandard::NonFungible as u8)
+            .serialize(&mut data)
+            .unwrap();
+        collection.serialize(&mut data).unwrap();
+        // Uses, collection details and programmable config.
+        (None::<u8>, None::<u8>, None::<u8>)
+            .serialize(&mut data)
+            .unwrap();
+        data
+    }
+}
+
+impl EscrowTest {
+    /// A supply-one SPL Token mint held by `owner`, with `metadata` as its
+    /// Metaplex metadata.
+    fn create_nft(&mut self, owner: &Pubkey, metadata: &NftMetadata) -> Pubkey {
+        let mint = self.create_mint(&anchor_spl::token::spl_token::ID, 0);
+        self.mint_to(&mint, owner, 1);
+        self.set_nft_metadata(&mint, metadata);
+        mint
+    }
+
+    fn set_nft_metadata(&mut self, mint: &Pubkey, metadata: &NftMetadata) {
+        let data = metadata.account_data(mint);
+        self.runtime.set_account(
+            metadata_address(mint),
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: mpl_token_metadata::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    fn make_nft_offer_accounts(
+        &self,
+        maker: &Pubkey,
+        nft: &Pubkey,
+        token_mint_b: &Pubkey,
+    ) -> escrow::accounts::MakeNftOffer {
+        let offer = offer_address(maker, self.next_offer_id(maker));
+        escrow::accounts::MakeNftOffer {
+            maker: *maker,
+            token_mint_a: *nft,
+            token_mint_b: *token_mint_b,
+            maker_token_account_a: self.token_account_address(nft, maker),
+            maker_profile: maker_profile_address(maker),
+            offer,
+            vault: self.token_account_address(nft, &offer),
+            metadata: metadata_address(nft),
+            token_mint_a_allowance: self.mint_allowance(nft),
+            token_mint_b_allowance: self.mint_allowance(token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(nft),
+            system_program: system_program::ID,
+        }
+    }
+
+    /// Lists `nft` for `token_b_wanted_amount`, with no expiry or taker
+    /// restriction.
+    fn make_nft_offer(
+        &mut self,
+        maker: &Pubkey,
+        nft: &Pubkey,
+        token_mint_b: &Pubkey,
+        token_b_wanted_amount: u64,
+        collection: Option<Pubkey>,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts = self.make_nft_offer_accounts(maker, nft, token_mint_b);
+        let offer = accounts.offer;
+        let args = escrow::instruction::MakeNftOffer {
+            token_b_wanted_amount,
+            token_b_amount_basis: AmountBasis::Gross,
+            expires_at: None,
+            allowed_taker: None,
+            collection,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    /// `take_nft_offer` for `offer`, with each creator in the NFT's metadata
+    /// that has a share, and their token account, as the remaining accounts.
+    fn take_nft_offer_instruction(&self, taker: &Pubkey, offer: &Pubkey) -> Instruction {
+        let state = self.offer(offer).unwrap();
+        let treasury = self.config().treasury;
+        let metadata = metadata_address(&state.token_mint_a);
+        let accounts = escrow::accounts::TakeNftOffer {
+            taker: *taker,
+            maker: state.maker,
+            token_mint_a: state.token_mint_a,
+            token_mint_b: state.token_mint_b,
+            taker_token_account_a: self.token_account_address(&state.token_mint_a, taker),
+            taker_token_account_b: self.token_account_address(&state.token_mint_b, taker),
+            maker_token_account_b: self.token_account_address(&state.token_mint_b, &state.maker),
+            config: config_address(),
+            treasury,
+            treasury_token_account_b: self.token_account_address(&state.token_mint_b, &treasury),
+            maker_profile: maker_profile_address(&state.maker),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            metadata,
+            token_mint_a_allowance: self.mint_allowance(&state.token_mint_a),
+            token_mint_b_allowance: self.mint_allowance(&state.token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&state.token_mint_a),
+            system_program: system_program::ID,
+        };
+
+        let account = self.runtime.get_account(&metadata).unwrap();
+        let metadata = MetadataAccount::try_deserialize(&mut account.data.as_slice()).unwrap();
+        let remaining_accounts = metadata
+            .creators
+            .iter()
+            .flatten()
+            .filter(|creator| creator.share > 0)
+            .flat_map(|creator| {
+                [
+                    AccountMeta::new_readonly(creator.address, false),
+                    AccountMeta::new(
+                        self.token_account_address(&state.token_mint_b, &creator.address),
+                        false,
+                    ),
+                ]
+            })
+            .collect();
+        escrow_instruction(
+            accounts,
+            remaining_accounts,
+            escrow::instruction::TakeNftOffer {},
+        )
+    }
+
+    fn take_nft_offer(&mut self, taker: &Pubkey, offer: &Pubkey) -> Result<(), TransactionError> {
+        let instruction = self.take_nft_offer_instruction(taker, offer);
+        self.runtime.process_transaction(&[instruction], &[*taker])
+    }
+}
+
+#[test]
+fn nft_sale_pays_creators_their_royalties_out_of_the_maker_share() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        creators,
+    ) = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    let nft = test.create_nft(&alice, &metadata(creators));
+    let offer = test
+        .make_nft_offer(&alice, &nft, &usdc, 100_000_000, None)
+        .unwrap();
+    assert_eq!(test.token_balance(&nft, &offer), 1);
+    assert_eq!(
+        test.offer(&offer).unwrap().nft,
+        Some(NftTerms { collection: None })
+    );
+
+    test.take_nft_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&nft, &bob), 1);
+    assert_eq!(test.token_balance(&usdc, &bob), 900_000_000);
+    assert_eq!(test.token_balance(&usdc, &creators[0]), 3_500_000);
+    assert_eq!(test.token_balance(&usdc, &creators[1]), 1_500_000);
+    assert_eq!(test.token_balance(&usdc, &test.treasury), 1_000_000);
+    assert_eq!(test.token_balance(&usdc, &alice), 94_000_000);
+    assert!(test.offer(&offer).is_none());
+
+    let royalties = test.events::<RoyaltyPaid>();
+    assert_eq!(royalties.len(), 2);
+    assert_eq!(royalties[0].creator, creators[0]);
+    assert_eq!(royalties[0].amount, 3_500_000);
+    assert_eq!(royalties[0].nft_mint, nft);
+
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.filled_offers, 1);
+    assert_eq!(profile.volume, 100_000_000);
+}
+
+#[test]
+fn only_supply_one_mints_with_their_metadata_can_be_listed() {
+    let (
+        Market {
+            mut test,
+            alice,
+            usdc,
+            ..
+        },
+        creators,
+    ) = setup();
+
+    let fungible = test.create_mint(&spl_token::ID, 6);
+    test.mint_to(&fungible, &alice, 1_000_000);
+    test.set_nft_metadata(&fungible, &metadata(creators));
+    let editions = test.create_nft(&alice, &metadata(creators));
+    test.mint_to(&editions, &alice, 1);
+    for mint in [fungible, editions] {
+        assert_eq!(
+            test.make_nft_offer(&alice, &mint, &usdc, 100_000_000, None),
+            Err(program_error(ErrorCode::NotAnNft))
+        );
+    }
+
+    let nft = test.create_nft(&alice, &metadata(creators));
+    let other = test.create_nft(&alice, &metadata(creators));
+    let mut accounts = test.make_nft_offer_accounts(&alice, &nft, &usdc);
+    accounts.metadata = metadata_address(&other);
+    let args = escrow::instruction::MakeNftOffer {
+        token_b_wanted_amount: 100_000_000,
+        token_b_amount_basis: escrow::AmountBasis::Gross,
+        expires_at: None,
+        allowed_taker: None,
+        collection: None,
+    };
+    assert_eq!(
+        test.process(accounts, args, &[alice]),
+        Err(program_error(ErrorCode::NftMetadataMismatch))
+    );
+    assert_eq!(test.token_balance(&nft, &alice), 1);
+}
+
+#[test]
+fn collection_offers_need_a_verified_member_when_made_and_taken() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        creators,
+    ) = setup();
+    let collection = Pubkey::new_unique();
+
+    let unverified = test.create_nft(
+        &alice,
+        &NftMetadata {
+            collection: Some((collection, false)),
+            ..metadata(creators)
+        },
+    );
+    let outsider = test.create_nft(
+        &alice,
+        &NftMetadata {
+            collection: Some((Pubkey::new_unique(), true)),
+            ..metadata(creators)
+        },
+    );
+    for nft in [unverified, outsider] {
+        assert_eq!(
+            test.make_nft_offer(&alice, &nft, &usdc, 100_000_000, Some(collection)),
+            Err(program_error(ErrorCode::NftNotInCollection))
+        );
+    }
+
+    let member = NftMetadata {
+        collection: Some((collection, true)),
+        ..metadata(creators)
+    };
+    let nft = test.create_nft(&alice, &member);
+    let offer = test
+        .make_nft_offer(&alice, &nft, &usdc, 100_000_000, Some(collection))
+        .unwrap();
+
+    // Unverified from the collection after it was listed.
+    test.set_nft_metadata(
+        &nft,
+        &NftMetadata {
+            collection: Some((collection, false)),
+            ..member.clone()
+        },
+    );
+    assert_eq!(
+        test.take_nft_offer(&bob, &offer),
+        Err(program_error(ErrorCode::NftNotInCollection))
+    );
+
+    test.set_nft_metadata(&nft, &member);
+    test.take_nft_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&nft, &bob), 1);
+}
+
+#[test]
+fn nft_offers_cannot_be_taken_around_the_royalties() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        creators,
+    ) = setup();
+
+    let nft = test.create_nft(&alice, &metadata(creators));
+    let offer = test
+        .make_nft_offer(&alice, &nft, &usdc, 100_000_000, None)
+        .unwrap();
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(ErrorCode::OfferIsNft))
+    );
+    assert_eq!(
+        test.take_offers(&bob, &[offer], 100_000_000),
+        Err(program_error(ErrorCode::OfferIsNft))
+    );
+
+    // Leaving out the second creator.
+    let mut instruction = test.take_nft_offer_instruction(&bob, &offer);
+    instruction
+        .accounts
+        .truncate(instruction.accounts.len() - 2);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::RoyaltyAccountMismatch))
+    );
+
+    // Paying the first creator's share to someone else.
+    let mut instruction = test.take_nft_offer_instruction(&bob, &offer);
+    let first = instruction.accounts.len() - 4;
+    let stranger = Pubkey::new_unique();
+    instruction.accounts[first] = AccountMeta::new_readonly(stranger, false);
+    instruction.accounts[first + 1] =
+        AccountMeta::new(test.token_account_address(&usdc, &stranger), false);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::RoyaltyAccountMismatch))
+    );
+
+    // Nor can plain offers be taken as NFT offers.
+    let wif = test.create_mint(&spl_token::ID, 6);
+    test.mint_to(&wif, &alice, 10_000_000);
+    let plain = test
+        .make_offer(&bob, &usdc, 10_000_000, &wif, 10_000_000)
+        .unwrap();
+    test.set_nft_metadata(&usdc, &metadata(creators));
+    assert_eq!(
+        test.take_nft_offer(&alice, &plain),
+        Err(program_error(ErrorCode::OfferIsNotNft))
+    );
+
+    test.refund_offer(&alice, &offer).unwrap();
+    assert_eq!(test.token_balance(&nft, &alice), 1);
+    assert_eq!(test.token_balance(&usdc, &bob), 990_000_000);
+}
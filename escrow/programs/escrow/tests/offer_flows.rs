@@ -0,0 +1,277 @@
+//! Make, take and refund flows, run once with SPL Token mints and once with
+//! Token-2022 mints.
+
+mod common;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode,
+    prelude::{Pubkey, Rent},
+    Space,
+};
+use anchor_spl::{token::spl_token, token_2022::spl_token_2022};
+use escrow::{error::ErrorCode, MakerProfile, ANCHOR_DISCRIMINATOR};
+
+use common::*;
+
+const TOKEN_PROGRAMS: [Pubkey; 2] = [spl_token::ID, spl_token_2022::ID];
+
+// WIF has 9 decimals here, so the two legs differ in scale.
+fn setup(token_program: &Pubkey) -> Market {
+    Market::with(MarketOptions {
+        token_program: *token_program,
+        wif_decimals: 9,
+        ..MarketOptions::default()
+    })
+}
+
+// Paid by the maker with their first offer, and kept by the maker profile.
+fn maker_profile_rent() -> u64 {
+    Rent::default().minimum_balance(ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE)
+}
+
+#[test]
+fn taken_offer_swaps_both_legs_and_closes_the_offer_and_vault() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+        let alice_lamports = test.runtime.lamports(&alice);
+
+        let offer = test
+            .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+            .unwrap();
+        let vault = test.token_account_address(&usdc, &offer);
+        let vault_rent = test.runtime.lamports(&vault);
+        assert_eq!(test.token_balance(&usdc, &alice), 90_000_000);
+        assert_eq!(test.token_account(&vault).unwrap().amount, 10_000_000);
+        assert_eq!(
+            test.runtime.get_account(&vault).unwrap().owner,
+            token_program
+        );
+
+        test.take_offer(&bob, &offer).unwrap();
+
+        assert_eq!(test.token_balance(&usdc, &alice), 90_000_000);
+        assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+        assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+        assert_eq!(test.token_balance(&wif, &bob), 50_000_000);
+        assert!(test.runtime.get_account(&offer).is_none());
+        assert!(test.runtime.get_account(&vault).is_none());
+        // Bob paid for Alice's new token account and got the vault rent for
+        // closing it, the offer rent went back to Alice.
+        assert_eq!(
+            test.runtime.lamports(&alice),
+            alice_lamports - maker_profile_rent() - vault_rent
+        );
+    }
+}
+
+#[test]
+fn refunded_offer_returns_the_tokens_and_closes_the_offer_and_vault() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+        let alice_lamports = test.runtime.lamports(&alice);
+
+        let offer = test
+            .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+            .unwrap();
+        let vault = test.token_account_address(&usdc, &offer);
+
+        test.refund_offer(&alice, &offer).unwrap();
+
+        assert_eq!(test.token_balance(&usdc, &alice), 100_000_000);
+        assert!(test.runtime.get_account(&offer).is_none());
+        assert!(test.runtime.get_account(&vault).is_none());
+        assert_eq!(
+            test.runtime.lamports(&alice),
+            alice_lamports - maker_profile_rent()
+        );
+    }
+}
+
+#[test]
+fn partial_fills_keep_the_offer_open_until_the_last_one() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+
+        let offer = test
+            .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+            .unwrap();
+        let vault = test.token_account_address(&usdc, &offer);
+
+        test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+        let state = test.offer(&offer).unwrap();
+        assert_eq!(state.token_a_remaining_amount, 6_000_000);
+        assert_eq!(state.token_b_remaining_amount, 30_000_000);
+        assert_eq!(test.token_account(&vault).unwrap().amount, 6_000_000);
+        assert_eq!(test.token_balance(&usdc, &bob), 4_000_000);
+        assert_eq!(test.token_balance(&wif, &alice), 20_000_000);
+
+        // Asking for more than is left fails and leaves the offer as it was.
+        assert_eq!(
+            test.take_offer_partial(&bob, &offer, 30_000_001),
+            Err(program_error(ErrorCode::FillExceedsRemaining))
+        );
+
+        test.take_offer_partial(&bob, &offer, 30_000_000).unwrap();
+        assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+        assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+        assert!(test.runtime.get_account(&offer).is_none());
+        assert!(test.runtime.get_account(&vault).is_none());
+    }
+}
+
+#[test]
+fn protocol_fee_goes_to_the_treasury() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+        let admin = test.admin;
+        test.update_fee(&admin, 250).unwrap();
+
+        let offer = test
+            .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+            .unwrap();
+        test.take_offer(&bob, &offer).unwrap();
+
+        let treasury = test.treasury;
+        assert_eq!(test.token_balance(&wif, &treasury), 1_250_000);
+        assert_eq!(test.token_balance(&wif, &alice), 48_750_000);
+        assert_eq!(test.token_balance(&wif, &bob), 50_000_000);
+        assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    }
+}
+
+#[test]
+fn only_the_maker_can_refund() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+
+        let offer = test
+            .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+            .unwrap();
+
+        // Bob has a maker profile and a token A account of his own, but the
+        // offer is not his.
+        test.make_offer(&bob, &wif, 10_000_000, &usdc, 1_000_000)
+            .unwrap();
+        test.mint_to(&usdc, &bob, 1_000_000);
+        assert_eq!(
+            test.refund_offer(&bob, &offer),
+            Err(program_error(AnchorErrorCode::ConstraintSeeds))
+        );
+        assert_eq!(
+            test.offer(&offer).unwrap().token_a_remaining_amount,
+            10_000_000
+        );
+        assert_eq!(test.token_balance(&usdc, &alice), 90_000_000);
+    }
+}
+
+#[test]
+fn refunds_recreate_a_maker_token_account_closed_since_the_offer_was_made() {
+    for token_program in TOKEN_PROGRAMS {
+        let Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        } = setup(&token_program);
+        let expires_at = test.runtime.clock().unix_timestamp + 60;
+
+        let refunded = test
+            .make_offer(&alice, &usdc, 50_000_000, &wif, 50_000_000)
+            .unwrap();
+        let expired = test
+            .make_offer_with(
+                &alice,
+                &usdc,
+                &wif,
+                escrow::instruction::MakeOffer {
+                    token_a_offered_amount: 50_000_000,
+                    token_b_wanted_amount: 50_000_000,
+                    token_b_amount_basis: escrow::AmountBasis::Gross,
+                    expires_at: Some(expires_at),
+                    allowed_taker: None,
+                },
+            )
+            .unwrap();
+        let alice_usdc = test.token_account_address(&usdc, &alice);
+        let close = spl_token_2022::instruction::close_account(
+            &token_program,
+            &alice_usdc,
+            &alice,
+            &alice,
+            &[],
+        )
+        .unwrap();
+        test.runtime
+            .process_transaction(&[close], &[alice])
+            .unwrap();
+
+        test.refund_offer(&alice, &refunded).unwrap();
+        assert_eq!(test.token_balance(&usdc, &alice), 50_000_000);
+
+        let close = spl_token_2022::instruction::close_account(
+            &token_program,
+            &alice_usdc,
+            &alice,
+            &alice,
+            &[],
+        )
+        .unwrap();
+        test.mint_to(&usdc, &bob, 50_000_000);
+        let bob_usdc = test.token_account_address(&usdc, &bob);
+        let transfer = spl_token_2022::instruction::transfer_checked(
+            &token_program,
+            &alice_usdc,
+            &usdc,
+            &bob_usdc,
+            &alice,
+            &[],
+            50_000_000,
+            6,
+        )
+        .unwrap();
+        test.runtime
+            .process_transaction(&[transfer, close], &[alice])
+            .unwrap();
+
+        test.runtime.warp_to_timestamp(expires_at);
+        test.close_expired_offer(&bob, &expired).unwrap();
+        assert_eq!(test.token_balance(&usdc, &alice), 50_000_000);
+    }
+}
@@ -0,0 +1,363 @@
+mod common;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use escrow::{error::ErrorCode, PriceUpdateV2, Pricing, VerificationLevel, MAX_PRICE_AGE_SECONDS};
+
+use common::*;
+
+// A feed pricing one SOL (9 decimals) at 150.00 USDC (6 decimals).  The
+// market's second mint stands in for SOL: Alice holds 10 SOL and Bob 1,000
+// USDC.
+fn setup() -> (Market, Pubkey) {
+    let mut market = Market::with(MarketOptions {
+        wif_decimals: 9,
+        usdc_balances: [0, 1_000_000_000, 0],
+        wif_balances: [10_000_000_000, 0, 0],
+        ..MarketOptions::default()
+    });
+    let feed = market.test.create_feed(15_000, -2);
+    (market, feed)
+}
+
+impl EscrowTest {
+    fn price_feed(&self, address: &Pubkey) -> PriceUpdateV2 {
+        let account = self.runtime.get_account(address).unwrap();
+        PriceUpdateV2::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+}
+
+#[test]
+fn oracle_offer_is_taken_at_the_current_feed_price() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(
+        state.pricing,
+        Pricing::Oracle {
+            price_feed: feed,
+            feed_id: [7; 32],
+            spread_basis_points: 0
+        }
+    );
+    assert_eq!(state.token_b_remaining_amount, 300_000_000);
+
+    test.set_price(&feed, 16_000, -2);
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&sol, &bob), 2_000_000_000);
+    assert_eq!(test.token_balance(&usdc, &alice), 320_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 680_000_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn spread_is_applied_on_top_of_the_feed_price() {
+    let (
+        Market {
+            mut test,
+            alice,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let premium = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 100)
+        .unwrap();
+    let discount = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, -50)
+        .unwrap();
+
+    assert_eq!(
+        test.offer(&premium).unwrap().token_b_remaining_amount,
+        303_000_000
+    );
+    assert_eq!(
+        test.offer(&discount).unwrap().token_b_remaining_amount,
+        298_500_000
+    );
+
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 5_001),
+        Err(program_error(ErrorCode::SpreadTooLarge))
+    );
+}
+
+#[test]
+fn partial_fills_are_priced_from_the_feed_at_the_time_of_each_fill() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+
+    test.take_offer_partial(&bob, &offer, 150_000_000).unwrap();
+    assert_eq!(test.token_balance(&sol, &bob), 1_000_000_000);
+
+    // The remaining SOL is quoted at the new price.
+    test.set_price(&feed, 20_000, -2);
+    test.take_offer_partial(&bob, &offer, 200_000_000).unwrap();
+
+    assert_eq!(test.token_balance(&sol, &bob), 2_000_000_000);
+    assert_eq!(test.token_balance(&usdc, &alice), 350_000_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn take_is_rejected_while_the_feed_is_stale() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+
+    let published_at = test.price_feed(&feed).price_message.publish_time;
+    test.runtime
+        .warp_to_timestamp(published_at + MAX_PRICE_AGE_SECONDS + 1);
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(ErrorCode::StalePrice))
+    );
+    assert_eq!(
+        test.take_offer_partial(&bob, &offer, 150_000_000),
+        Err(program_error(ErrorCode::StalePrice))
+    );
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0),
+        Err(program_error(ErrorCode::StalePrice))
+    );
+
+    test.set_price(&feed, 15_000, -2);
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &alice), 300_000_000);
+}
+
+#[test]
+fn take_is_rejected_with_a_different_or_missing_feed() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+    let cheap_feed = test.create_feed(1, -2);
+
+    let mut accounts = test.take_offer_accounts(&bob, &offer);
+    accounts.price_feed = Some(cheap_feed);
+    assert_eq!(
+        test.process(
+            accounts,
+            escrow::instruction::TakeOffer {
+                max_token_b_amount: u64::MAX,
+            },
+            &[bob]
+        ),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+
+    let mut accounts = test.take_offer_accounts(&bob, &offer);
+    accounts.price_feed = None;
+    assert_eq!(
+        test.process(
+            accounts,
+            escrow::instruction::TakeOffer {
+                max_token_b_amount: u64::MAX,
+            },
+            &[bob]
+        ),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+
+    assert_eq!(test.token_balance(&usdc, &bob), 1_000_000_000);
+    assert!(test.offer(&offer).is_some());
+}
+
+#[test]
+fn take_is_rejected_once_the_feed_carries_another_price_series() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+
+    // The write authority of the update account posts a verified price of a
+    // different feed into it.
+    let mut update = test.price_feed(&feed);
+    update.price_message.feed_id = [8; 32];
+    update.price_message.price = 1;
+    test.set_price_update(&feed, &update, escrow::PYTH_RECEIVER_PROGRAM_ID);
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+    assert_eq!(
+        test.take_offers(&bob, &[offer], 1_000_000_000),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+    assert_eq!(test.token_balance(&usdc, &bob), 1_000_000_000);
+    assert!(test.offer(&offer).is_some());
+}
+
+#[test]
+fn oracle_offer_needs_a_usable_price() {
+    let (
+        Market {
+            mut test,
+            alice,
+            wif: sol,
+            usdc,
+            ..
+        },
+        _,
+    ) = setup();
+
+    let zero_feed = test.create_feed(0, 0);
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &zero_feed, 0),
+        Err(program_error(ErrorCode::InvalidPrice))
+    );
+
+    let negative_feed = test.create_feed(-15_000, -2);
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &negative_feed, 0),
+        Err(program_error(ErrorCode::InvalidPrice))
+    );
+}
+
+#[test]
+fn oracle_offer_needs_a_fully_verified_pyth_price() {
+    let (
+        Market {
+            mut test,
+            alice,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let mut update = test.price_feed(&feed);
+    update.verification_level = VerificationLevel::Partial { num_signatures: 5 };
+    let partial_feed = Pubkey::new_unique();
+    test.set_price_update(&partial_feed, &update, escrow::PYTH_RECEIVER_PROGRAM_ID);
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &partial_feed, 0),
+        Err(program_error(ErrorCode::InvalidPrice))
+    );
+
+    // The same bytes in an account the Pyth receiver does not own could have
+    // been written by anyone.
+    let forged_feed = Pubkey::new_unique();
+    let update = test.price_feed(&feed);
+    test.set_price_update(&forged_feed, &update, Pubkey::new_unique());
+    assert_eq!(
+        test.make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &forged_feed, 0),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+}
+
+#[test]
+fn taker_limits_hold_when_the_price_moves_before_the_take() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            wif: sol,
+            usdc,
+            ..
+        },
+        feed,
+    ) = setup();
+
+    let offer = test
+        .make_oracle_offer(&alice, &sol, 2_000_000_000, &usdc, &feed, 0)
+        .unwrap();
+    // Bob saw 2 SOL quoted at 300 USDC, but the price rises to 160.00.
+    test.set_price(&feed, 16_000, -2);
+
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let data = escrow::instruction::TakeOffer {
+        max_token_b_amount: 300_000_000,
+    };
+    assert_eq!(
+        test.process(accounts, data, &[bob]),
+        Err(program_error(ErrorCode::TakerLimitExceeded))
+    );
+
+    // 150 USDC now buys less than the 1 SOL Bob expected.
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let data = escrow::instruction::TakeOfferPartial {
+        token_b_amount: 150_000_000,
+        min_token_a_amount: 1_000_000_000,
+    };
+    assert_eq!(
+        test.process(accounts, data, &[bob]),
+        Err(program_error(ErrorCode::TakerLimitExceeded))
+    );
+
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let data = escrow::instruction::TakeOffer {
+        max_token_b_amount: 320_000_000,
+    };
+    test.process(accounts, data, &[bob]).unwrap();
+    assert_eq!(test.token_balance(&usdc, &alice), 320_000_000);
+}
@@ -0,0 +1,299 @@
+mod common;
+
+use anchor_lang::{
+    prelude::{Pubkey, Rent},
+    solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack},
+    Space,
+};
+use anchor_spl::token::spl_token;
+use escrow::{error::ErrorCode, AmountBasis, MakerProfile, ANCHOR_DISCRIMINATOR, NATIVE_SOL_MINT};
+
+use common::*;
+
+// Alice and Bob both hold 100 USDC, and trade it for SOL.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        usdc_balances: [100_000_000, 100_000_000, 0],
+        wif_balances: [0; 3],
+        ..MarketOptions::default()
+    })
+}
+
+fn token_account_rent() -> u64 {
+    Rent::default().minimum_balance(spl_token::state::Account::LEN)
+}
+
+// Paid by the maker with their first offer, and kept by the maker profile.
+fn maker_profile_rent() -> u64 {
+    Rent::default().minimum_balance(ANCHOR_DISCRIMINATOR + MakerProfile::INIT_SPACE)
+}
+
+impl EscrowTest {
+    fn refund_sol_offer(&mut self, maker: &Pubkey, offer: &Pubkey) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::RefundSolOffer {
+            maker: *maker,
+            maker_profile: maker_profile_address(maker),
+            offer: *offer,
+        };
+        self.process(accounts, escrow::instruction::RefundSolOffer {}, &[*maker])
+    }
+}
+
+#[test]
+fn tokens_can_be_sold_for_sol() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let alice_lamports = test.runtime.lamports(&alice);
+    let bob_lamports = test.runtime.lamports(&bob);
+
+    let offer = test
+        .make_offer_for_sol(&alice, &usdc, 10_000_000, 2 * LAMPORTS_PER_SOL)
+        .unwrap();
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_mint_b, NATIVE_SOL_MINT);
+    assert_eq!(state.token_b_remaining_amount, 2 * LAMPORTS_PER_SOL);
+
+    test.take_offer_for_sol(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &alice), 90_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 110_000_000);
+    // Alice gets the offer rent back, the vault rent goes to Bob as with
+    // token-for-token offers.
+    assert_eq!(
+        test.runtime.lamports(&alice),
+        alice_lamports + 2 * LAMPORTS_PER_SOL - token_account_rent() - maker_profile_rent()
+    );
+    assert_eq!(
+        test.runtime.lamports(&bob),
+        bob_lamports - 2 * LAMPORTS_PER_SOL + token_account_rent()
+    );
+    assert!(test.runtime.get_account(&offer).is_none());
+    assert!(test
+        .runtime
+        .get_account(&test.token_account_address(&usdc, &offer))
+        .is_none());
+}
+
+#[test]
+fn protocol_fee_on_a_sol_leg_is_paid_in_lamports() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+    let treasury = test.treasury;
+
+    let offer = test
+        .make_offer_for_sol(&alice, &usdc, 10_000_000, 2 * LAMPORTS_PER_SOL)
+        .unwrap();
+    let alice_lamports = test.runtime.lamports(&alice);
+    let offer_rent = test.runtime.lamports(&offer);
+
+    let treasury_lamports = test.runtime.lamports(&treasury);
+    test.take_offer_for_sol(&bob, &offer).unwrap();
+
+    let fee = 2 * LAMPORTS_PER_SOL / 100;
+    assert_eq!(test.runtime.lamports(&treasury), treasury_lamports + fee);
+    assert_eq!(
+        test.runtime.lamports(&alice),
+        alice_lamports + offer_rent + 2 * LAMPORTS_PER_SOL - fee
+    );
+}
+
+#[test]
+fn tokens_can_be_bought_with_sol() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let alice_lamports = test.runtime.lamports(&alice);
+    let bob_lamports = test.runtime.lamports(&bob);
+
+    let offer = test
+        .make_sol_offer(&alice, 3 * LAMPORTS_PER_SOL, &usdc, 50_000_000)
+        .unwrap();
+    let offer_rent =
+        Rent::default().minimum_balance(ANCHOR_DISCRIMINATOR + escrow::Offer::INIT_SPACE);
+    assert_eq!(
+        test.runtime.lamports(&offer),
+        offer_rent + 3 * LAMPORTS_PER_SOL
+    );
+    assert_eq!(test.offer(&offer).unwrap().token_mint_a, NATIVE_SOL_MINT);
+
+    test.take_sol_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &alice), 150_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 50_000_000);
+    assert_eq!(
+        test.runtime.lamports(&alice),
+        alice_lamports - 3 * LAMPORTS_PER_SOL - maker_profile_rent()
+    );
+    // Bob pays the rent of the treasury's token account, which is created on
+    // the first take.
+    assert_eq!(
+        test.runtime.lamports(&bob),
+        bob_lamports + 3 * LAMPORTS_PER_SOL - token_account_rent()
+    );
+    assert!(test.runtime.get_account(&offer).is_none());
+}
+
+#[test]
+fn sol_offer_can_be_refunded_by_the_maker() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let alice_lamports = test.runtime.lamports(&alice);
+
+    let offer = test
+        .make_sol_offer(&alice, 3 * LAMPORTS_PER_SOL, &usdc, 50_000_000)
+        .unwrap();
+
+    assert!(test.refund_sol_offer(&bob, &offer).is_err());
+
+    test.refund_sol_offer(&alice, &offer).unwrap();
+
+    assert_eq!(
+        test.runtime.lamports(&alice),
+        alice_lamports - maker_profile_rent()
+    );
+    assert!(test.runtime.get_account(&offer).is_none());
+}
+
+#[test]
+fn expired_sol_offer_cannot_be_taken_but_can_be_closed_by_anyone() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let alice_lamports = test.runtime.lamports(&alice);
+    let expires_at = test.runtime.clock().unix_timestamp + 60;
+
+    let offer = test
+        .make_sol_offer_with(
+            &alice,
+            &usdc,
+            escrow::instruction::MakeSolOffer {
+                lamports_offered: 3 * LAMPORTS_PER_SOL,
+                token_b_wanted_amount: 50_000_000,
+                token_b_amount_basis: AmountBasis::Gross,
+                expires_at: Some(expires_at),
+                allowed_taker: None,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        test.close_expired_sol_offer(&offer),
+        Err(program_error(ErrorCode::OfferNotExpired))
+    );
+
+    test.runtime.warp_to_timestamp(expires_at);
+
+    assert_eq!(
+        test.take_sol_offer(&bob, &offer),
+        Err(program_error(ErrorCode::OfferExpired))
+    );
+
+    test.close_expired_sol_offer(&offer).unwrap();
+
+    assert_eq!(
+        test.runtime.lamports(&alice),
+        alice_lamports - maker_profile_rent()
+    );
+    assert!(test.runtime.get_account(&offer).is_none());
+}
+
+#[test]
+fn sol_instructions_reject_token_offers() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    test.mint_to(&wif, &bob, 50_000_000);
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    assert_eq!(
+        test.refund_sol_offer(&alice, &offer),
+        Err(program_error(ErrorCode::NotNativeSol))
+    );
+    assert_eq!(
+        test.take_sol_offer(&bob, &offer),
+        Err(program_error(ErrorCode::NotNativeSol))
+    );
+    assert_eq!(
+        test.take_offer_for_sol(&bob, &offer),
+        Err(program_error(ErrorCode::NotNativeSol))
+    );
+}
+
+fn update_treasury(test: &mut EscrowTest, treasury: &Pubkey) -> Result<(), TransactionError> {
+    let admin = test.admin;
+    let accounts = escrow::accounts::UpdateTreasury {
+        admin,
+        config: config_address(),
+        treasury: *treasury,
+    };
+    test.process(accounts, escrow::instruction::UpdateTreasury {}, &[admin])?;
+    test.treasury = *treasury;
+    Ok(())
+}
+
+#[test]
+fn lamport_fees_below_the_rent_exempt_minimum_reach_the_treasury() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        ..
+    } = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    // An empty account could not receive a fee smaller than the rent-exempt
+    // minimum, so it cannot become the treasury.
+    let treasury = Pubkey::new_unique();
+    assert_eq!(
+        update_treasury(&mut test, &treasury),
+        Err(program_error(ErrorCode::InvalidTreasury))
+    );
+    test.runtime
+        .airdrop(&treasury, Rent::default().minimum_balance(0));
+    update_treasury(&mut test, &treasury).unwrap();
+
+    let offer = test
+        .make_offer_for_sol(&alice, &usdc, 10_000_000, 10_000)
+        .unwrap();
+    test.take_offer_for_sol(&bob, &offer).unwrap();
+
+    assert_eq!(
+        test.runtime.lamports(&treasury),
+        Rent::default().minimum_balance(0) + 100
+    );
+}
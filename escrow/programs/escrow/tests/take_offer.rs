@@ -0,0 +1,206 @@
+mod common;
+
+use anchor_lang::{error::ErrorCode as AnchorErrorCode, prelude::Pubkey};
+use anchor_spl::{token::spl_token::error::TokenError, token_2022::spl_token_2022};
+
+use common::*;
+
+impl EscrowTest {
+    /// Freezes `account`, using `mint_authority` as the freeze authority.
+    fn freeze(&mut self, mint: &Pubkey, account: &Pubkey) {
+        let instruction = spl_token_2022::instruction::freeze_account(
+            &self.token_program_of(mint),
+            account,
+            mint,
+            &self.mint_authority,
+            &[],
+        )
+        .unwrap();
+        self.runtime
+            .process_transaction(&[instruction], &[self.mint_authority])
+            .unwrap();
+    }
+}
+
+#[test]
+fn take_offer_accepts_the_offer_pda() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert!(test.runtime.get_account(&offer).is_none());
+}
+
+#[test]
+fn take_offer_rejects_a_copy_of_an_offer_at_another_address() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    // Byte-for-byte copy of the real offer, owned by the escrow program, so
+    // every `has_one` check passes.  Only the address gives it away.
+    let spoofed_offer = Pubkey::new_unique();
+    let account = test.runtime.get_account(&offer).unwrap().clone();
+    test.runtime.set_account(spoofed_offer, account);
+    test.mint_to(&usdc, &spoofed_offer, 10_000_000);
+
+    assert_eq!(
+        test.take_offer(&bob, &spoofed_offer),
+        Err(program_error(AnchorErrorCode::ConstraintSeeds))
+    );
+
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 0);
+    assert_eq!(test.token_balance(&usdc, &spoofed_offer), 10_000_000);
+
+    // The genuine offer is unaffected.
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+}
+
+#[test]
+fn take_offer_rejects_an_offer_claiming_another_id() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    // Same account at the same address, but the stored id no longer matches
+    // the seeds the address was derived from.
+    let mut account = test.runtime.get_account(&offer).unwrap().clone();
+    account.data[8..16].copy_from_slice(&2u64.to_le_bytes());
+    test.runtime.set_account(offer, account);
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(AnchorErrorCode::ConstraintSeeds))
+    );
+}
+
+#[test]
+fn take_offer_fails_without_closing_anything_when_the_taker_account_is_frozen() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let vault = test.token_account_address(&usdc, &offer);
+
+    test.mint_to(&usdc, &bob, 0);
+    test.freeze(&usdc, &test.token_account_address(&usdc, &bob));
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(TokenError::AccountFrozen as u32))
+    );
+
+    // The payment to the maker is rolled back along with the failed
+    // withdrawal, and the offer can still be refunded.
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 0);
+    assert_eq!(test.token_account(&vault).unwrap().amount, 10_000_000);
+    assert!(test.offer(&offer).is_some());
+
+    test.refund_offer(&alice, &offer).unwrap();
+    assert_eq!(test.token_balance(&usdc, &alice), 100_000_000);
+}
+
+#[test]
+fn take_offer_fails_without_closing_anything_when_the_vault_is_frozen() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let vault = test.token_account_address(&usdc, &offer);
+    test.freeze(&usdc, &vault);
+
+    assert_eq!(
+        test.take_offer(&bob, &offer),
+        Err(program_error(TokenError::AccountFrozen as u32))
+    );
+    assert_eq!(
+        test.take_offer_partial(&bob, &offer, 50_000_000),
+        Err(program_error(TokenError::AccountFrozen as u32))
+    );
+
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 0);
+    assert_eq!(test.token_account(&vault).unwrap().amount, 10_000_000);
+    assert!(test.offer(&offer).is_some());
+}
+
+#[test]
+fn final_partial_fill_fails_without_closing_anything_when_the_taker_account_is_frozen() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let vault = test.token_account_address(&usdc, &offer);
+
+    test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+    test.freeze(&usdc, &test.token_account_address(&usdc, &bob));
+
+    assert_eq!(
+        test.take_offer_partial(&bob, &offer, 30_000_000),
+        Err(program_error(TokenError::AccountFrozen as u32))
+    );
+
+    assert_eq!(test.token_balance(&wif, &alice), 20_000_000);
+    assert_eq!(test.token_account(&vault).unwrap().amount, 6_000_000);
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_a_remaining_amount, 6_000_000);
+    assert_eq!(state.token_b_remaining_amount, 30_000_000);
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use escrow::{error::ErrorCode, OfferTaken};
+
+use common::*;
+
+// Alice and Carol sell USDC for WIF, Bob buys.
+fn setup() -> Market {
+    Market::with(MarketOptions {
+        usdc_balances: [100_000_000, 0, 100_000_000],
+        wif_balances: [0, 1_000_000_000, 0],
+        ..MarketOptions::default()
+    })
+}
+
+#[test]
+fn sweep_fills_offers_in_order_up_to_the_maximum_spend() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    // 4, 5 and 6 WIF per USDC.
+    let cheapest = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let middle = test
+        .make_offer(&carol, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+    let dearest = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 60_000_000)
+        .unwrap();
+
+    test.take_offers(&bob, &[cheapest, middle, dearest], 100_000_000)
+        .unwrap();
+
+    // The first two in full, 10 WIF worth of the last one.
+    assert_eq!(test.token_balance(&wif, &bob), 900_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 21_666_666);
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert_eq!(test.token_balance(&wif, &carol), 50_000_000);
+    assert!(test.offer(&cheapest).is_none());
+    assert!(test.offer(&middle).is_none());
+    assert!(test
+        .runtime
+        .get_account(&test.token_account_address(&usdc, &middle))
+        .is_none());
+
+    let state = test.offer(&dearest).unwrap();
+    assert_eq!(state.token_a_remaining_amount, 8_333_334);
+    assert_eq!(state.token_b_remaining_amount, 50_000_000);
+
+    let events = test.events::<OfferTaken>();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[1].maker, carol);
+    assert_eq!(events[1].token_a_remaining_amount, 0);
+    assert_eq!(events[2].token_b_amount, 10_000_000);
+
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.open_offers, 1);
+    assert_eq!(profile.filled_offers, 1);
+    assert_eq!(profile.volume, 50_000_000);
+    let profile = test.maker_profile(&carol).unwrap();
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 1);
+    assert_eq!(profile.volume, 50_000_000);
+}
+
+#[test]
+fn sweep_pays_the_protocol_fee_on_every_fill() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    let first = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let second = test
+        .make_offer(&carol, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    test.take_offers(&bob, &[first, second], 1_000_000_000)
+        .unwrap();
+
+    let treasury = test.treasury;
+    assert_eq!(test.token_balance(&wif, &treasury), 400_000 + 500_000);
+    assert_eq!(test.token_balance(&wif, &alice), 39_600_000);
+    assert_eq!(test.token_balance(&wif, &carol), 49_500_000);
+    assert_eq!(test.token_balance(&wif, &bob), 910_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 20_000_000);
+}
+
+#[test]
+fn sweep_quotes_oracle_offers_from_their_feed() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    // 4.5 WIF per USDC.
+    let feed = test.create_feed(450, -2);
+
+    let fixed = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let oracle = test
+        .make_oracle_offer(&carol, &usdc, 10_000_000, &wif, &feed, 0)
+        .unwrap();
+    test.set_price(&feed, 500, -2);
+
+    test.take_offers(&bob, &[fixed, oracle], 90_000_000)
+        .unwrap();
+
+    assert!(test.offer(&fixed).is_none());
+    assert!(test.offer(&oracle).is_none());
+    assert_eq!(test.token_balance(&wif, &carol), 50_000_000);
+    assert_eq!(test.token_balance(&wif, &bob), 910_000_000);
+}
+
+#[test]
+fn sweep_stops_when_what_is_left_buys_nothing() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let first = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    // One base unit of USDC for 10 WIF.
+    let second = test.make_offer(&carol, &usdc, 1, &wif, 10_000_000).unwrap();
+
+    test.take_offers(&bob, &[first, second], 40_000_001)
+        .unwrap();
+
+    assert!(test.offer(&first).is_none());
+    assert_eq!(
+        test.offer(&second).unwrap().token_b_remaining_amount,
+        10_000_000
+    );
+    assert_eq!(test.token_balance(&wif, &bob), 960_000_000);
+
+    // Nothing at all is an error.
+    assert_eq!(
+        test.take_offers(&bob, &[second], 1),
+        Err(program_error(ErrorCode::SweepFilledNothing))
+    );
+    assert_eq!(
+        test.take_offers(&bob, &[second], 0),
+        Err(program_error(ErrorCode::InvalidAmount))
+    );
+}
+
+#[test]
+fn one_bad_offer_fails_the_whole_sweep() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    test.mint_to(&wif, &carol, 100_000_000);
+
+    let good = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let other_pair = test
+        .make_offer(&carol, &wif, 10_000_000, &usdc, 40_000_000)
+        .unwrap();
+    let reserved = test
+        .make_offer_with(
+            &carol,
+            &usdc,
+            &wif,
+            escrow::instruction::MakeOffer {
+                token_a_offered_amount: 10_000_000,
+                token_b_wanted_amount: 40_000_000,
+                token_b_amount_basis: escrow::AmountBasis::Gross,
+                expires_at: None,
+                allowed_taker: Some(alice),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        test.take_offers(&bob, &[good, other_pair], 80_000_000),
+        Err(program_error(ErrorCode::SweepAccountMismatch))
+    );
+    assert_eq!(
+        test.take_offers(&bob, &[good, reserved], 80_000_000),
+        Err(program_error(ErrorCode::TakerNotAllowed))
+    );
+    // Listed twice, the offer is gone by the time it comes up again.
+    assert_eq!(
+        test.take_offers(&bob, &[good, good], 80_000_000),
+        Err(program_error(AnchorErrorCode::AccountNotInitialized))
+    );
+
+    assert!(test.offer(&good).is_some());
+    assert_eq!(test.token_balance(&wif, &bob), 1_000_000_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 0);
+}
+
+#[test]
+fn sweep_rejects_accounts_that_do_not_belong_to_the_offer() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let alices = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let carols = test
+        .make_offer(&carol, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+
+    // Alice's offer with Carol's vault, then with Carol's profile.
+    for index in [1, 3] {
+        let mut instruction = test.take_offers_instruction(&bob, &[alices, carols], 80_000_000, 0);
+        let offset = instruction.accounts.len() - 12;
+        instruction.accounts[offset + index].pubkey =
+            instruction.accounts[offset + 6 + index].pubkey;
+        assert_eq!(
+            test.runtime.process_transaction(&[instruction], &[bob]),
+            Err(program_error(ErrorCode::SweepAccountMismatch))
+        );
+    }
+
+    // Incomplete accounts for an offer.
+    let mut instruction = test.take_offers_instruction(&bob, &[alices], 40_000_000, 0);
+    instruction.accounts.pop();
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::SweepAccountMismatch))
+    );
+}
+
+#[test]
+fn sweep_fails_if_it_buys_less_than_the_minimum() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        carol,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let feed = test.create_feed(450, -2);
+
+    let fixed = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 40_000_000)
+        .unwrap();
+    let oracle = test
+        .make_oracle_offer(&carol, &usdc, 10_000_000, &wif, &feed, 0)
+        .unwrap();
+    // The oracle offer now costs 50 WIF, so 80 WIF buys 18 USDC.
+    test.set_price(&feed, 500, -2);
+
+    let instruction = test.take_offers_instruction(&bob, &[fixed, oracle], 80_000_000, 20_000_000);
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(ErrorCode::TakerLimitExceeded))
+    );
+    assert!(test.offer(&fixed).is_some());
+
+    let instruction = test.take_offers_instruction(&bob, &[fixed, oracle], 80_000_000, 18_000_000);
+    test.runtime
+        .process_transaction(&[instruction], &[bob])
+        .unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 18_000_000);
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use anchor_lang::{prelude::Pubkey, solana_program::native_token::LAMPORTS_PER_SOL};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee::instruction::initialize_transfer_fee_config, ExtensionType},
+};
+use escrow::{error::ErrorCode, AmountBasis, OfferMade};
+
+use common::*;
+
+// Both mints are Token-2022 mints charging a 1% transfer fee.
+fn setup() -> Market {
+    Market::with_mints(
+        MarketOptions {
+            token_program: spl_token_2022::ID,
+            ..MarketOptions::default()
+        },
+        |test, _, decimals| test.create_mint_with_transfer_fee(decimals, 100, u64::MAX),
+    )
+}
+
+fn make_offer(
+    test: &mut EscrowTest,
+    maker: &Pubkey,
+    token_mint_a: &Pubkey,
+    token_mint_b: &Pubkey,
+    token_b_amount_basis: AmountBasis,
+) -> Pubkey {
+    test.make_offer_with(
+        maker,
+        token_mint_a,
+        token_mint_b,
+        escrow::instruction::MakeOffer {
+            token_a_offered_amount: 10_000_000,
+            token_b_wanted_amount: 50_000_000,
+            token_b_amount_basis,
+            expires_at: None,
+            allowed_taker: None,
+        },
+    )
+    .unwrap()
+}
+
+impl EscrowTest {
+    /// A Token-2022 mint that charges `transfer_fee_basis_points` on every
+    /// transfer, up to `maximum_fee`.
+    fn create_mint_with_transfer_fee(
+        &mut self,
+        decimals: u8,
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    ) -> Pubkey {
+        self.create_mint_with_extensions(
+            &spl_token_2022::ID,
+            decimals,
+            &[ExtensionType::TransferFeeConfig],
+            |mint| {
+                vec![initialize_transfer_fee_config(
+                    &spl_token_2022::ID,
+                    mint,
+                    None,
+                    None,
+                    transfer_fee_basis_points,
+                    maximum_fee,
+                )
+                .unwrap()]
+            },
+        )
+    }
+}
+
+#[test]
+fn offer_records_what_landed_in_the_vault() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Gross);
+
+    let vault = test.token_account_address(&usdc, &offer);
+    assert_eq!(test.token_account(&vault).unwrap().amount, 9_900_000);
+    assert_eq!(
+        test.offer(&offer).unwrap().token_a_remaining_amount,
+        9_900_000
+    );
+
+    let events = test.events::<OfferMade>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].token_a_offered_amount, 9_900_000);
+    assert_eq!(events[0].token_b_wanted_amount, 50_000_000);
+}
+
+#[test]
+fn gross_offer_leaves_the_transfer_fee_to_the_maker() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Gross);
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&wif, &bob), 50_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 49_500_000);
+    // The taker bears the fee on the way out of the vault.
+    assert_eq!(test.token_balance(&usdc, &bob), 9_801_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn net_offer_has_the_taker_pay_the_transfer_fee() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Net);
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000 - 50_505_051);
+}
+
+#[test]
+fn take_counts_the_transfer_fee_of_net_offers_against_the_maximum() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Net);
+
+    // 50 WIF is what the offer wants, but not what Bob pays for it.
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let data = escrow::instruction::TakeOffer {
+        max_token_b_amount: 50_505_050,
+    };
+    assert_eq!(
+        test.process(accounts, data, &[bob]),
+        Err(program_error(ErrorCode::TakerLimitExceeded))
+    );
+
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let data = escrow::instruction::TakeOffer {
+        max_token_b_amount: 50_505_051,
+    };
+    test.process(accounts, data, &[bob]).unwrap();
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000 - 50_505_051);
+}
+
+#[test]
+fn net_offer_pays_the_maker_exactly_on_every_partial_fill() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Net);
+
+    test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+    assert_eq!(test.token_balance(&wif, &alice), 20_000_000);
+
+    test.take_offer_partial(&bob, &offer, 30_000_000).unwrap();
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn sweep_counts_the_transfer_fee_of_net_offers_against_the_maximum() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Net);
+    test.take_offers(&bob, &[offer], 50_000_000).unwrap();
+
+    // Paying the full 50 WIF net would cost 50_505_051 with the fee on top.
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000 - 49_994_898);
+    assert_eq!(test.token_balance(&wif, &alice), 49_494_949);
+    assert_eq!(
+        test.offer(&offer).unwrap().token_b_remaining_amount,
+        505_051
+    );
+}
+
+#[test]
+fn net_offer_still_deducts_the_protocol_fee() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Net);
+    test.take_offer(&bob, &offer).unwrap();
+
+    // The maker's share arrives in full, the protocol fee is sent as is.
+    let treasury = test.treasury;
+    assert_eq!(test.token_balance(&wif, &alice), 49_500_000);
+    assert_eq!(test.token_balance(&wif, &treasury), 495_000);
+}
+
+#[test]
+fn top_up_only_counts_what_landed_in_the_vault() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Gross);
+    test.update_offer(&alice, &offer, 15_000_000, 75_000_000)
+        .unwrap();
+
+    let vault = test.token_account_address(&usdc, &offer);
+    assert_eq!(test.token_account(&vault).unwrap().amount, 14_949_000);
+    assert_eq!(
+        test.offer(&offer).unwrap().token_a_remaining_amount,
+        14_949_000
+    );
+
+    // Withdrawals leave the vault in full, whatever arrives at the maker.
+    test.update_offer(&alice, &offer, 4_949_000, 25_000_000)
+        .unwrap();
+    assert_eq!(test.token_account(&vault).unwrap().amount, 4_949_000);
+    assert_eq!(
+        test.offer(&offer).unwrap().token_a_remaining_amount,
+        4_949_000
+    );
+}
+
+#[test]
+fn net_sol_offer_has_the_taker_pay_the_transfer_fee() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        wif,
+        ..
+    } = setup();
+
+    let offer = test
+        .make_sol_offer_with(
+            &alice,
+            &wif,
+            escrow::instruction::MakeSolOffer {
+                lamports_offered: LAMPORTS_PER_SOL,
+                token_b_wanted_amount: 50_000_000,
+                token_b_amount_basis: AmountBasis::Net,
+                expires_at: None,
+                allowed_taker: None,
+            },
+        )
+        .unwrap();
+    test.take_sol_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000 - 50_505_051);
+}
+
+#[test]
+fn refund_harvests_the_fees_withheld_in_the_vault() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = setup();
+
+    let offer = make_offer(&mut test, &alice, &usdc, &wif, AmountBasis::Gross);
+    let vault = test.token_account_address(&usdc, &offer);
+    test.refund_offer(&alice, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &alice), 90_000_000 + 9_801_000);
+    assert!(test.runtime.get_account(&vault).is_none());
+}
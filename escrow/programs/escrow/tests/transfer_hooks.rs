@@ -0,0 +1,258 @@
+mod common;
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_hook, ExtensionType},
+};
+use spl_tlv_account_resolution::error::AccountResolutionError;
+use spl_transfer_hook_interface::error::TransferHookError;
+
+use common::*;
+
+// Alice and Bob hold 100 USDC, a Token-2022 mint, and 100 of `hooked`,
+// which runs the counter hook and has been allowed by the admin.
+fn setup() -> (Market, Pubkey) {
+    let mut market = Market::with(MarketOptions {
+        token_program: spl_token_2022::ID,
+        usdc_balances: [100_000_000, 100_000_000, 0],
+        wif_balances: [0; 3],
+        ..MarketOptions::default()
+    });
+    let test = &mut market.test;
+    let hooked = test.create_mint_with_counter_hook(6);
+    let admin = test.admin;
+    test.allow_mint(&admin, &hooked).unwrap();
+    test.mint_to(&hooked, &market.alice, 100_000_000);
+    test.mint_to(&hooked, &market.bob, 100_000_000);
+    (market, hooked)
+}
+
+impl EscrowTest {
+    /// A Token-2022 mint whose transfers run the counter hook, with its extra
+    /// account metas and counter accounts set up.
+    fn create_mint_with_counter_hook(&mut self, decimals: u8) -> Pubkey {
+        let mint = self.create_mint_with_extensions(
+            &spl_token_2022::ID,
+            decimals,
+            &[ExtensionType::TransferHook],
+            |mint| {
+                vec![transfer_hook::instruction::initialize(
+                    &spl_token_2022::ID,
+                    mint,
+                    None,
+                    Some(COUNTER_HOOK_ID),
+                )
+                .unwrap()]
+            },
+        );
+
+        let data = extra_account_metas_data(&mint);
+        self.runtime.set_account(
+            extra_account_metas_address(&mint),
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: COUNTER_HOOK_ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        self.runtime.set_account(
+            counter_address(&mint),
+            Account {
+                lamports: Rent::default().minimum_balance(8),
+                data: vec![0; 8],
+                owner: COUNTER_HOOK_ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        mint
+    }
+
+    /// Number of transfers of `mint` the counter hook has seen.
+    fn hook_invocations(&self, mint: &Pubkey) -> u64 {
+        let data = &self
+            .runtime
+            .get_account(&counter_address(mint))
+            .unwrap()
+            .data;
+        u64::from_le_bytes(data[..8].try_into().unwrap())
+    }
+}
+
+#[test]
+fn offered_hooked_mint_runs_the_hook_on_make_and_take() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        hooked,
+    ) = setup();
+
+    let offer = test
+        .make_offer(&alice, &hooked, 10_000_000, &usdc, 50_000_000)
+        .unwrap();
+    assert_eq!(test.hook_invocations(&hooked), 1);
+
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.hook_invocations(&hooked), 2);
+
+    assert_eq!(test.token_balance(&hooked, &bob), 110_000_000);
+    assert_eq!(test.token_balance(&usdc, &alice), 150_000_000);
+    assert!(test.offer(&offer).is_none());
+}
+
+#[test]
+fn wanted_hooked_mint_runs_the_hook_for_the_maker_and_the_treasury() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        hooked,
+    ) = setup();
+    let admin = test.admin;
+    test.update_fee(&admin, 100).unwrap();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &hooked, 50_000_000)
+        .unwrap();
+    test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+    assert_eq!(test.hook_invocations(&hooked), 2);
+
+    test.take_offer(&bob, &offer).unwrap();
+    assert_eq!(test.hook_invocations(&hooked), 4);
+
+    let treasury = test.treasury;
+    assert_eq!(
+        test.token_balance(&hooked, &alice),
+        100_000_000 + 49_500_000
+    );
+    assert_eq!(test.token_balance(&hooked, &treasury), 500_000);
+    assert_eq!(test.token_balance(&usdc, &bob), 110_000_000);
+}
+
+#[test]
+fn maker_can_update_and_refund_a_hooked_offer() {
+    let (
+        Market {
+            mut test,
+            alice,
+            usdc,
+            ..
+        },
+        hooked,
+    ) = setup();
+
+    let offer = test
+        .make_offer(&alice, &hooked, 10_000_000, &usdc, 50_000_000)
+        .unwrap();
+    test.update_offer(&alice, &offer, 15_000_000, 75_000_000)
+        .unwrap();
+    test.update_offer(&alice, &offer, 5_000_000, 25_000_000)
+        .unwrap();
+    test.refund_offer(&alice, &offer).unwrap();
+
+    assert_eq!(test.hook_invocations(&hooked), 4);
+    assert_eq!(test.token_balance(&hooked, &alice), 100_000_000);
+}
+
+#[test]
+fn expired_hooked_offer_can_be_closed_by_anyone() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        hooked,
+    ) = setup();
+
+    let expires_at = test.runtime.clock().unix_timestamp + 60;
+    let offer = test
+        .make_offer_with(
+            &alice,
+            &hooked,
+            &usdc,
+            escrow::instruction::MakeOffer {
+                token_a_offered_amount: 10_000_000,
+                token_b_wanted_amount: 50_000_000,
+                token_b_amount_basis: escrow::AmountBasis::Gross,
+                expires_at: Some(expires_at),
+                allowed_taker: None,
+            },
+        )
+        .unwrap();
+    test.runtime.warp_to_timestamp(expires_at);
+
+    test.close_expired_offer(&bob, &offer).unwrap();
+    assert_eq!(test.hook_invocations(&hooked), 2);
+    assert_eq!(test.token_balance(&hooked, &alice), 100_000_000);
+}
+
+#[test]
+fn take_fails_without_the_hook_accounts() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            ..
+        },
+        hooked,
+    ) = setup();
+
+    let offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &hooked, 50_000_000)
+        .unwrap();
+
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let mut remaining_accounts = test.transfer_hook_accounts_for(&accounts);
+    assert_eq!(remaining_accounts.len(), 3);
+
+    // The hook program and its extra account metas, but not the counter the
+    // hook lists.
+    remaining_accounts.pop();
+    let instruction = escrow_instruction(
+        accounts,
+        remaining_accounts,
+        escrow::instruction::TakeOffer {
+            max_token_b_amount: u64::MAX,
+        },
+    );
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(
+            AccountResolutionError::IncorrectAccount as u32
+        ))
+    );
+
+    let accounts = test.take_offer_accounts(&bob, &offer);
+    let instruction = escrow_instruction(
+        accounts,
+        Vec::new(),
+        escrow::instruction::TakeOffer {
+            max_token_b_amount: u64::MAX,
+        },
+    );
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[bob]),
+        Err(program_error(TransferHookError::IncorrectAccount as u32))
+    );
+
+    assert_eq!(test.hook_invocations(&hooked), 0);
+    assert_eq!(test.token_balance(&hooked, &bob), 100_000_000);
+    assert!(test.offer(&offer).is_some());
+}
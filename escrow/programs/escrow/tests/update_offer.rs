@@ -0,0 +1,146 @@
+mod common;
+
+use anchor_lang::{error::ErrorCode as AnchorErrorCode, prelude::Pubkey};
+use escrow::{error::ErrorCode, OfferUpdated};
+
+use common::*;
+
+// Alice offers 10 USDC for 50 WIF.
+fn setup() -> (Market, Pubkey) {
+    let mut market = Market::new();
+    let offer = market
+        .test
+        .make_offer(
+            &market.alice,
+            &market.usdc,
+            10_000_000,
+            &market.wif,
+            50_000_000,
+        )
+        .unwrap();
+    (market, offer)
+}
+
+#[test]
+fn maker_can_top_up_and_reprice_an_offer() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        },
+        offer,
+    ) = setup();
+
+    test.update_offer(&alice, &offer, 15_000_000, 60_000_000)
+        .unwrap();
+
+    let events = test.events::<OfferUpdated>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].maker, alice);
+    assert_eq!(events[0].token_mint_a, usdc);
+    assert_eq!(events[0].token_mint_b, wif);
+    assert_eq!(events[0].old_token_a_amount, 10_000_000);
+    assert_eq!(events[0].new_token_a_amount, 15_000_000);
+    assert_eq!(events[0].old_token_b_amount, 50_000_000);
+    assert_eq!(events[0].new_token_b_amount, 60_000_000);
+    assert_eq!(test.token_balance(&usdc, &alice), 85_000_000);
+    assert_eq!(test.token_balance(&usdc, &offer), 15_000_000);
+
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &bob), 15_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 60_000_000);
+}
+
+#[test]
+fn maker_can_reduce_the_deposit() {
+    let (
+        Market {
+            mut test,
+            alice,
+            usdc,
+            ..
+        },
+        offer,
+    ) = setup();
+
+    test.update_offer(&alice, &offer, 4_000_000, 20_000_000)
+        .unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &alice), 96_000_000);
+    assert_eq!(test.token_balance(&usdc, &offer), 4_000_000);
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_a_remaining_amount, 4_000_000);
+    assert_eq!(state.token_b_remaining_amount, 20_000_000);
+    assert_eq!(state.token_b_wanted_amount, 20_000_000);
+}
+
+#[test]
+fn update_after_a_partial_fill_only_changes_what_is_left() {
+    let (
+        Market {
+            mut test,
+            alice,
+            bob,
+            usdc,
+            wif,
+            ..
+        },
+        offer,
+    ) = setup();
+
+    test.take_offer_partial(&bob, &offer, 20_000_000).unwrap();
+    test.update_offer(&alice, &offer, 6_000_000, 24_000_000)
+        .unwrap();
+
+    let state = test.offer(&offer).unwrap();
+    assert_eq!(state.token_b_wanted_amount, 44_000_000);
+    assert_eq!(state.token_b_remaining_amount, 24_000_000);
+
+    test.take_offer(&bob, &offer).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_000);
+    assert_eq!(test.token_balance(&wif, &alice), 44_000_000);
+}
+
+#[test]
+fn only_the_maker_can_update_an_offer() {
+    let (
+        Market {
+            mut test,
+            bob,
+            usdc,
+            ..
+        },
+        offer,
+    ) = setup();
+    test.mint_to(&usdc, &bob, 10_000_000);
+
+    assert_eq!(
+        test.update_offer(&bob, &offer, 1_000_000, 1_000_000),
+        Err(program_error(AnchorErrorCode::ConstraintSeeds))
+    );
+}
+
+#[test]
+fn update_offer_rejects_zero_amounts() {
+    let (
+        Market {
+            mut test, alice, ..
+        },
+        offer,
+    ) = setup();
+
+    assert_eq!(
+        test.update_offer(&alice, &offer, 0, 50_000_000),
+        Err(program_error(ErrorCode::InvalidAmount))
+    );
+    assert_eq!(
+        test.update_offer(&alice, &offer, 10_000_000, 0),
+        Err(program_error(ErrorCode::InvalidAmount))
+    );
+}
@@ -0,0 +1,364 @@
+mod common;
+
+use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode, prelude::Pubkey, solana_program::system_program,
+    AccountDeserialize,
+};
+use anchor_spl::associated_token::spl_associated_token_account;
+use escrow::{
+    error::ErrorCode, AmountBasis, OfferTaken, VestedTokensClaimed, Vesting, VestingSchedule,
+};
+
+use common::*;
+
+const SCHEDULE: VestingSchedule = VestingSchedule {
+    cliff_seconds: 100,
+    duration_seconds: 400,
+};
+
+fn vesting_address(offer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vesting", offer.as_ref()], &escrow::ID).0
+}
+
+impl EscrowTest {
+    fn vesting(&self, address: &Pubkey) -> Option<Vesting> {
+        let account = self.runtime.get_account(address)?;
+        Some(Vesting::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    /// Makes a vesting offer with no expiry or taker restriction.
+    fn make_vesting_offer(
+        &mut self,
+        maker: &Pubkey,
+        token_mint_a: &Pubkey,
+        token_a_offered_amount: u64,
+        token_mint_b: &Pubkey,
+        token_b_wanted_amount: u64,
+        vesting: VestingSchedule,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts = self.make_offer_accounts(maker, token_mint_a, token_mint_b, None);
+        let offer = accounts.offer;
+        let args = escrow::instruction::MakeVestingOffer {
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            token_b_amount_basis: AmountBasis::Gross,
+            expires_at: None,
+            allowed_taker: None,
+            vesting,
+        };
+        self.process(accounts, args, &[*maker])?;
+        Ok(offer)
+    }
+
+    fn take_vesting_offer_accounts(
+        &self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> escrow::accounts::TakeVestingOffer {
+        let state = self.offer(offer).unwrap();
+        let treasury = self.config().treasury;
+        let vesting = vesting_address(offer);
+        escrow::accounts::TakeVestingOffer {
+            taker: *taker,
+            maker: state.maker,
+            maker_profile: maker_profile_address(&state.maker),
+            token_mint_a: state.token_mint_a,
+            token_mint_b: state.token_mint_b,
+            taker_token_account_b: self.token_account_address(&state.token_mint_b, taker),
+            maker_token_account_b: self.token_account_address(&state.token_mint_b, &state.maker),
+            config: config_address(),
+            treasury,
+            treasury_token_account_b: self.token_account_address(&state.token_mint_b, &treasury),
+            offer: *offer,
+            vault: self.token_account_address(&state.token_mint_a, offer),
+            vesting,
+            vesting_vault: self.token_account_address(&state.token_mint_a, &vesting),
+            token_mint_a_allowance: self.mint_allowance(&state.token_mint_a),
+            token_mint_b_allowance: self.mint_allowance(&state.token_mint_b),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(&state.token_mint_a),
+            system_program: system_program::ID,
+        }
+    }
+
+    /// Takes `offer` and returns the address of the taker's vesting account.
+    fn take_vesting_offer(
+        &mut self,
+        taker: &Pubkey,
+        offer: &Pubkey,
+    ) -> Result<Pubkey, TransactionError> {
+        let accounts = self.take_vesting_offer_accounts(taker, offer);
+        let vesting = accounts.vesting;
+        self.process(
+            accounts,
+            escrow::instruction::TakeVestingOffer {},
+            &[*taker],
+        )?;
+        Ok(vesting)
+    }
+
+    /// Claims from `vesting` as `beneficiary`, who does not have to be the
+    /// beneficiary stored in it.
+    fn claim_vested(
+        &mut self,
+        beneficiary: &Pubkey,
+        vesting: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let accounts = escrow::accounts::ClaimVested {
+            beneficiary: *beneficiary,
+            mint: *mint,
+            beneficiary_token_account: self.token_account_address(mint, beneficiary),
+            vesting: *vesting,
+            vesting_vault: self.token_account_address(mint, vesting),
+            associated_token_program: spl_associated_token_account::ID,
+            token_program: self.token_program_of(mint),
+            system_program: system_program::ID,
+        };
+        self.process(
+            accounts,
+            escrow::instruction::ClaimVested {},
+            &[*beneficiary],
+        )
+    }
+}
+
+#[test]
+fn taker_claims_token_a_as_it_vests() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_vesting_offer(&alice, &usdc, 12_000_000, &wif, 50_000_000, SCHEDULE)
+        .unwrap();
+    let start_at = test.runtime.clock().unix_timestamp;
+    let vesting = test.take_vesting_offer(&bob, &offer).unwrap();
+
+    // The maker is paid at once, the offer and its vault are gone.
+    assert_eq!(test.token_balance(&wif, &alice), 50_000_000);
+    assert!(test.offer(&offer).is_none());
+    assert!(test
+        .runtime
+        .get_account(&test.token_account_address(&usdc, &offer))
+        .is_none());
+    assert_eq!(
+        test.token_balance(&usdc, &vesting_address(&offer)),
+        12_000_000
+    );
+    assert_eq!(test.events::<OfferTaken>()[0].token_a_amount, 12_000_000);
+    let profile = test.maker_profile(&alice).unwrap();
+    assert_eq!(profile.open_offers, 0);
+    assert_eq!(profile.filled_offers, 1);
+    assert_eq!(profile.volume, 50_000_000);
+
+    let state = test.vesting(&vesting).unwrap();
+    assert_eq!(state.beneficiary, bob);
+    assert_eq!(state.offer, offer);
+    assert_eq!(state.total_amount, 12_000_000);
+    assert_eq!(state.cliff_at, start_at + 100);
+    assert_eq!(state.end_at, start_at + 400);
+
+    // Nothing before the cliff, then a quarter at once.
+    test.runtime.warp_to_timestamp(start_at + 99);
+    assert_eq!(
+        test.claim_vested(&bob, &vesting, &usdc),
+        Err(program_error(ErrorCode::NothingToClaim))
+    );
+    test.runtime.warp_to_timestamp(start_at + 100);
+    test.claim_vested(&bob, &vesting, &usdc).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 3_000_000);
+    assert_eq!(
+        test.claim_vested(&bob, &vesting, &usdc),
+        Err(program_error(ErrorCode::NothingToClaim))
+    );
+
+    test.runtime.warp_to_timestamp(start_at + 300);
+    test.claim_vested(&bob, &vesting, &usdc).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 9_000_000);
+    assert_eq!(test.vesting(&vesting).unwrap().claimed_amount, 9_000_000);
+
+    // The last claim closes the vesting account and its vault.
+    test.runtime.warp_to_timestamp(start_at + 1_000);
+    test.claim_vested(&bob, &vesting, &usdc).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 12_000_000);
+    assert!(test.vesting(&vesting).is_none());
+    assert!(test
+        .runtime
+        .get_account(&test.token_account_address(&usdc, &vesting))
+        .is_none());
+
+    let claim = &test.events::<VestedTokensClaimed>()[0];
+    assert_eq!(claim.token_a_amount, 3_000_000);
+    assert_eq!(claim.claimed_amount, 12_000_000);
+}
+
+#[test]
+fn vesting_offers_can_only_be_taken_with_take_vesting_offer() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let vesting_offer = test
+        .make_vesting_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000, SCHEDULE)
+        .unwrap();
+    let plain_offer = test
+        .make_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000)
+        .unwrap();
+
+    assert_eq!(
+        test.take_offer(&bob, &vesting_offer),
+        Err(program_error(ErrorCode::OfferVests))
+    );
+    assert_eq!(
+        test.take_offer_partial(&bob, &vesting_offer, 10_000_000),
+        Err(program_error(ErrorCode::OfferVests))
+    );
+    assert_eq!(
+        test.take_offers(&bob, &[vesting_offer], 50_000_000),
+        Err(program_error(ErrorCode::OfferVests))
+    );
+    assert_eq!(
+        test.take_vesting_offer(&bob, &plain_offer),
+        Err(program_error(ErrorCode::OfferDoesNotVest))
+    );
+
+    assert_eq!(test.token_balance(&wif, &bob), 100_000_000);
+    assert!(test.offer(&vesting_offer).is_some());
+    assert!(test.offer(&plain_offer).is_some());
+}
+
+#[test]
+fn tokens_sent_to_the_vesting_vault_go_out_with_the_last_claim() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_vesting_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000, SCHEDULE)
+        .unwrap();
+    let vesting = test.take_vesting_offer(&bob, &offer).unwrap();
+    test.mint_to(&usdc, &vesting, 1);
+
+    let end_at = test.vesting(&vesting).unwrap().end_at;
+    test.runtime.warp_to_timestamp(end_at);
+    test.claim_vested(&bob, &vesting, &usdc).unwrap();
+
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_001);
+    assert!(test.vesting(&vesting).is_none());
+    let claim = &test.events::<VestedTokensClaimed>()[0];
+    assert_eq!(claim.token_a_amount, 10_000_001);
+    assert_eq!(claim.claimed_amount, 10_000_000);
+}
+
+#[test]
+fn a_vesting_vault_created_before_the_take_does_not_block_it() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_vesting_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000, SCHEDULE)
+        .unwrap();
+    // Anyone can create the associated token account of the vesting PDA.
+    let vesting = vesting_address(&offer);
+    test.mint_to(&usdc, &vesting, 1);
+
+    test.take_vesting_offer(&bob, &offer).unwrap();
+    assert_eq!(test.vesting(&vesting).unwrap().total_amount, 10_000_001);
+
+    let end_at = test.vesting(&vesting).unwrap().end_at;
+    test.runtime.warp_to_timestamp(end_at);
+    test.claim_vested(&bob, &vesting, &usdc).unwrap();
+    assert_eq!(test.token_balance(&usdc, &bob), 10_000_001);
+}
+
+#[test]
+fn only_the_taker_can_claim() {
+    let Market {
+        mut test,
+        alice,
+        bob,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    let offer = test
+        .make_vesting_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000, SCHEDULE)
+        .unwrap();
+    let vesting = test.take_vesting_offer(&bob, &offer).unwrap();
+    let end_at = test.vesting(&vesting).unwrap().end_at;
+    test.runtime.warp_to_timestamp(end_at);
+
+    assert_eq!(
+        test.claim_vested(&alice, &vesting, &usdc),
+        Err(program_error(AnchorErrorCode::ConstraintHasOne))
+    );
+    assert_eq!(test.vesting(&vesting).unwrap().claimed_amount, 0);
+}
+
+#[test]
+fn schedule_needs_a_duration_and_a_cliff_within_it() {
+    let Market {
+        mut test,
+        alice,
+        usdc,
+        wif,
+        ..
+    } = Market::new();
+
+    for schedule in [
+        VestingSchedule {
+            cliff_seconds: 0,
+            duration_seconds: 0,
+        },
+        VestingSchedule {
+            cliff_seconds: 500,
+            duration_seconds: 400,
+        },
+        VestingSchedule {
+            cliff_seconds: -1,
+            duration_seconds: 400,
+        },
+    ] {
+        assert_eq!(
+            test.make_vesting_offer(&alice, &usdc, 10_000_000, &wif, 50_000_000, schedule),
+            Err(program_error(ErrorCode::InvalidVestingSchedule))
+        );
+    }
+
+    // No cliff vests from the take on.
+    test.make_vesting_offer(
+        &alice,
+        &usdc,
+        10_000_000,
+        &wif,
+        50_000_000,
+        VestingSchedule {
+            cliff_seconds: 0,
+            duration_seconds: 1,
+        },
+    )
+    .unwrap();
+}
@@ -0,0 +1,65 @@
+//! A price feed whose price is set by hand.
+//!
+//! Stands in for a real oracle in local tests of oracle-priced escrow offers.
+//! The authority of a feed can publish any price, so a feed from this program
+//! is only as trustworthy as whoever created it.
+
+use anchor_lang::prelude::*;
+
+declare_id!("vWLdGFRuMUXJ3ymCd9UUQRjmvcw56uUGp84X3Mvye3k");
+
+#[program]
+pub mod mock_feed {
+    use super::*;
+
+    pub fn initialize_feed(
+        context: Context<InitializeFeed>,
+        price: i64,
+        exponent: i32,
+    ) -> Result<()> {
+        context.accounts.feed.set_inner(PriceFeed {
+            authority: context.accounts.authority.key(),
+            price,
+            exponent,
+            publish_time: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn set_price(context: Context<SetPrice>, price: i64, exponent: i32) -> Result<()> {
+        let feed = &mut context.accounts.feed;
+        feed.price = price;
+        feed.exponent = exponent;
+        feed.publish_time = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+}
+
+// One whole token A is worth `price * 10^exponent` whole tokens B.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub authority: Pubkey,
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeed<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(init, payer = authority, space = 8 + PriceFeed::INIT_SPACE)]
+    pub feed: Account<'info, PriceFeed>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, has_one = authority)]
+    pub feed: Account<'info, PriceFeed>,
+}
@@ -37,6 +37,10 @@ import {
 } from "@solana/spl-token";
 import { randomBytes } from "crypto";
 
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
 import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
 
 const TOKEN_PROGRAM: typeof TOKEN_2022_PROGRAM_ID | typeof TOKEN_PROGRAM_ID =
@@ -143,7 +147,12 @@ describe("escrow", () => {
 
   const program = anchor.workspace.Escrow as Program<Escrow>;
 
-  const [alice, bob, usdcMint, wifMint] = makeKeypairs(4);
+  const [alice, bob, usdcMint, wifMint, treasury] = makeKeypairs(5);
+
+  const [programDataAddress] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
 
   const [aliceUsdcAccount, aliceWifAccount, bobUsdcAccount, bobWifAccount] = [
     alice,
@@ -159,17 +168,20 @@ describe("escrow", () => {
     )
   );
 
-  // Pick a random ID for the new offer.
-  const offerId = getRandomBigNumber();
+  // Offer IDs come from the maker's profile, so Alice's first offer is 0.
+  const offerId = new BN(0);
 
   // Creates Alice and Bob accounts, 2 token mints, and associated token
   // accounts for both tokens for both users.
   beforeAll(async () => {
     // global.console = require('console');
 
+    // The treasury is funded too: it has to hold the rent-exempt minimum to
+    // be accepted by `initialize`.
     const giveAliceAndBobSolIxs: Array<TransactionInstruction> = [
       alice,
       bob,
+      treasury,
     ].map((owner) =>
       SystemProgram.transfer({
         fromPubkey: provider.publicKey,
@@ -216,6 +228,20 @@ describe("escrow", () => {
       usdcMint,
       wifMint,
     ]);
+
+    // The provider wallet deployed the program, so it is the upgrade authority
+    // and becomes the config admin.  The fee starts at zero and is raised in
+    // the fee test at the end.
+    const initializeTxSig = await program.methods
+      .initialize(0)
+      .accountsPartial({
+        admin: provider.publicKey,
+        treasury: treasury.publicKey,
+        programData: programDataAddress,
+      })
+      .rpc();
+
+    await confirmTransaction(connection, initializeTxSig);
   });
 
   // afterAll(() => {
@@ -224,19 +250,49 @@ describe("escrow", () => {
 
   const makeOfferTx = async (
     maker: Keypair,
-    offerId: BN,
     offeredTokenMint: PublicKey,
     offeredAmount: BN,
     wantedTokenMint: PublicKey,
-    wantedAmount: BN
+    wantedAmount: BN,
+    expiresAt: BN | null = null,
+    allowedTaker: PublicKey | null = null
   ): Promise<{
     offerAddress: PublicKey;
     vaultAddress: PublicKey;
   }> => {
+    // The new offer takes the next ID from the maker's profile, which only
+    // exists once the maker has made an offer.
+    const [makerProfileAddress, _makerProfileBump] =
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("maker_profile"), maker.publicKey.toBuffer()],
+        program.programId
+      );
+    const makerProfile = await program.account.makerProfile.fetchNullable(
+      makerProfileAddress
+    );
+    const offerId = makerProfile?.nextOfferId ?? new BN(0);
+
+    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        maker.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
     const transactionSignature = await program.methods
-      .makeOffer(offerId, offeredAmount, wantedAmount)
-      .accounts({
+      .makeOffer(
+        offeredAmount,
+        wantedAmount,
+        { gross: {} },
+        expiresAt,
+        allowedTaker
+      )
+      .accountsPartial({
         maker: maker.publicKey,
+        makerProfile: makerProfileAddress,
+        offer: offerAddress,
         tokenMintA: offeredTokenMint,
         tokenMintB: wantedTokenMint,
         // As the `token_program` account is specified as
@@ -251,25 +307,19 @@ describe("escrow", () => {
         // expected to reference the same programs for all the `makeOffer`
         // invocations.
         tokenProgram: TOKEN_PROGRAM,
+        priceFeed: null,
+        tokenMintAAllowance: null,
+        tokenMintBAllowance: null,
       })
       .signers([maker])
       .rpc();
 
     await confirmTransaction(connection, transactionSignature);
 
-    // Both `offer` address and the `vault` address accounts are computed based
-    // on the other provided account addresses, and so we do not need to provide
-    // them explicitly in the `makeOffer()` account call above.  But we compute
-    // them here and return for convenience.
-
-    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("offer"),
-        maker.publicKey.toBuffer(),
-        offerId.toArrayLike(Buffer, "le", 8),
-      ],
-      program.programId
-    );
+    // The `vault` address is computed based on the other provided account
+    // addresses, and so we do not need to provide it explicitly in the
+    // `makeOffer()` account call above.  But we compute it here and return it
+    // for convenience.
 
     const vaultAddress = getAssociatedTokenAddressSync(
       offeredTokenMint,
@@ -281,6 +331,8 @@ describe("escrow", () => {
     return { offerAddress, vaultAddress };
   };
 
+  // The offers here are fixed-price, so the taker does not need to bound the
+  // quote they are taken at.
   const takeOfferTx = async (
     offerAddress: PublicKey,
     taker: Keypair,
@@ -301,10 +353,15 @@ describe("escrow", () => {
     // >;
 
     const transactionSignature = await program.methods
-      .takeOffer()
+      .takeOffer(new BN("18446744073709551615"))
       .accounts({
         taker: taker.publicKey,
         offer: offerAddress,
+        // Only oracle-priced offers read a price feed.
+        priceFeed: null,
+        // Only mints with risky Token-2022 extensions need an allowance.
+        tokenMintAAllowance: null,
+        tokenMintBAllowance: null,
         // See note in the `makeOfferTx` on why this program address is provided
         // and the rest are not.
         tokenProgram: TOKEN_PROGRAM,
@@ -315,6 +372,69 @@ describe("escrow", () => {
     await confirmTransaction(connection, transactionSignature);
   };
 
+  const takeOfferPartialTx = async (
+    offerAddress: PublicKey,
+    taker: Keypair,
+    tokenBAmount: BN,
+  ): Promise<void> => {
+    const transactionSignature = await program.methods
+      .takeOfferPartial(tokenBAmount, new BN(0))
+      .accounts({
+        taker: taker.publicKey,
+        offer: offerAddress,
+        // Only oracle-priced offers read a price feed.
+        priceFeed: null,
+        // Only mints with risky Token-2022 extensions need an allowance.
+        tokenMintAAllowance: null,
+        tokenMintBAllowance: null,
+        // See note in the `makeOfferTx` on why this program address is provided
+        // and the rest are not.
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([taker])
+      .rpc();
+
+    await confirmTransaction(connection, transactionSignature);
+  };
+
+  const refundOfferTx = async (
+    offerAddress: PublicKey,
+    maker: Keypair,
+  ): Promise<void> => {
+    const transactionSignature = await program.methods
+      .refundOffer()
+      .accountsPartial({
+        maker: maker.publicKey,
+        offer: offerAddress,
+        // See note in the `makeOfferTx` on why this program address is provided
+        // and the rest are not.
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([maker])
+      .rpc();
+
+    await confirmTransaction(connection, transactionSignature);
+  };
+
+  const closeExpiredOfferTx = async (
+    offerAddress: PublicKey,
+    caller: Keypair,
+  ): Promise<void> => {
+    const transactionSignature = await program.methods
+      .closeExpiredOffer()
+      .accounts({
+        payer: caller.publicKey,
+        offer: offerAddress,
+        // See note in the `makeOfferTx` on why this program address is provided
+        // and the rest are not.
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([caller])
+      .rpc();
+
+    await confirmTransaction(connection, transactionSignature);
+  };
+
   test("Offer created by Alice, vault holds the offer tokens", async () => {
     const offeredUsdc = new BN(10_000_000);
     const wantedWif = new BN(100_000_000);
@@ -323,7 +443,6 @@ describe("escrow", () => {
 
     const { offerAddress, vaultAddress } = await makeOfferTx(
       alice,
-      offerId,
       usdcMint.publicKey,
       offeredUsdc,
       wifMint.publicKey,
@@ -373,4 +492,181 @@ describe("escrow", () => {
     expect(await getTokenBalance(bobUsdcAccount)).toEqual(new BN(30_000_000));
     expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(200_000_000));
   });
+
+  test("Offer refunded to Alice, vault and offer accounts are closed", async () => {
+    const offeredUsdc = new BN(10_000_000);
+    const wantedWif = new BN(50_000_000);
+
+    const getTokenBalance = getTokenBalanceOn(connection);
+
+    const { offerAddress, vaultAddress } = await makeOfferTx(
+      alice,
+      usdcMint.publicKey,
+      offeredUsdc,
+      wifMint.publicKey,
+      wantedWif
+    );
+
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(new BN(80_000_000));
+    expect(await getTokenBalance(vaultAddress)).toEqual(offeredUsdc);
+
+    await refundOfferTx(offerAddress, alice);
+
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(new BN(90_000_000));
+    expect(await connection.getAccountInfo(offerAddress)).toBeNull();
+    expect(await connection.getAccountInfo(vaultAddress)).toBeNull();
+  });
+
+  test("Offer filled by Bob in two parts, maker is never paid below the price", async () => {
+    const offeredUsdc = new BN(10_000_000);
+    const wantedWif = new BN(30_000_000);
+
+    const getTokenBalance = getTokenBalanceOn(connection);
+
+    const { offerAddress, vaultAddress } = await makeOfferTx(
+      alice,
+      usdcMint.publicKey,
+      offeredUsdc,
+      wifMint.publicKey,
+      wantedWif
+    );
+
+    // A third of the wanted tokens releases a third of the offered tokens,
+    // rounded down.
+    await takeOfferPartialTx(offerAddress, bob, new BN(10_000_000));
+
+    expect(await getTokenBalance(vaultAddress)).toEqual(new BN(6_666_667));
+    expect(await getTokenBalance(bobUsdcAccount)).toEqual(new BN(33_333_333));
+    expect(await getTokenBalance(aliceWifAccount)).toEqual(new BN(115_000_000));
+
+    const offerAccount = await program.account.offer.fetch(offerAddress);
+    expect(offerAccount.tokenARemainingAmount).toEqual(new BN(6_666_667));
+    expect(offerAccount.tokenBRemainingAmount).toEqual(new BN(20_000_000));
+
+    // The last fill releases whatever is left in the vault.
+    await takeOfferPartialTx(offerAddress, bob, new BN(20_000_000));
+
+    expect(await getTokenBalance(bobUsdcAccount)).toEqual(new BN(40_000_000));
+    expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(170_000_000));
+    expect(await getTokenBalance(aliceWifAccount)).toEqual(new BN(135_000_000));
+    expect(await connection.getAccountInfo(offerAddress)).toBeNull();
+    expect(await connection.getAccountInfo(vaultAddress)).toBeNull();
+  });
+
+  test("Expired offer cannot be taken, anyone can return it to Alice", async () => {
+    const offeredUsdc = new BN(10_000_000);
+    const wantedWif = new BN(10_000_000);
+
+    const getTokenBalance = getTokenBalanceOn(connection);
+
+    const expiresAt = new BN(Math.floor(Date.now() / 1000) + 2);
+    const { offerAddress, vaultAddress } = await makeOfferTx(
+      alice,
+      usdcMint.publicKey,
+      offeredUsdc,
+      wifMint.publicKey,
+      wantedWif,
+      expiresAt
+    );
+
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(new BN(70_000_000));
+
+    await expect(closeExpiredOfferTx(offerAddress, bob)).rejects.toThrow(
+      "OfferNotExpired"
+    );
+
+    // Let the validator clock move past `expiresAt`.
+    await new Promise((resolve) => setTimeout(resolve, 5_000));
+
+    await expect(takeOfferTx(offerAddress, bob)).rejects.toThrow(
+      "OfferExpired"
+    );
+
+    await closeExpiredOfferTx(offerAddress, bob);
+
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(new BN(80_000_000));
+    expect(await connection.getAccountInfo(offerAddress)).toBeNull();
+    expect(await connection.getAccountInfo(vaultAddress)).toBeNull();
+  }, 30_000);
+
+  test("Offer reserved for Bob cannot be taken by anyone else", async () => {
+    const offeredUsdc = new BN(10_000_000);
+    const wantedWif = new BN(10_000_000);
+
+    const getTokenBalance = getTokenBalanceOn(connection);
+
+    const { offerAddress } = await makeOfferTx(
+      alice,
+      usdcMint.publicKey,
+      offeredUsdc,
+      wifMint.publicKey,
+      wantedWif,
+      null,
+      bob.publicKey
+    );
+
+    await expect(takeOfferTx(offerAddress, alice)).rejects.toThrow(
+      "TakerNotAllowed"
+    );
+
+    await takeOfferTx(offerAddress, bob);
+
+    expect(await getTokenBalance(bobUsdcAccount)).toEqual(new BN(50_000_000));
+    expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(160_000_000));
+    expect(await connection.getAccountInfo(offerAddress)).toBeNull();
+  });
+
+  test("Protocol fee is skimmed from the token B leg into the treasury", async () => {
+    const offeredUsdc = new BN(10_000_000);
+    const wantedWif = new BN(10_000_000);
+
+    const getTokenBalance = getTokenBalanceOn(connection);
+
+    await expect(
+      program.methods
+        .updateFee(100)
+        .accountsPartial({ admin: bob.publicKey })
+        .signers([bob])
+        .rpc()
+    ).rejects.toThrow("ConstraintHasOne");
+
+    await expect(
+      program.methods
+        .updateFee(1_001)
+        .accountsPartial({ admin: provider.publicKey })
+        .rpc()
+    ).rejects.toThrow("FeeTooHigh");
+
+    // 1%
+    await program.methods
+      .updateFee(100)
+      .accountsPartial({ admin: provider.publicKey })
+      .rpc();
+
+    const { offerAddress } = await makeOfferTx(
+      alice,
+      usdcMint.publicKey,
+      offeredUsdc,
+      wifMint.publicKey,
+      wantedWif
+    );
+
+    await takeOfferTx(offerAddress, bob);
+
+    const treasuryWifAccount = getAssociatedTokenAddressSync(
+      wifMint.publicKey,
+      treasury.publicKey,
+      false,
+      TOKEN_PROGRAM
+    );
+
+    expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(150_000_000));
+    expect(await getTokenBalance(aliceWifAccount)).toEqual(new BN(154_900_000));
+    expect(await getTokenBalance(treasuryWifAccount)).toEqual(new BN(100_000));
+
+    await program.methods
+      .updateFee(0)
+      .accountsPartial({ admin: provider.publicKey })
+      .rpc();
+  });
 });
\ No newline at end of file
@@ -415,3 +415,26 @@ fn test_create_metadata() {
     .unwrap();
     dbg!(sig);
 }
+
+use escrow_client::EscrowClient;
+
+/// Takes the escrow offer at `offer` in full, paying with the taker's token B
+/// and failing if that would cost more than `max_token_b_amount`.
+pub fn take_escrow_offer(
+    escrow_client: &EscrowClient<RpcClient>,
+    taker_keypair: &Keypair,
+    offer: &Pubkey,
+    max_token_b_amount: u64,
+) -> core::result::Result<Signature, Box<dyn Error>> {
+    let taker_pubkey = taker_keypair.encodable_pubkey();
+    let instruction = escrow_client.take_offer(&taker_pubkey, offer, max_token_b_amount)?;
+    let rpc_client = escrow_client.source();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&taker_pubkey),
+        &[taker_keypair],
+        rpc_client.get_latest_blockhash()?,
+    );
+    let sig = rpc_client.send_and_confirm_transaction(&transaction)?;
+    Ok(sig)
+}